@@ -0,0 +1,33 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use simple_xmlparser::{Document, Element};
+
+// About 1MB of records with attributes, references and nesting.
+fn document() -> String {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n<catalog>\n");
+    let mut i = 0;
+    while xml.len() < 1 << 20 {
+        xml.push_str(&format!(
+            "  <book id=\"bk{0}\" lang=\"en\">\n    <title>Volume {0} &amp; more</title>\n    \
+             <price currency=\"EUR\">{0}.95</price>\n    <tags><tag>a</tag><tag>b</tag></tags>\n  \
+             </book>\n",
+            i
+        ));
+        i += 1;
+    }
+    xml.push_str("</catalog>\n");
+    xml
+}
+
+fn parse(c: &mut Criterion) {
+    let xml = document();
+    let body = &xml[xml.find("<catalog").unwrap()..];
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(xml.len() as u64));
+    group.sample_size(20);
+    group.bench_function("document", |b| b.iter(|| Document::parse(&xml).unwrap()));
+    group.bench_function("element", |b| b.iter(|| body.parse::<Element>().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
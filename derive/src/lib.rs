@@ -0,0 +1,213 @@
+//! `#[derive(FromXml, ToXml)]` for structs with named fields. Every field
+//! takes one annotation:
+//!
+//! - `#[xml(attr)]` or `#[xml(attr = "name")]`: an attribute, converted with
+//!   `FromStr` and `Display`;
+//! - `#[xml(child = "name")]`: a child element of a type that is itself
+//!   `FromXml`/`ToXml`;
+//! - `#[xml(text)]`: the element's text, converted like an attribute.
+//!
+//! An `Option` field may be absent, and a `Vec` child field collects every
+//! child of that name. The element is named after the struct unless the
+//! struct has `#[xml(rename = "name")]`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Fields, GenericArgument, LitStr, PathArguments, Type,
+};
+
+#[proc_macro_derive(FromXml, attributes(xml))]
+pub fn derive_from_xml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, from_xml).unwrap_or_else(|error| error.to_compile_error().into())
+}
+
+#[proc_macro_derive(ToXml, attributes(xml))]
+pub fn derive_to_xml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, to_xml).unwrap_or_else(|error| error.to_compile_error().into())
+}
+
+enum Kind {
+    Attr(String),
+    Child(String),
+    Text,
+}
+
+// How many values a field holds, going by its outermost type.
+enum Arity {
+    One,
+    Optional,
+    Many,
+}
+
+struct Field {
+    ident: syn::Ident,
+    kind: Kind,
+    arity: Arity,
+}
+
+fn expand(
+    input: &DeriveInput,
+    generate: fn(&DeriveInput, &str, &[Field]) -> TokenStream2,
+) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(error(input, "expected a struct with named fields")),
+        },
+        _ => return Err(error(input, "expected a struct with named fields")),
+    };
+    let mut name = input.ident.to_string();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("xml"))
+    {
+        attr.parse_nested_meta(|meta| match meta.path.is_ident("rename") {
+            true => {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            }
+            false => Err(meta.error("expected `rename`")),
+        })?;
+    }
+    let fields = fields.iter().map(field).collect::<syn::Result<Vec<_>>>()?;
+    Ok(generate(input, &name, &fields).into())
+}
+
+fn field(field: &syn::Field) -> syn::Result<Field> {
+    let ident = field
+        .ident
+        .clone()
+        .ok_or_else(|| error(field, "expected a named field"))?;
+    let mut kind = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("xml"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("attr") {
+                let name = match meta.input.is_empty() || meta.input.peek(syn::Token![,]) {
+                    true => ident.to_string(),
+                    false => meta.value()?.parse::<LitStr>()?.value(),
+                };
+                kind = Some(Kind::Attr(name));
+            } else if meta.path.is_ident("child") {
+                kind = Some(Kind::Child(meta.value()?.parse::<LitStr>()?.value()));
+            } else if meta.path.is_ident("text") {
+                kind = Some(Kind::Text);
+            } else {
+                return Err(meta.error("expected `attr`, `child` or `text`"));
+            }
+            Ok(())
+        })?;
+    }
+    let kind = kind.ok_or_else(|| {
+        error(
+            field,
+            "expected #[xml(attr)], #[xml(child = \"...\")] or #[xml(text)]",
+        )
+    })?;
+    let arity = match wrapper(&field.ty) {
+        Some("Option") => Arity::Optional,
+        Some("Vec") if matches!(kind, Kind::Child(_)) => Arity::Many,
+        _ => Arity::One,
+    };
+    if matches!((&kind, &arity), (Kind::Text, Arity::Optional)) {
+        return Err(error(field, "#[xml(text)] cannot be optional"));
+    }
+    Ok(Field { ident, kind, arity })
+}
+
+// The name of `Option` or `Vec` if `ty` is one of them with a type argument.
+fn wrapper(ty: &Type) -> Option<&'static str> {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+        _ => return None,
+    };
+    let argument = match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) if arguments.args.len() == 1 => &arguments.args[0],
+        _ => return None,
+    };
+    if !matches!(argument, GenericArgument::Type(_)) {
+        return None;
+    }
+    match segment.ident.to_string().as_str() {
+        "Option" => Some("Option"),
+        "Vec" => Some("Vec"),
+        _ => None,
+    }
+}
+
+fn from_xml(input: &DeriveInput, _: &str, fields: &[Field]) -> TokenStream2 {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let values = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let value = match (&field.kind, &field.arity) {
+            (Kind::Attr(name), Arity::Optional) => {
+                quote!(::simple_xmlparser::__private::optional_attr(el, #name)?)
+            }
+            (Kind::Attr(name), _) => quote!(::simple_xmlparser::__private::attr(el, #name)?),
+            (Kind::Child(name), Arity::One) => {
+                quote!(::simple_xmlparser::__private::child(el, #name)?)
+            }
+            (Kind::Child(name), Arity::Optional) => {
+                quote!(::simple_xmlparser::__private::optional_child(el, #name)?)
+            }
+            (Kind::Child(name), Arity::Many) => {
+                quote!(::simple_xmlparser::__private::children(el, #name)?)
+            }
+            (Kind::Text, _) => quote!(::simple_xmlparser::__private::text(el)?),
+        };
+        quote!(#ident: #value)
+    });
+    quote! {
+        impl #impl_generics ::simple_xmlparser::FromXml for #ident #ty_generics #where_clause {
+            fn from_xml(
+                el: &::simple_xmlparser::Element,
+            ) -> ::core::result::Result<Self, ::simple_xmlparser::Error> {
+                ::core::result::Result::Ok(#ident { #(#values),* })
+            }
+        }
+    }
+}
+
+fn to_xml(input: &DeriveInput, name: &str, fields: &[Field]) -> TokenStream2 {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let statements = fields.iter().map(|field| {
+        let ident = &field.ident;
+        let values = match field.arity {
+            Arity::One => quote!(::core::iter::once(&self.#ident)),
+            Arity::Optional | Arity::Many => quote!(self.#ident.iter()),
+        };
+        let add = match &field.kind {
+            Kind::Attr(name) => {
+                quote!(::simple_xmlparser::__private::set_attr(&mut el, #name, value))
+            }
+            Kind::Child(name) => {
+                quote!(::simple_xmlparser::__private::push_child(&mut el, #name, value))
+            }
+            Kind::Text => quote!(::simple_xmlparser::__private::push_text(&mut el, value)),
+        };
+        quote!(for value in #values { #add; })
+    });
+    quote! {
+        impl #impl_generics ::simple_xmlparser::ToXml for #ident #ty_generics #where_clause {
+            fn to_xml(&self) -> ::simple_xmlparser::Element {
+                let mut el = ::simple_xmlparser::Element::new(#name);
+                #(#statements)*
+                el
+            }
+        }
+    }
+}
+
+fn error<T: quote::ToTokens>(tokens: T, message: &str) -> syn::Error {
+    syn::Error::new_spanned(tokens, message)
+}
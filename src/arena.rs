@@ -0,0 +1,308 @@
+use crate::prelude::*;
+use crate::{Element, Node};
+
+/// A handle to a node of a `Tree`. Handles stay valid while the node moves
+/// around the tree or out of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(usize);
+
+/// What a node of a `Tree` holds, apart from its place in the tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeData {
+    Element {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    Text(String),
+    Comment(String),
+    ProcessingInstruction {
+        target: String,
+        data: String,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    data: NodeData,
+    parent: Option<NodeId>,
+    first_child: Option<NodeId>,
+    last_child: Option<NodeId>,
+    previous: Option<NodeId>,
+    next: Option<NodeId>,
+}
+
+/// An element tree kept in one `Vec`, linked by `NodeId`s, for editing
+/// where `Element` would need searching or copying: finding a parent,
+/// moving a subtree and detaching one all take constant time. Detached
+/// nodes keep their slots, and their handles, until the tree is turned back
+/// into an `Element`.
+///
+/// `Document` is not backed by a `Tree` yet: it still holds an `Element`
+/// root, because `root` and `root_mut` hand out `&Element`, and the
+/// serializers, queries and validators all walk `Element`s. Until that
+/// moves, build a tree from a document's root and put `into_element` back
+/// with `root_mut`; both moves take the nodes without copying them.
+#[derive(Clone, Debug)]
+pub struct Tree {
+    slots: Vec<Slot>,
+}
+
+impl Tree {
+    /// A tree holding `root` and its descendants, which are moved in
+    /// without copying.
+    pub fn new(root: Element) -> Tree {
+        let mut tree = Tree { slots: Vec::new() };
+        // Children are pushed last first, so each parent gets them appended
+        // in order.
+        let mut stack = vec![(None, Node::Element(root))];
+        while let Some((parent, node)) = stack.pop() {
+            let (data, children) = match node {
+                Node::Element(el) => (
+                    NodeData::Element {
+                        name: el.name,
+                        attributes: el.attributes,
+                    },
+                    el.children,
+                ),
+                Node::Text(text) => (NodeData::Text(text), Vec::new()),
+                Node::Comment(comment) => (NodeData::Comment(comment), Vec::new()),
+                Node::ProcessingInstruction { target, data } => {
+                    (NodeData::ProcessingInstruction { target, data }, Vec::new())
+                }
+            };
+            let id = tree.create(data);
+            if let Some(parent) = parent {
+                tree.link_last(parent, id);
+            }
+            stack.extend(children.into_iter().rev().map(|child| (Some(id), child)));
+        }
+        tree
+    }
+
+    /// The element the tree was made from. It can't be moved or detached.
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn get(&self, id: NodeId) -> &NodeData {
+        &self.slots[id.0].data
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> &mut NodeData {
+        &mut self.slots[id.0].data
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.slots[id.0].parent
+    }
+
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> {
+        self.slots[id.0].first_child
+    }
+
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> {
+        self.slots[id.0].last_child
+    }
+
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.slots[id.0].next
+    }
+
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        self.slots[id.0].previous
+    }
+
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        core::iter::successors(self.first_child(id), move |&child| self.next_sibling(child))
+    }
+
+    /// The parent, its parent and so on up to the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        core::iter::successors(self.parent(id), move |&parent| self.parent(parent))
+    }
+
+    /// A node outside the tree, to be placed with `append_child` or
+    /// `insert_before`.
+    pub fn create(&mut self, data: NodeData) -> NodeId {
+        self.slots.push(Slot {
+            data,
+            parent: None,
+            first_child: None,
+            last_child: None,
+            previous: None,
+            next: None,
+        });
+        NodeId(self.slots.len() - 1)
+    }
+
+    /// Moves `child`, with its descendants, to the end of `parent`'s
+    /// children.
+    ///
+    /// Panics if `parent` is not an element, or is `child` or inside it.
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.check_move(parent, child);
+        self.detach(child);
+        self.link_last(parent, child);
+    }
+
+    // Makes `child`, which must be detached, the last child of `parent`.
+    fn link_last(&mut self, parent: NodeId, child: NodeId) {
+        let previous = self.slots[parent.0].last_child.replace(child);
+        match previous {
+            Some(previous) => self.slots[previous.0].next = Some(child),
+            None => self.slots[parent.0].first_child = Some(child),
+        }
+        let slot = &mut self.slots[child.0];
+        slot.parent = Some(parent);
+        slot.previous = previous;
+    }
+
+    /// Moves `node`, with its descendants, to just before `sibling`.
+    ///
+    /// Panics if `sibling` has no parent, or `node` is `sibling`'s parent or
+    /// an ancestor of it.
+    pub fn insert_before(&mut self, sibling: NodeId, node: NodeId) {
+        let parent = self.parent(sibling).expect("sibling has no parent");
+        if node == sibling {
+            return;
+        }
+        self.check_move(parent, node);
+        self.detach(node);
+        let previous = self.slots[sibling.0].previous.replace(node);
+        match previous {
+            Some(previous) => self.slots[previous.0].next = Some(node),
+            None => self.slots[parent.0].first_child = Some(node),
+        }
+        let slot = &mut self.slots[node.0];
+        slot.parent = Some(parent);
+        slot.previous = previous;
+        slot.next = Some(sibling);
+    }
+
+    /// Takes `id`, with its descendants, out of the tree. It can be placed
+    /// again later; if not, it is dropped with the tree.
+    pub fn detach(&mut self, id: NodeId) {
+        let slot = &mut self.slots[id.0];
+        let (parent, previous, next) = (slot.parent.take(), slot.previous.take(), slot.next.take());
+        let parent = match parent {
+            Some(parent) => parent,
+            None => return,
+        };
+        match previous {
+            Some(previous) => self.slots[previous.0].next = next,
+            None => self.slots[parent.0].first_child = next,
+        }
+        match next {
+            Some(next) => self.slots[next.0].previous = previous,
+            None => self.slots[parent.0].last_child = previous,
+        }
+    }
+
+    /// A copy of the subtree at `id` as an `Element`, if `id` is one.
+    pub fn to_element(&self, id: NodeId) -> Option<Element> {
+        self.build(id, |id| self.get(id).clone())
+    }
+
+    /// The tree as an `Element`, leaving out detached nodes.
+    pub fn into_element(mut self) -> Element {
+        let take = |data: &mut NodeData| core::mem::replace(data, NodeData::Text(String::new()));
+        let mut data: Vec<_> = self
+            .slots
+            .iter_mut()
+            .map(|slot| take(&mut slot.data))
+            .collect();
+        self.build(self.root(), |id| take(&mut data[id.0]))
+            .expect("the root is an element")
+    }
+
+    fn check_move(&self, parent: NodeId, child: NodeId) {
+        assert!(child != self.root(), "the root can't be moved");
+        assert!(
+            matches!(self.get(parent), NodeData::Element { .. }),
+            "only elements have children"
+        );
+        assert!(
+            child != parent && self.ancestors(parent).all(|id| id != child),
+            "a node can't be moved inside itself"
+        );
+    }
+
+    // Assembles the subtree at `id` bottom up with an explicit stack, taking
+    // each node's data from `data`.
+    fn build<F>(&self, id: NodeId, mut data: F) -> Option<Element>
+    where
+        F: FnMut(NodeId) -> NodeData,
+    {
+        let element = |data: NodeData| match data {
+            NodeData::Element { name, attributes } => Some(Element {
+                name,
+                attributes,
+                children: Vec::new(),
+            }),
+            _ => None,
+        };
+        let mut stack = vec![(element(data(id))?, self.first_child(id))];
+        loop {
+            let depth = stack.len() - 1;
+            match stack[depth].1 {
+                Some(child) => {
+                    stack[depth].1 = self.next_sibling(child);
+                    let node = match data(child) {
+                        NodeData::Text(text) => Node::Text(text),
+                        NodeData::Comment(comment) => Node::Comment(comment),
+                        NodeData::ProcessingInstruction { target, data } => {
+                            Node::ProcessingInstruction { target, data }
+                        }
+                        el => {
+                            stack.push((element(el)?, self.first_child(child)));
+                            continue;
+                        }
+                    };
+                    stack[depth].0.children.push(node);
+                }
+                None => {
+                    let (el, _) = stack.pop()?;
+                    match stack.last_mut() {
+                        Some((parent, _)) => parent.children.push(Node::Element(el)),
+                        None => return Some(el),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl From<Element> for Tree {
+    fn from(root: Element) -> Tree {
+        Tree::new(root)
+    }
+}
+
+#[test]
+fn arena_tree() {
+    let root: Element = "<list><a>1</a><!--c--><b><c/></b></list>".parse().unwrap();
+    let mut tree = Tree::new(root.clone());
+    let list = tree.root();
+    let ids: Vec<_> = tree.children(list).collect();
+    assert_eq!(3, ids.len());
+    let (a, comment, b) = (ids[0], ids[1], ids[2]);
+    let c = tree.first_child(b).unwrap();
+    assert_eq!(vec![b, list], tree.ancestors(c).collect::<Vec<_>>());
+    assert_eq!(Some(comment), tree.previous_sibling(b));
+    assert_eq!(Some(root.clone()), tree.to_element(list));
+
+    tree.detach(comment);
+    tree.append_child(c, a);
+    tree.insert_before(b, c);
+    let text = tree.create(NodeData::Text("new".to_string()));
+    tree.append_child(list, text);
+    if let NodeData::Element { name, .. } = tree.get_mut(b) {
+        *name = "d".to_string();
+    }
+    assert_eq!(Some(list), tree.parent(c));
+    assert_eq!(None, tree.parent(comment));
+    assert_eq!(
+        "<list><c><a>1</a></c><d/>new</list>",
+        tree.into_element().to_string()
+    );
+}
@@ -0,0 +1,115 @@
+use alloc::collections::VecDeque;
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::Poll;
+
+use futures_io::AsyncBufRead;
+
+use crate::{Error, Event, ParseOptions, PushParser};
+
+/// Reads events from an `AsyncBufRead` without blocking the thread, by
+/// feeding whatever the reader has buffered to a `PushParser`. Works with
+/// any runtime that implements the `futures-io` traits; Tokio readers can
+/// be adapted with `tokio-util`'s `compat`.
+pub struct AsyncXmlReader<R> {
+    reader: R,
+    parser: PushParser,
+    pending: VecDeque<Event>,
+    done: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncXmlReader<R> {
+    pub fn new(reader: R) -> Self {
+        AsyncXmlReader::with_options(reader, ParseOptions::default())
+    }
+
+    /// The tree is never built, so endless streams use bounded memory.
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        AsyncXmlReader {
+            reader,
+            parser: PushParser::new(options).events_only(),
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// The next event, or `None` once the reader is exhausted after a
+    /// complete document. Input that ends mid-document is an error.
+    pub async fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            if self.done {
+                return Ok(None);
+            }
+            let AsyncXmlReader { reader, parser, .. } = self;
+            let events = poll_fn(|cx| match Pin::new(&mut *reader).poll_fill_buf(cx) {
+                Poll::Ready(Ok([])) => Poll::Ready(Ok(None)),
+                Poll::Ready(Ok(chunk)) => {
+                    let len = chunk.len();
+                    let events = parser.feed(chunk);
+                    Pin::new(&mut *reader).consume(len);
+                    Poll::Ready(events.map(Some))
+                }
+                Poll::Ready(Err(error)) => Poll::Ready(Err(Error::from(error))),
+                Poll::Pending => Poll::Pending,
+            })
+            .await?;
+            match events {
+                Some(events) => self.pending.extend(events),
+                None => {
+                    self.parser.end()?;
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn async_reader_events() {
+    use crate::prelude::*;
+    use std::sync::Arc;
+    use std::task::{Context, Wake};
+
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+    fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        let waker = Arc::new(Noop).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    let mut reader = AsyncXmlReader::new(&b"<a><b/>text</a>"[..]);
+    let events = block_on(async {
+        let mut events = Vec::new();
+        while let Some(event) = reader.next_event().await? {
+            events.push(event);
+        }
+        Ok::<_, Error>(events)
+    });
+    assert_eq!(Ok(5), events.map(|events| events.len()));
+
+    let mut truncated = AsyncXmlReader::new(&b"<a><b>"[..]);
+    let result = block_on(async {
+        while truncated.next_event().await?.is_some() {}
+        Ok(())
+    });
+    assert_eq!(Err(Error::Syntax { offset: 6 }), result);
+}
+
+#[test]
+fn async_reader_is_send() {
+    fn assert_send<T: Send>(_: &T) {}
+    let mut reader = AsyncXmlReader::new(&b"<a/>"[..]);
+    assert_send(&reader);
+    assert_send(&reader.next_event());
+}
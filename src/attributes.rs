@@ -0,0 +1,110 @@
+use alloc::collections::BTreeMap;
+
+use crate::prelude::*;
+use crate::{Element, Error};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy {
+    KeepExisting,
+    Overwrite,
+    ErrorOnConflict,
+}
+
+impl Element {
+    /// The attributes as a map from name to value. Each call builds the map
+    /// afresh, which takes longer than one `attribute()` lookup, so it only
+    /// pays off when the map is kept for many lookups into an element with
+    /// many attributes; `attribute()` searches the list in order, which is
+    /// quicker for the few attributes most elements have. `attributes()`
+    /// still has them in source order. If a name repeats, the first
+    /// occurrence wins, as with `attribute()`.
+    pub fn attributes_map(&self) -> BTreeMap<&str, &str> {
+        let mut map = BTreeMap::new();
+        for (name, value) in &self.attributes {
+            map.entry(name.as_str()).or_insert(value.as_str());
+        }
+        map
+    }
+
+    /// Sets `name` to `value`, keeping the attribute's position if it exists.
+    pub fn set_attribute<N, V>(&mut self, name: N, value: V)
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        let (name, value) = (name.into(), value.into());
+        match self.attributes.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.attributes.push((name, value)),
+        }
+    }
+
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|(key, _)| key == name)?;
+        Some(self.attributes.remove(index).1)
+    }
+
+    /// Copies `other`'s attributes onto this element. Attributes already
+    /// present keep their position; new ones are appended in `other`'s order.
+    /// With `ErrorOnConflict` nothing is changed if any shared attribute has
+    /// a different value.
+    pub fn merge_attributes_from(
+        &mut self,
+        other: &Element,
+        policy: MergePolicy,
+    ) -> Result<(), Error> {
+        if policy == MergePolicy::ErrorOnConflict {
+            for (name, value) in &other.attributes {
+                match self.attribute(name) {
+                    Some(existing) if existing != value => {
+                        return Err(Error::AttributeConflict(name.clone()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        for (name, value) in &other.attributes {
+            match self.attributes.iter_mut().find(|(key, _)| key == name) {
+                Some((_, existing)) if policy == MergePolicy::Overwrite => {
+                    *existing = value.clone()
+                }
+                Some(_) => {}
+                None => self.attributes.push((name.clone(), value.clone())),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn merge_attributes() {
+    let template: Element = "<a x=\"1\" y=\"2\"/>".parse().unwrap();
+    let overrides: Element = "<b z=\"3\" y=\"9\" w=\"4\"/>".parse().unwrap();
+
+    let mut el = template.clone();
+    el.merge_attributes_from(&overrides, MergePolicy::KeepExisting)
+        .unwrap();
+    assert_eq!("<a x=\"1\" y=\"2\" z=\"3\" w=\"4\"/>", el.to_string());
+
+    let mut el = template.clone();
+    el.merge_attributes_from(&overrides, MergePolicy::Overwrite)
+        .unwrap();
+    assert_eq!("<a x=\"1\" y=\"9\" z=\"3\" w=\"4\"/>", el.to_string());
+
+    let mut el = template.clone();
+    assert_eq!(
+        Err(Error::AttributeConflict("y".to_string())),
+        el.merge_attributes_from(&overrides, MergePolicy::ErrorOnConflict)
+    );
+    assert_eq!(template, el);
+}
+
+#[test]
+fn attribute_lookup() {
+    let el: Element = "<a z=\"1\" y=\"2\" x=\"3\"/>".parse().unwrap();
+    let map = el.attributes_map();
+    assert_eq!(Some(&"2"), map.get("y"));
+    assert_eq!(None, map.get("w"));
+    let names: Vec<_> = el.attributes().iter().map(|(k, _)| k.as_str()).collect();
+    assert_eq!(vec!["z", "y", "x"], names);
+}
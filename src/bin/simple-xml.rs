@@ -0,0 +1,87 @@
+//! `simple-xml fmt|validate|get`, reading a file or `-` for standard input.
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use simple_xmlparser::{Document, SerializeOptions};
+
+const USAGE: &str = "usage: simple-xml fmt <file>
+       simple-xml validate <file>
+       simple-xml get <file> <path>
+
+<path> is /root/child/... with an optional /@attribute at the end";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (command, file, path) = match args.as_slice() {
+        [command @ ("fmt" | "validate"), file] => (*command, *file, None),
+        ["get", file, path] => ("get", *file, Some(*path)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let source = match read(file) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("simple-xml: {}: {}", file, error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let doc = match Document::parse(&source) {
+        Ok(doc) => doc,
+        Err(error) => {
+            eprint!("{}: {}", file, error.render(&source));
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = match (command, path) {
+        ("fmt", _) => doc.to_string_with(&SerializeOptions::new().indent("  ")),
+        ("get", Some(path)) => match get(&doc, path) {
+            Some(value) => Ok(value),
+            None => {
+                eprintln!("simple-xml: {}: nothing at {}", file, path);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => return ExitCode::SUCCESS,
+    };
+    match output {
+        Ok(output) => {
+            let _ = writeln!(io::stdout(), "{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("simple-xml: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read(file: &str) -> io::Result<String> {
+    let mut source = String::new();
+    match file {
+        "-" => io::stdin().read_to_string(&mut source)?,
+        file => std::fs::File::open(file)?.read_to_string(&mut source)?,
+    };
+    Ok(source)
+}
+
+// The first step of `path` names the root; the rest are found with
+// `Element::find_path`.
+fn get(doc: &Document, path: &str) -> Option<String> {
+    let (path, attribute) = match path.rsplit_once("/@") {
+        Some((path, attribute)) => (path, Some(attribute)),
+        None => (path, None),
+    };
+    let path = path.trim_start_matches('/');
+    let (root, rest) = path.split_once('/').unwrap_or((path, ""));
+    let el = Some(doc.root())
+        .filter(|el| el.name() == root)?
+        .find_path(rest)?;
+    match attribute {
+        Some(attribute) => el.attribute(attribute).map(str::to_string),
+        None => Some(el.immediate_text()),
+    }
+}
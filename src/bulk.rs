@@ -0,0 +1,100 @@
+#[cfg(feature = "rayon")]
+use crate::fragment::{fragment_in, parse_fragment_in};
+#[cfg(feature = "rayon")]
+use crate::prelude::*;
+#[cfg(feature = "rayon")]
+use crate::Node;
+use crate::{document_in, Context, Document, Error, ParseOptions};
+
+/// Parses every input as a separate document. The parser is built once and
+/// reused for all of them, which matters when the inputs are many and small.
+pub fn parse_many<'a, I>(
+    inputs: I,
+    options: &ParseOptions,
+) -> impl Iterator<Item = Result<Document, Error>> + 'a
+where
+    I: IntoIterator<Item = &'a str>,
+    I::IntoIter: 'a,
+{
+    let context = Context::new(options.clone());
+    let parser = document_in(context.clone());
+    inputs
+        .into_iter()
+        .map(move |input| Document::parse_in(input, &parser, &context))
+}
+
+/// Like `parse_many`, spread over the rayon thread pool. Each worker builds
+/// its own parser; results come back in input order.
+#[cfg(feature = "rayon")]
+pub fn parse_many_parallel(
+    inputs: &[&str],
+    options: &ParseOptions,
+) -> Vec<Result<Document, Error>> {
+    use rayon::prelude::*;
+
+    inputs
+        .par_iter()
+        .map_init(
+            || {
+                let context = Context::new(options.clone());
+                (document_in(context.clone()), context)
+            },
+            |(parser, context), input| Document::parse_in(input, parser, context),
+        )
+        .collect()
+}
+
+/// Parses every input with `parse_fragment_with`, spread over the rayon
+/// thread pool. Results come back in input order.
+#[cfg(feature = "rayon")]
+pub fn parse_fragments_parallel(
+    inputs: &[&str],
+    options: &ParseOptions,
+) -> Vec<Result<Vec<Node>, Error>> {
+    use rayon::prelude::*;
+
+    inputs
+        .par_iter()
+        .map_init(
+            || {
+                let context = Context::new(options.clone());
+                (fragment_in(context.clone()), context)
+            },
+            |(parser, context), input| {
+                parse_fragment_in(input, parser, context).map(|(nodes, _)| nodes)
+            },
+        )
+        .collect()
+}
+
+#[test]
+fn bulk_parse() {
+    use crate::prelude::*;
+
+    let inputs = ["<a/>", "<b>x</b>", "<c>", "<d v=\"1\"/>"];
+    let results: Vec<_> = parse_many(inputs.iter().copied(), &ParseOptions::default())
+        .map(|result| result.map(|doc| doc.root().name.clone()))
+        .collect();
+    assert_eq!(
+        vec![
+            Ok("a".to_string()),
+            Ok("b".to_string()),
+            Err(Error::Syntax { offset: 3 }),
+            Ok("d".to_string()),
+        ],
+        results
+    );
+    #[cfg(feature = "rayon")]
+    assert_eq!(
+        parse_many(inputs.iter().copied(), &ParseOptions::default()).collect::<Vec<_>>(),
+        parse_many_parallel(&inputs, &ParseOptions::default())
+    );
+    #[cfg(feature = "rayon")]
+    assert_eq!(
+        inputs
+            .iter()
+            .map(|input| crate::parse_fragment(input))
+            .collect::<Vec<_>>(),
+        parse_fragments_parallel(&inputs, &ParseOptions::default())
+    );
+}
@@ -0,0 +1,112 @@
+use crate::prelude::*;
+use crate::{Element, ExpandedName, NamespaceScope, Node, ParseOptions, Whitespace, XML_NAMESPACE};
+
+impl Element {
+    /// `canonicalize_with(Whitespace::Collapse)`.
+    pub fn canonicalize(&self) -> Element {
+        self.canonicalize_with(Whitespace::Collapse)
+    }
+
+    /// A normal form for comparing trees by meaning rather than spelling.
+    /// Namespaces are resolved and rebound to the prefixes `ns0`, `ns1`, ...
+    /// in order of first use, all declared on the root; attributes are
+    /// sorted by namespace URI and local name; comments are dropped,
+    /// adjacent text is merged and `whitespace` is applied to it, honoring
+    /// `xml:space`. Prefixes that are not bound are kept as written.
+    pub fn canonicalize_with(&self, whitespace: Whitespace) -> Element {
+        let mut canonicalizer = Canonicalizer {
+            scope: NamespaceScope::new(),
+            uris: Vec::new(),
+        };
+        let mut root = canonicalizer.element(self);
+        let declarations = canonicalizer
+            .uris
+            .iter()
+            .enumerate()
+            .map(|(i, uri)| (format!("xmlns:ns{}", i), uri.to_string()));
+        root.attributes.splice(0..0, declarations);
+        ParseOptions::new().whitespace(whitespace).finish(&mut root);
+        root
+    }
+
+    /// Whether both trees have the same `canonicalize()` form.
+    pub fn canonical_eq(&self, other: &Element) -> bool {
+        self.canonicalize() == other.canonicalize()
+    }
+}
+
+struct Canonicalizer<'a> {
+    scope: NamespaceScope<'a>,
+    uris: Vec<&'a str>,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn element(&mut self, el: &'a Element) -> Element {
+        self.scope.push(el);
+        let name = self.name(self.scope.element_name(el), &el.name);
+        let mut attributes: Vec<_> = el
+            .attributes
+            .iter()
+            .filter(|(name, _)| name != "xmlns" && !name.starts_with("xmlns:"))
+            .map(|(name, value)| (self.scope.attribute_name(name), name, value))
+            .collect();
+        attributes
+            .sort_by_key(|(expanded, _, _)| (expanded.namespace.unwrap_or(""), expanded.local));
+        let attributes = attributes
+            .into_iter()
+            .map(|(expanded, name, value)| (self.name(expanded, name), value.clone()))
+            .collect();
+        let mut children: Vec<Node> = Vec::new();
+        for child in &el.children {
+            match (child, children.last_mut()) {
+                (Node::Element(child), _) => children.push(Node::Element(self.element(child))),
+                (Node::Text(text), Some(Node::Text(last))) => last.push_str(text),
+                (Node::Comment(_), _) => {}
+                (other, _) => children.push(other.clone()),
+            }
+        }
+        self.scope.pop();
+        Element {
+            name,
+            attributes,
+            children,
+        }
+    }
+
+    fn name(&mut self, expanded: ExpandedName<'a>, written: &str) -> String {
+        match expanded.namespace {
+            None => written.to_string(),
+            Some(XML_NAMESPACE) => format!("xml:{}", expanded.local),
+            Some(uri) => {
+                let index = match self.uris.iter().position(|u| *u == uri) {
+                    Some(index) => index,
+                    None => {
+                        self.uris.push(uri);
+                        self.uris.len() - 1
+                    }
+                };
+                format!("ns{}:{}", index, expanded.local)
+            }
+        }
+    }
+}
+
+#[test]
+fn canonical_form() {
+    let a: Element = r#"<r xmlns="urn:a" xmlns:b="urn:b" z="1" b:y="2" a="3">
+            <b:c>  some   text</b:c><!-- note --><d/>
+        </r>"#
+        .parse()
+        .unwrap();
+    let b: Element = r#"<x:r xmlns:x="urn:a" a="3" z="1" xmlns:q="urn:b" q:y="2"><q:c xmlns:q="urn:b">some text</q:c><x:d></x:d></x:r>"#
+        .parse()
+        .unwrap();
+    assert!(a.canonical_eq(&b));
+    assert_eq!(
+        "<ns0:r xmlns:ns0=\"urn:a\" xmlns:ns1=\"urn:b\" a=\"3\" z=\"1\" ns1:y=\"2\">\
+         <ns1:c>some text</ns1:c><ns0:d/></ns0:r>",
+        a.canonicalize().to_string()
+    );
+    let c: Element = "<r xmlns=\"urn:other\"/>".parse().unwrap();
+    assert!(!c.canonical_eq(&"<r/>".parse().unwrap()));
+}
@@ -0,0 +1,492 @@
+use core::fmt;
+use core::str::FromStr;
+
+use crate::parser::name_end;
+use crate::prelude::*;
+use crate::{is_name_char, reference, Error};
+
+/// A document parsed into a concrete syntax tree, which keeps everything the
+/// `Element` tree throws away: quoting, attribute order and spacing, the
+/// whitespace and comments between nodes, and whether empty elements were
+/// self-closing. Its `Display` output reproduces the input byte for byte,
+/// and an edit changes only the text it touches, so a tool can rewrite one
+/// attribute of a `pom.xml` and leave a one-line diff.
+///
+/// Text, the declaration, processing instructions, CDATA sections and the
+/// document type are kept verbatim and are not decoded or checked further.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstDocument {
+    nodes: Vec<CstNode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CstNode {
+    Element(CstElement),
+    /// Character data as written, references left undecoded.
+    Text(String),
+    /// The body of a `<!--...-->` comment.
+    Comment(String),
+    /// Any other markup as written: `<?...?>`, `<![CDATA[...]]>` or
+    /// `<!DOCTYPE ...>`.
+    Markup(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstElement {
+    name: String,
+    attributes: Vec<CstAttribute>,
+    // The whitespace between the last attribute and `>` or `/>`.
+    tag_end: String,
+    self_closing: bool,
+    children: Vec<CstNode>,
+    // The whitespace between the name of the closing tag and its `>`.
+    close_end: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstAttribute {
+    // The whitespace before the name.
+    leading: String,
+    name: String,
+    // The whitespace around the `=`.
+    before_eq: String,
+    after_eq: String,
+    quote: char,
+    // The value as written between the quotes.
+    raw: String,
+}
+
+impl CstDocument {
+    pub fn parse(input: &str) -> Result<CstDocument, Error> {
+        let fail = |rest: &str| Error::Syntax {
+            offset: input.len() - rest.len(),
+        };
+        let (rest, nodes) = nodes(input).map_err(fail)?;
+        if !rest.is_empty() {
+            return Err(fail(rest));
+        }
+        // Only whitespace and markup may surround the one root element.
+        let mut offset = 0;
+        let mut roots = 0;
+        for node in &nodes {
+            match node {
+                CstNode::Element(_) if roots == 0 => roots += 1,
+                CstNode::Text(text) if text.trim().is_empty() => {}
+                CstNode::Element(_) | CstNode::Text(_) => return Err(Error::Syntax { offset }),
+                _ => {}
+            }
+            offset += node.to_string().len();
+        }
+        match roots {
+            1 => Ok(CstDocument { nodes }),
+            _ => Err(Error::Syntax { offset }),
+        }
+    }
+
+    /// Everything at the top level, including the whitespace between nodes.
+    pub fn nodes(&self) -> &[CstNode] {
+        &self.nodes
+    }
+
+    pub fn root(&self) -> &CstElement {
+        self.nodes
+            .iter()
+            .find_map(|node| match node {
+                CstNode::Element(el) => Some(el),
+                _ => None,
+            })
+            .unwrap_or_else(|| unreachable!("parse checks for a root"))
+    }
+
+    pub fn root_mut(&mut self) -> &mut CstElement {
+        self.nodes
+            .iter_mut()
+            .find_map(|node| match node {
+                CstNode::Element(el) => Some(el),
+                _ => None,
+            })
+            .unwrap_or_else(|| unreachable!("parse checks for a root"))
+    }
+}
+
+/// Parses `input` into a `CstDocument`, lets `edit` change it, and writes it
+/// out again. Whatever `edit` leaves alone comes back byte for byte:
+/// references, quoting, spacing and `<b></b>` stay as written, so the
+/// output differs from the input only where the document was edited.
+pub fn rewrite<F>(input: &str, edit: F) -> Result<String, Error>
+where
+    F: FnOnce(&mut CstDocument),
+{
+    let mut doc = CstDocument::parse(input)?;
+    edit(&mut doc);
+    Ok(doc.to_string())
+}
+
+impl FromStr for CstDocument {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<CstDocument, Error> {
+        CstDocument::parse(input)
+    }
+}
+
+impl CstElement {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn attributes(&self) -> &[CstAttribute] {
+        &self.attributes
+    }
+
+    /// The decoded value of the attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.name == name)
+            .map(CstAttribute::value)
+    }
+
+    /// Sets an attribute, keeping the quoting and spacing of an existing
+    /// one. A new attribute goes last as ` name="value"`.
+    pub fn set_attribute<N: Into<String>>(&mut self, name: N, value: &str) {
+        let name = name.into();
+        match self.attributes.iter_mut().find(|attr| attr.name == name) {
+            Some(attr) => attr.raw = escape(value, attr.quote),
+            None => self.attributes.push(CstAttribute {
+                leading: " ".to_string(),
+                name,
+                before_eq: String::new(),
+                after_eq: String::new(),
+                quote: '"',
+                raw: escape(value, '"'),
+            }),
+        }
+    }
+
+    /// Removes an attribute along with the whitespace before it, returning
+    /// its decoded value.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        let index = self.attributes.iter().position(|attr| attr.name == name)?;
+        Some(self.attributes.remove(index).value())
+    }
+
+    pub fn children(&self) -> &[CstNode] {
+        &self.children
+    }
+
+    /// The children, for adding or removing nodes. An element that gains
+    /// children stops being written as self-closing.
+    pub fn children_mut(&mut self) -> &mut Vec<CstNode> {
+        &mut self.children
+    }
+
+    /// The child elements, skipping text, comments and markup.
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut CstElement> {
+        self.children.iter_mut().filter_map(|child| match child {
+            CstNode::Element(el) => Some(el),
+            _ => None,
+        })
+    }
+
+    /// The first child element called `name`.
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut CstElement> {
+        self.elements_mut().find(|el| el.name == name)
+    }
+
+    /// Whether the element was written as `<a/>` rather than `<a></a>`.
+    pub fn is_self_closing(&self) -> bool {
+        self.self_closing && self.children.is_empty()
+    }
+}
+
+impl CstAttribute {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The value with references decoded and whitespace normalized, as
+    /// `Element::attribute` returns it.
+    pub fn value(&self) -> String {
+        decode(&self.raw)
+    }
+
+    /// The quote character the value was written with.
+    pub fn quote(&self) -> char {
+        self.quote
+    }
+}
+
+fn nodes(mut input: &str) -> Result<(&str, Vec<CstNode>), &str> {
+    let mut nodes = Vec::new();
+    loop {
+        if input.is_empty() || input.starts_with("</") {
+            return Ok((input, nodes));
+        }
+        let (rest, node) = node(input)?;
+        nodes.push(node);
+        input = rest;
+    }
+}
+
+fn node(input: &str) -> Result<(&str, CstNode), &str> {
+    if let Some(body) = input.strip_prefix("<!--") {
+        let end = body.find("-->").ok_or(input)?;
+        return Ok((&body[end + 3..], CstNode::Comment(body[..end].to_string())));
+    }
+    let end = if input.starts_with("<?") {
+        input.find("?>").map(|end| end + 2)
+    } else if input.starts_with("<![CDATA[") {
+        input.find("]]>").map(|end| end + 3)
+    } else if input.starts_with("<!") {
+        doctype_end(input)
+    } else if input.starts_with('<') {
+        return element(input).map(|(rest, el)| (rest, CstNode::Element(el)));
+    } else {
+        Some(input.find('<').unwrap_or(input.len()))
+    };
+    let end = end.ok_or(input)?;
+    let node = match input.starts_with('<') {
+        true => CstNode::Markup(input[..end].to_string()),
+        false => CstNode::Text(input[..end].to_string()),
+    };
+    Ok((&input[end..], node))
+}
+
+// The end of a `<!DOCTYPE ...>`, which may hold a `[...]` internal subset.
+pub(crate) fn doctype_end(input: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth -= 1,
+            (None, '>') if depth == 0 => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn element(input: &str) -> Result<(&str, CstElement), &str> {
+    let body = &input[1..];
+    let len = name_end(body).ok_or(body)?;
+    let name = body[..len].to_string();
+    let mut rest = &body[len..];
+    let mut attributes = Vec::new();
+    let (rest, tag_end, self_closing) = loop {
+        let (after, space) = whitespace(rest);
+        if let Some(after) = after.strip_prefix("/>") {
+            break (after, space, true);
+        }
+        if let Some(after) = after.strip_prefix('>') {
+            break (after, space, false);
+        }
+        if space.is_empty() {
+            return Err(after);
+        }
+        let (after, attr) = attribute(after, space)?;
+        attributes.push(attr);
+        rest = after;
+    };
+    let mut el = CstElement {
+        name,
+        attributes,
+        tag_end: tag_end.to_string(),
+        self_closing,
+        children: Vec::new(),
+        close_end: String::new(),
+    };
+    if self_closing {
+        return Ok((rest, el));
+    }
+    let (rest, children) = nodes(rest)?;
+    el.children = children;
+    let close = rest.strip_prefix("</").ok_or(rest)?;
+    let close = close
+        .strip_prefix(el.name.as_str())
+        .filter(|after| !after.starts_with(is_name_char))
+        .ok_or(rest)?;
+    let (close, space) = whitespace(close);
+    el.close_end = space.to_string();
+    let rest = close.strip_prefix('>').ok_or(close)?;
+    Ok((rest, el))
+}
+
+fn attribute<'a>(input: &'a str, leading: &str) -> Result<(&'a str, CstAttribute), &'a str> {
+    let len = name_end(input).ok_or(input)?;
+    let (rest, before_eq) = whitespace(&input[len..]);
+    let rest = rest.strip_prefix('=').ok_or(rest)?;
+    let (rest, after_eq) = whitespace(rest);
+    let quote = match rest.chars().next() {
+        Some(quote) if quote == '"' || quote == '\'' => quote,
+        _ => return Err(rest),
+    };
+    let end = rest[1..].find(quote).ok_or(rest)?;
+    let raw = &rest[1..end + 1];
+    if raw.contains('<') {
+        return Err(rest);
+    }
+    let attr = CstAttribute {
+        leading: leading.to_string(),
+        name: input[..len].to_string(),
+        before_eq: before_eq.to_string(),
+        after_eq: after_eq.to_string(),
+        quote,
+        raw: raw.to_string(),
+    };
+    Ok((&rest[end + 2..], attr))
+}
+
+fn whitespace(input: &str) -> (&str, &str) {
+    let rest = input.trim_start_matches([' ', '\t', '\r', '\n']);
+    (rest, &input[..input.len() - rest.len()])
+}
+
+fn decode(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(at) = rest.find(['&', '\t', '\n', '\r']) {
+        value.push_str(&rest[..at]);
+        rest = &rest[at..];
+        if !rest.starts_with('&') {
+            value.push(' ');
+            rest = rest.strip_prefix("\r\n").unwrap_or(&rest[1..]);
+            continue;
+        }
+        match reference(rest) {
+            Ok((next, c)) => {
+                value.push(c.unwrap_or('\u{FFFD}'));
+                rest = next;
+            }
+            Err(_) => {
+                value.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    value.push_str(rest);
+    value
+}
+
+fn escape(value: &str, quote: char) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '"' if quote == '"' => escaped.push_str("&quot;"),
+            '\'' if quote == '\'' => escaped.push_str("&apos;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl fmt::Display for CstDocument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.nodes.iter().try_for_each(|node| write!(f, "{}", node))
+    }
+}
+
+impl fmt::Display for CstNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CstNode::Element(el) => write!(f, "{}", el),
+            CstNode::Text(text) | CstNode::Markup(text) => f.write_str(text),
+            CstNode::Comment(text) => write!(f, "<!--{}-->", text),
+        }
+    }
+}
+
+impl fmt::Display for CstElement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for attr in &self.attributes {
+            write!(
+                f,
+                "{}{}{}={}{}{}{}",
+                attr.leading,
+                attr.name,
+                attr.before_eq,
+                attr.after_eq,
+                attr.quote,
+                attr.raw,
+                attr.quote
+            )?;
+        }
+        f.write_str(&self.tag_end)?;
+        if self.is_self_closing() {
+            return f.write_str("/>");
+        }
+        f.write_str(">")?;
+        self.children
+            .iter()
+            .try_for_each(|child| write!(f, "{}", child))?;
+        write!(f, "</{}{}>", self.name, self.close_end)
+    }
+}
+
+#[test]
+fn lossless_round_trip() {
+    let input = "<?xml version=\"1.0\"?>\n<!-- build -->\n<project  a='1'\n         b = \"x &amp; y\" >\n  <empty></empty>\n  <version/>\n  <![CDATA[<raw>]]>\n</project >\n";
+    let mut doc = CstDocument::parse(input).unwrap();
+    assert_eq!(input, doc.to_string());
+    assert_eq!(Some("x & y".to_string()), doc.root().attribute("b"));
+
+    let root = doc.root_mut();
+    root.set_attribute("a", "it's");
+    root.set_attribute("c", "new");
+    let version = root.child_mut("version").unwrap();
+    assert!(version.is_self_closing());
+    version
+        .children_mut()
+        .push(CstNode::Text("1.1".to_string()));
+    let edited = input
+        .replace("a='1'", "a='it&apos;s'")
+        .replace("y\" >", "y\" c=\"new\" >")
+        .replace("<version/>", "<version>1.1</version>");
+    assert_eq!(edited, doc.to_string());
+    assert_eq!(
+        Some("it's".to_string()),
+        doc.root_mut().remove_attribute("a")
+    );
+
+    assert!(CstDocument::parse("<a><b></a></b>").is_err());
+    assert_eq!(
+        Err(Error::Syntax { offset: 4 }),
+        CstDocument::parse("<a/><b/>")
+    );
+    assert!(CstDocument::parse("x<a/>").is_err());
+}
+
+#[test]
+fn rewrite_document() {
+    let input = "<?xml version=\"1.0\"?>\n<config>\n  <port value=\"80\"/>\n</config>";
+    let output = rewrite(input, |doc| {
+        if let Some(port) = doc.root_mut().child_mut("port") {
+            port.set_attribute("value", "8080");
+        }
+    });
+    assert_eq!(
+        Ok("<?xml version=\"1.0\"?>\n<config>\n  <port value=\"8080\"/>\n</config>".to_string()),
+        output
+    );
+    let input = "<a  b='&apos;'>&#65;<c></c><d x=\"1\" /></a>";
+    assert_eq!(Ok(input.to_string()), rewrite(input, |_| {}));
+    assert_eq!(
+        Ok("<a  b='&apos;'>&#65;<c></c><d x=\"2\" /></a>".to_string()),
+        rewrite(input, |doc| {
+            if let Some(d) = doc.root_mut().child_mut("d") {
+                d.set_attribute("x", "2");
+            }
+        })
+    );
+    assert!(rewrite("<a>", |_| {}).is_err());
+}
@@ -0,0 +1,457 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::{Element, ExpandedName, NamespaceScope, Node};
+
+// Children are aligned with a longest-common-subsequence table of up to
+// this many cells; larger elements are aligned greedily instead.
+const MAX_LCS_CELLS: usize = 1 << 18;
+
+/// One difference between two trees. Paths are XPath-like (`/a/b[2]`,
+/// `/a/text()[1]`) and locate the node in the old tree, except for
+/// `Added`, which locates the new node in the new tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diff {
+    Added {
+        path: String,
+        node: Node,
+    },
+    Removed {
+        path: String,
+        node: Node,
+    },
+    Renamed {
+        path: String,
+        from: String,
+        to: String,
+    },
+    /// The element is in another namespace, whether or not its prefix
+    /// changed; `None` is no namespace.
+    NamespaceChanged {
+        path: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    AttributeAdded {
+        path: String,
+        name: String,
+        value: String,
+    },
+    AttributeRemoved {
+        path: String,
+        name: String,
+        value: String,
+    },
+    AttributeChanged {
+        path: String,
+        name: String,
+        from: String,
+        to: String,
+    },
+    TextChanged {
+        path: String,
+        from: String,
+        to: String,
+    },
+    NamespaceAdded {
+        path: String,
+        prefix: String,
+        uri: String,
+    },
+    NamespaceRemoved {
+        path: String,
+        prefix: String,
+        uri: String,
+    },
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Key<'a> {
+    Element(ExpandedName<'a>),
+    Text,
+    Comment,
+    ProcessingInstruction(&'a str),
+}
+
+struct Differ<'a> {
+    old_scope: NamespaceScope<'a>,
+    new_scope: NamespaceScope<'a>,
+    diffs: Vec<Diff>,
+}
+
+impl Element {
+    /// Compares the trees by namespace URI rather than prefix, so a document
+    /// that only re-prefixes its namespaces has no differences. Namespace
+    /// declarations are compared by the URIs they bind and reported as
+    /// `NamespaceAdded`/`NamespaceRemoved`; an element that moves to
+    /// another namespace is reported as `NamespaceChanged`, and as
+    /// `Renamed` only if its local name changed too.
+    pub fn diff(&self, other: &Element) -> Vec<Diff> {
+        let mut differ = Differ {
+            old_scope: NamespaceScope::new(),
+            new_scope: NamespaceScope::new(),
+            diffs: Vec::new(),
+        };
+        differ.old_scope.push(self);
+        differ.new_scope.push(other);
+        differ.element(self, other, &format!("/{}", self.name));
+        differ.diffs
+    }
+}
+
+pub(crate) fn child_paths(parent: &str, children: &[Node]) -> Vec<String> {
+    let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+    children
+        .iter()
+        .map(|child| {
+            let step = match child {
+                Node::Element(el) => el.name.as_str(),
+                Node::Text(_) => "text()",
+                Node::Comment(_) => "comment()",
+                Node::ProcessingInstruction { .. } => "processing-instruction()",
+            };
+            let index = seen.entry(step).or_insert(0);
+            *index += 1;
+            format!("{}/{}[{}]", parent, step, index)
+        })
+        .collect()
+}
+
+impl<'a> Differ<'a> {
+    // Both scopes must already include `old` and `new`.
+    fn element(&mut self, old: &'a Element, new: &'a Element, path: &str) {
+        let old_name = self.old_scope.element_name(old);
+        let new_name = self.new_scope.element_name(new);
+        if old_name.local != new_name.local {
+            self.diffs.push(Diff::Renamed {
+                path: path.to_string(),
+                from: old.name.clone(),
+                to: new.name.clone(),
+            });
+        }
+        if old_name.namespace != new_name.namespace {
+            self.diffs.push(Diff::NamespaceChanged {
+                path: path.to_string(),
+                from: old_name.namespace.map(String::from),
+                to: new_name.namespace.map(String::from),
+            });
+        }
+        self.namespaces(old, new, path);
+        self.attributes(old, new, path);
+        self.children(old, new, path);
+    }
+
+    fn namespaces(&mut self, old: &Element, new: &Element, path: &str) {
+        for (prefix, uri) in old.namespaces_missing_from(new) {
+            self.diffs.push(Diff::NamespaceRemoved {
+                path: path.to_string(),
+                prefix: prefix.to_string(),
+                uri: uri.to_string(),
+            });
+        }
+        for (prefix, uri) in new.namespaces_missing_from(old) {
+            self.diffs.push(Diff::NamespaceAdded {
+                path: path.to_string(),
+                prefix: prefix.to_string(),
+                uri: uri.to_string(),
+            });
+        }
+    }
+
+    fn attributes(&mut self, old: &'a Element, new: &'a Element, path: &str) {
+        let old_attrs = self.old_scope.attributes(old);
+        let new_attrs = self.new_scope.attributes(new);
+        for (key, name, value) in &old_attrs {
+            match new_attrs.iter().find(|(k, _, _)| k == key) {
+                Some((_, _, new_value)) if new_value != value => {
+                    self.diffs.push(Diff::AttributeChanged {
+                        path: path.to_string(),
+                        name: name.to_string(),
+                        from: value.to_string(),
+                        to: new_value.to_string(),
+                    })
+                }
+                Some(_) => {}
+                None => self.diffs.push(Diff::AttributeRemoved {
+                    path: path.to_string(),
+                    name: name.to_string(),
+                    value: value.to_string(),
+                }),
+            }
+        }
+        for (key, name, value) in &new_attrs {
+            if !old_attrs.iter().any(|(k, _, _)| k == key) {
+                self.diffs.push(Diff::AttributeAdded {
+                    path: path.to_string(),
+                    name: name.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+    }
+
+    // Aligns the children on the longest common subsequence of their names,
+    // or greedily for very large elements, then pairs up what is left
+    // between matches as renames or text edits.
+    fn children(&mut self, old: &'a Element, new: &'a Element, path: &str) {
+        let old_keys: Vec<_> = old
+            .children
+            .iter()
+            .map(|c| key(&mut self.old_scope, c))
+            .collect();
+        let new_keys: Vec<_> = new
+            .children
+            .iter()
+            .map(|c| key(&mut self.new_scope, c))
+            .collect();
+        let old_paths = child_paths(path, &old.children);
+        let new_paths = child_paths(path, &new.children);
+
+        let (n, m) = (old_keys.len(), new_keys.len());
+        let matches = match n.checked_mul(m) {
+            Some(cells) if cells <= MAX_LCS_CELLS => lcs_matches(&old_keys, &new_keys),
+            _ => greedy_matches(&old_keys, &new_keys),
+        };
+        let (mut i, mut j) = (0, 0);
+        for (next_i, next_j) in matches.into_iter().chain(core::iter::once((n, m))) {
+            self.gap(old, new, i..next_i, j..next_j, &old_paths, &new_paths);
+            if next_i < n {
+                self.pair(
+                    &old.children[next_i],
+                    &new.children[next_j],
+                    &old_paths[next_i],
+                );
+            }
+            i = next_i + 1;
+            j = next_j + 1;
+        }
+    }
+
+    // Pairs the children between two matches in order, each with the next
+    // unpaired one of the same kind.
+    fn gap(
+        &mut self,
+        old: &'a Element,
+        new: &'a Element,
+        gap_old: Range<usize>,
+        gap_new: Range<usize>,
+        old_paths: &[String],
+        new_paths: &[String],
+    ) {
+        let kind = |node: &Node| match node {
+            Node::Element(_) => 0,
+            Node::Text(_) => 1,
+            Node::Comment(_) => 2,
+            Node::ProcessingInstruction { .. } => 3,
+        };
+        let mut unpaired: [VecDeque<usize>; 4] = Default::default();
+        for j in gap_new {
+            unpaired[kind(&new.children[j])].push_back(j);
+        }
+        for i in gap_old {
+            let old_child = &old.children[i];
+            match unpaired[kind(old_child)].pop_front() {
+                Some(j) => self.pair(old_child, &new.children[j], &old_paths[i]),
+                None => self.diffs.push(Diff::Removed {
+                    path: old_paths[i].clone(),
+                    node: old_child.clone(),
+                }),
+            }
+        }
+        let mut added: Vec<usize> = unpaired.iter().flatten().copied().collect();
+        added.sort_unstable();
+        for j in added {
+            self.diffs.push(Diff::Added {
+                path: new_paths[j].clone(),
+                node: new.children[j].clone(),
+            });
+        }
+    }
+
+    fn pair(&mut self, old: &'a Node, new: &'a Node, path: &str) {
+        match (old, new) {
+            (Node::Element(old), Node::Element(new)) => {
+                self.old_scope.push(old);
+                self.new_scope.push(new);
+                self.element(old, new, path);
+                self.old_scope.pop();
+                self.new_scope.pop();
+            }
+            (Node::Text(from), Node::Text(to)) | (Node::Comment(from), Node::Comment(to))
+                if from != to =>
+            {
+                self.diffs.push(Diff::TextChanged {
+                    path: path.to_string(),
+                    from: from.clone(),
+                    to: to.clone(),
+                })
+            }
+            (
+                Node::ProcessingInstruction { target, data },
+                Node::ProcessingInstruction {
+                    target: new_target,
+                    data: new_data,
+                },
+            ) => {
+                if target != new_target {
+                    self.diffs.push(Diff::Renamed {
+                        path: path.to_string(),
+                        from: target.clone(),
+                        to: new_target.clone(),
+                    });
+                }
+                if data != new_data {
+                    self.diffs.push(Diff::TextChanged {
+                        path: path.to_string(),
+                        from: data.clone(),
+                        to: new_data.clone(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The index pairs of a longest common subsequence of the keys.
+fn lcs_matches(old: &[Key<'_>], new: &[Key<'_>]) -> Vec<(usize, usize)> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut matches = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            matches.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+// Matches each old key to the first unmatched new one with the same key
+// after the last match, which takes time and memory in proportion to the
+// number of children but may match fewer than `lcs_matches`.
+fn greedy_matches(old: &[Key<'_>], new: &[Key<'_>]) -> Vec<(usize, usize)> {
+    let mut positions: BTreeMap<&Key<'_>, VecDeque<usize>> = BTreeMap::new();
+    for (j, key) in new.iter().enumerate() {
+        positions.entry(key).or_default().push_back(j);
+    }
+    let mut matches = Vec::new();
+    let mut next = 0;
+    for (i, key) in old.iter().enumerate() {
+        let queue = match positions.get_mut(key) {
+            Some(queue) => queue,
+            None => continue,
+        };
+        while matches!(queue.front(), Some(&j) if j < next) {
+            queue.pop_front();
+        }
+        if let Some(j) = queue.pop_front() {
+            matches.push((i, j));
+            next = j + 1;
+        }
+    }
+    matches
+}
+
+fn key<'a>(scope: &mut NamespaceScope<'a>, node: &'a Node) -> Key<'a> {
+    match node {
+        Node::Element(el) => Key::Element(scope.child_name(el)),
+        Node::Text(_) => Key::Text,
+        Node::Comment(_) => Key::Comment,
+        Node::ProcessingInstruction { target, .. } => Key::ProcessingInstruction(target),
+    }
+}
+
+#[test]
+fn tree_diff() {
+    let old: Element = r#"<config version="1">
+            <server host="a" port="80"/>
+            <note>old</note>
+            <legacy/>
+        </config>"#
+        .parse()
+        .unwrap();
+    let new: Element = r#"<config version="2" debug="yes">
+            <server host="a"/>
+            <note>new</note>
+            <modern/>
+            <extra/>
+        </config>"#
+        .parse()
+        .unwrap();
+    let s = |s: &str| s.to_string();
+    assert_eq!(
+        vec![
+            Diff::AttributeChanged {
+                path: s("/config"),
+                name: s("version"),
+                from: s("1"),
+                to: s("2")
+            },
+            Diff::AttributeAdded {
+                path: s("/config"),
+                name: s("debug"),
+                value: s("yes")
+            },
+            Diff::AttributeRemoved {
+                path: s("/config/server[1]"),
+                name: s("port"),
+                value: s("80")
+            },
+            Diff::TextChanged {
+                path: s("/config/note[1]/text()[1]"),
+                from: s("old"),
+                to: s("new")
+            },
+            Diff::Renamed {
+                path: s("/config/legacy[1]"),
+                from: s("legacy"),
+                to: s("modern")
+            },
+            Diff::Added {
+                path: s("/config/extra[1]"),
+                node: Node::Element("<extra/>".parse().unwrap())
+            },
+        ],
+        old.diff(&new)
+    );
+}
+
+#[test]
+fn wide_diff() {
+    // 600 children each need a table past MAX_LCS_CELLS.
+    let mut old = Element::new("r");
+    old.children = vec![Node::Element(Element::new("a")); 600];
+    let mut new = old.clone();
+    new.children.pop();
+    new.children.insert(300, Node::Element(Element::new("b")));
+    assert_eq!(
+        vec![
+            Diff::Added {
+                path: "/r/b[1]".to_string(),
+                node: Node::Element(Element::new("b"))
+            },
+            Diff::Removed {
+                path: "/r/a[600]".to_string(),
+                node: Node::Element(Element::new("a"))
+            },
+        ],
+        old.diff(&new)
+    );
+}
@@ -0,0 +1,475 @@
+use alloc::collections::BTreeMap;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::{fs::File, io::Read, path::Path};
+
+use crate::ids::id_index;
+use crate::prelude::*;
+use crate::{
+    document_in, escape_attribute, Context, Element, Encoding, Error, Node, ParseOptions, Parser,
+    SerializeOptions, Warning,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Declaration {
+    pub version: String,
+    pub encoding: Option<String>,
+    pub standalone: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    pub(crate) declaration: Option<Declaration>,
+    pub(crate) prolog: Vec<Node>,
+    // The document type declaration as written, and how many prolog nodes
+    // come before it.
+    pub(crate) doctype: Option<(usize, String)>,
+    pub(crate) root: Element,
+    pub(crate) epilog: Vec<Node>,
+    pub(crate) encoding: Encoding,
+    pub(crate) warnings: Vec<Warning>,
+    pub(crate) ids: BTreeMap<String, Vec<usize>>,
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Result<Document, Error> {
+        Document::parse_with(input, &ParseOptions::default())
+    }
+
+    pub fn parse_with(input: &str, options: &ParseOptions) -> Result<Document, Error> {
+        let context = Context::new(options.clone());
+        Document::parse_in(input, &document_in(context.clone()), &context)
+    }
+
+    // Runs a parser built from `context`, which may be reused across inputs.
+    pub(crate) fn parse_in<'a, P>(
+        input: &'a str,
+        parser: &P,
+        context: &Context,
+    ) -> Result<Document, Error>
+    where
+        P: Parser<'a, Document>,
+    {
+        let _span = span!("parse", bytes = input.len());
+        context.reset();
+        let result = parser.parse_complete(input);
+        let result = match context.take_fatal(input) {
+            Some(error) => Err(error),
+            None => result,
+        };
+        let warnings = context.take_warnings(input);
+        if let Err(error) = &result {
+            event!("parse failed", offset = error.offset());
+        }
+        event!("parsed", warnings = warnings.len());
+        let mut document = result?;
+        document.warnings = warnings;
+        document.ids = id_index(&document.root);
+        Ok(document)
+    }
+
+    /// Decodes `bytes` using the BOM or the declared encoding, which is then
+    /// remembered so that `to_bytes` writes the document back the same way.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Document, Error> {
+        let (encoding, body) = detect_encoding(bytes)?;
+        let text = {
+            let _span = span!("decode", bytes = bytes.len());
+            encoding.decode(body)?
+        };
+        let mut doc = Document::parse(&text)?;
+        doc.encoding = encoding;
+        Ok(doc)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Document, Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Document::from_bytes(&bytes)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Document, Error> {
+        Document::from_reader(File::open(path)?)
+    }
+
+    pub fn root(&self) -> &Element {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut Element {
+        &mut self.root
+    }
+
+    /// The comments and processing instructions before the root element.
+    pub fn prolog(&self) -> &[Node] {
+        &self.prolog
+    }
+
+    /// The comments and processing instructions after the root element.
+    pub fn epilog(&self) -> &[Node] {
+        &self.epilog
+    }
+
+    /// The `<!DOCTYPE>` declaration as it was written, which is written back
+    /// with the document. The entities it declares have already been
+    /// expanded in the content.
+    pub fn doctype(&self) -> Option<&str> {
+        self.doctype.as_ref().map(|(_, doctype)| doctype.as_str())
+    }
+
+    pub fn declaration(&self) -> Option<&Declaration> {
+        self.declaration.as_ref()
+    }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        self.to_bytes_with(&SerializeOptions::new())
+    }
+
+    pub fn to_string_with(&self, options: &SerializeOptions) -> Result<String, Error> {
+        let encoding = options.encoding.unwrap_or(self.encoding);
+        options.write(|out| self.write_to(out, encoding, options))
+    }
+
+    /// Encodes the document with `options.encoding`, falling back to the
+    /// encoding it was read with.
+    pub fn to_bytes_with(&self, options: &SerializeOptions) -> Result<Vec<u8>, Error> {
+        let encoding = options.encoding.unwrap_or(self.encoding);
+        let mut bytes = encoding.bom().to_vec();
+        bytes.extend(encoding.encode(&self.to_string_with(options)?)?);
+        options.check_size(bytes.len())?;
+        Ok(bytes)
+    }
+
+    fn write_to<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        encoding: Encoding,
+        options: &SerializeOptions,
+    ) -> fmt::Result {
+        if self.declaration.is_some() || encoding != Encoding::Utf8 {
+            self.write_declaration(out, encoding)?;
+        }
+        for (i, node) in self.prolog.iter().enumerate() {
+            self.write_doctype(out, i)?;
+            writeln!(out, "{}", node)?;
+        }
+        self.write_doctype(out, self.prolog.len())?;
+        options.write_element(out, &self.root)?;
+        for node in &self.epilog {
+            write!(out, "\n{}", node)?;
+        }
+        Ok(())
+    }
+
+    // Writes the document type if it comes before prolog node `i`.
+    fn write_doctype<W: fmt::Write>(&self, out: &mut W, i: usize) -> fmt::Result {
+        match &self.doctype {
+            Some((at, doctype)) if *at == i => writeln!(out, "{}", doctype),
+            _ => Ok(()),
+        }
+    }
+
+    fn write_declaration<W: fmt::Write>(&self, out: &mut W, encoding: Encoding) -> fmt::Result {
+        let version = self.declaration.as_ref().map_or("1.0", |d| &d.version);
+        write!(out, "<?xml version=\"{}\"", escape_attribute(version))?;
+        // Keep the original spelling of the label when it still applies.
+        let label = self
+            .declaration
+            .as_ref()
+            .and_then(|d| d.encoding.as_deref())
+            .filter(|label| Encoding::from_label(label) == Some(encoding))
+            .or_else(|| match encoding {
+                Encoding::Utf8 => None,
+                _ => Some(encoding.label()),
+            });
+        if let Some(label) = label {
+            write!(out, " encoding=\"{}\"", escape_attribute(label))?;
+        }
+        if let Some(standalone) = self
+            .declaration
+            .as_ref()
+            .and_then(|d| d.standalone.as_ref())
+        {
+            write!(out, " standalone=\"{}\"", escape_attribute(standalone))?;
+        }
+        writeln!(out, "?>")
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Document, Error> {
+        Document::parse(input)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Document {
+    type Error = Error;
+
+    fn try_from(input: &'a str) -> Result<Document, Error> {
+        Document::parse(input)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_to(f, self.encoding, &SerializeOptions::new())
+    }
+}
+
+// The encoding of `bytes` and the bytes past any BOM.
+pub(crate) fn detect_encoding(bytes: &[u8]) -> Result<(Encoding, &[u8]), Error> {
+    Ok(match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => (Encoding::Utf8, rest),
+        [0xFF, 0xFE, rest @ ..] => (Encoding::Utf16Le, rest),
+        [0xFE, 0xFF, rest @ ..] => (Encoding::Utf16Be, rest),
+        [0x3C, 0x00, 0x3F, 0x00, ..] => (Encoding::Utf16Le, bytes),
+        [0x00, 0x3C, 0x00, 0x3F, ..] => (Encoding::Utf16Be, bytes),
+        _ => (declared_encoding(bytes)?.unwrap_or(Encoding::Utf8), bytes),
+    })
+}
+
+// Only ASCII-compatible encodings can reach this point, so the declaration
+// can be read as Latin-1 before the real encoding is known.
+fn declared_encoding(bytes: &[u8]) -> Result<Option<Encoding>, Error> {
+    if !bytes.starts_with(b"<?xml") {
+        return Ok(None);
+    }
+    let end = match bytes.windows(2).position(|w| w == b"?>") {
+        Some(end) => end + 2,
+        None => return Ok(None),
+    };
+    let prolog = Encoding::Latin1.decode(&bytes[..end])?;
+    let declared = crate::xml_declaration()
+        .parse(&prolog)
+        .ok()
+        .and_then(|(_, decl)| decl.encoding);
+    match declared {
+        Some(label) => Encoding::from_label(&label)
+            .map(Some)
+            .ok_or(Error::UnknownEncoding(label)),
+        None => Ok(None),
+    }
+}
+
+#[test]
+fn latin1_round_trip() {
+    let bytes = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><p name=\"caf\xE9\"/>";
+    let doc = Document::from_bytes(bytes).unwrap();
+    assert_eq!(Encoding::Latin1, doc.encoding());
+    assert_eq!(
+        vec![("name".to_string(), "caf\u{e9}".to_string())],
+        doc.root().attributes
+    );
+    assert_eq!(
+        "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<p name=\"caf\u{e9}\"/>",
+        doc.to_string()
+    );
+    assert_eq!(
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<p name=\"caf\xE9\"/>".to_vec(),
+        doc.to_bytes().unwrap()
+    );
+}
+
+#[test]
+fn utf16_round_trip() {
+    let text = "<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n<a b=\"\u{3b1}\"/>";
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    let doc = Document::from_bytes(&bytes).unwrap();
+    assert_eq!(Encoding::Utf16Le, doc.encoding());
+    assert_eq!(bytes, doc.to_bytes().unwrap());
+}
+
+#[test]
+fn unencodable_output() {
+    let mut doc = Document::parse("<a b=\"\u{3b1}\"/>").unwrap();
+    doc.set_encoding(Encoding::Latin1);
+    assert_eq!(
+        Err(Error::Unencodable {
+            encoding: Encoding::Latin1,
+            ch: '\u{3b1}'
+        }),
+        doc.to_bytes()
+    );
+}
+
+#[test]
+fn bounded_output() {
+    let doc = Document::parse("<a><b/><c/></a>").unwrap();
+    let options = SerializeOptions::new().max_size(10);
+    assert_eq!(
+        Err(Error::OutputTooLarge { limit: 10 }),
+        doc.to_string_with(&options)
+    );
+    let options = SerializeOptions::new()
+        .max_size(20)
+        .encoding(Encoding::Utf16Le);
+    assert_eq!(
+        Err(Error::OutputTooLarge { limit: 20 }),
+        doc.to_bytes_with(&options)
+    );
+    let options = SerializeOptions::new().max_size(15);
+    assert_eq!(
+        Ok("<a><b/><c/></a>".to_string()),
+        doc.to_string_with(&options)
+    );
+}
+
+#[test]
+fn document_from_str() {
+    let doc: Document = "<?xml version=\"1.0\"?>\n<a/>\n".parse().unwrap();
+    assert_eq!("a", doc.root().name);
+    assert_eq!(Ok(doc), Document::try_from("<?xml version=\"1.0\"?><a/>"));
+    assert_eq!(
+        Err(Error::Syntax { offset: 5 }),
+        "<a/> <b/>".parse::<Document>()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn document_from_reader() {
+    let bytes: &[u8] = b"\xEF\xBB\xBF<?xml version=\"1.0\"?>\n<a/>";
+    let doc = Document::from_reader(bytes).unwrap();
+    assert_eq!(Encoding::Utf8, doc.encoding());
+    assert_eq!("a", doc.root().name);
+
+    let bytes: &[u8] = b"<?xml version=\"1.0\" encoding=\"EBCDIC\"?><a/>";
+    assert_eq!(
+        Err(Error::UnknownEncoding("EBCDIC".to_string())),
+        Document::from_reader(bytes)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn document_from_missing_file() {
+    match Document::from_file("does/not/exist.xml") {
+        Err(Error::Io { kind, .. }) => assert_eq!(std::io::ErrorKind::NotFound, kind),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn lenient_references() {
+    let input = "<a>x &#xD800; &#12ab y &oops; &#65;</a>";
+    assert_eq!(Err(Error::Syntax { offset: 5 }), Document::parse(input));
+    let doc = Document::parse_with(input, &ParseOptions::new().lenient(true)).unwrap();
+    assert_eq!(
+        "<a>x \u{FFFD} \u{FFFD} y &amp;oops; A</a>",
+        doc.root().to_string()
+    );
+    assert_eq!(
+        vec![
+            Warning {
+                offset: 5,
+                kind: crate::WarningKind::InvalidCharacterReference
+            },
+            Warning {
+                offset: 14,
+                kind: crate::WarningKind::InvalidCharacterReference
+            },
+            Warning {
+                offset: 23,
+                kind: crate::WarningKind::UnescapedAmpersand
+            },
+        ],
+        doc.warnings()
+    );
+}
+
+#[test]
+fn lenient_attributes() {
+    let input = "<input checked width=100 href=/a/b name=\"x\"/>";
+    assert_eq!(Err(Error::Syntax { offset: 14 }), Document::parse(input));
+    let options = ParseOptions::new().lenient(true).flag_value("yes");
+    let doc = Document::parse_with(input, &options).unwrap();
+    assert_eq!(
+        "<input checked=\"yes\" width=\"100\" href=\"/a/b\" name=\"x\"/>",
+        doc.root().to_string()
+    );
+    let kinds: Vec<_> = doc.warnings().iter().map(|w| (w.offset, w.kind)).collect();
+    assert_eq!(
+        vec![
+            (7, crate::WarningKind::ValuelessAttribute),
+            (15, crate::WarningKind::UnquotedAttributeValue),
+            (25, crate::WarningKind::UnquotedAttributeValue),
+        ],
+        kinds
+    );
+}
+
+#[test]
+fn prolog_and_epilog() {
+    let input = "<?xml version=\"1.0\"?>\n<?xml-stylesheet href=\"a.css\"?>\n<!-- top -->\n<a><!--in--></a>\n<!-- end -->\n";
+    let document = Document::parse(input).unwrap();
+    assert_eq!(
+        [
+            Node::ProcessingInstruction {
+                target: "xml-stylesheet".to_string(),
+                data: "href=\"a.css\"".to_string(),
+            },
+            Node::Comment(" top ".to_string()),
+        ],
+        document.prolog()
+    );
+    assert_eq!([Node::Comment(" end ".to_string())], document.epilog());
+    assert_eq!(input.trim_end(), document.to_string());
+    assert!(Document::parse("<a/><b/>").is_err());
+}
+
+#[test]
+fn doctype_kept() {
+    let input = "<!-- a -->\n<!DOCTYPE a [\n  <!ENTITY e \"x\">\n]>\n<!-- b -->\n<a>&e;</a>";
+    let doc = Document::parse(input).unwrap();
+    assert_eq!(
+        Some("<!DOCTYPE a [\n  <!ENTITY e \"x\">\n]>"),
+        doc.doctype()
+    );
+    assert_eq!(input.replace("&e;", "x"), doc.to_string());
+    assert_eq!(Ok(input.to_string()), crate::rewrite(input, |_| {}));
+    assert_eq!(None, Document::parse("<a/>").unwrap().doctype());
+}
+
+#[test]
+fn content_before_start() {
+    use crate::{Warning, WarningKind};
+
+    let doc = Document::parse("\u{FEFF}<?xml version=\"1.0\"?><a/>").unwrap();
+    assert_eq!("a", doc.root().name);
+    assert!(Document::parse(" \n<a/>").is_ok());
+    assert_eq!(
+        Err(Error::ContentBeforeStart { offset: 0 }),
+        Document::parse(" <?xml version=\"1.0\"?><a/>")
+    );
+    let junk = "\u{FEFF}\u{1}junk<a/>";
+    assert_eq!(
+        Err(Error::ContentBeforeStart { offset: 3 }),
+        Document::parse(junk)
+    );
+    let doc = Document::parse_with(junk, &ParseOptions::new().lenient(true)).unwrap();
+    assert_eq!(
+        &[Warning {
+            offset: 3,
+            kind: WarningKind::ContentBeforeStart
+        }],
+        doc.warnings()
+    );
+}
@@ -0,0 +1,243 @@
+use crate::parser::name_end;
+use crate::prelude::*;
+use crate::{reference, Context, ParseResult};
+
+/// The most levels of entities inside entities expanded by default.
+pub const DEFAULT_MAX_ENTITY_DEPTH: usize = 8;
+/// The most bytes of text entity expansion produces by default, over a
+/// whole document.
+pub const DEFAULT_MAX_ENTITY_EXPANSION: usize = 1 << 20;
+
+// Reads a `<!DOCTYPE>`, recording the general entities its internal subset
+// declares in `context`. Everything else in it is skipped: element and
+// attribute-list declarations are not enforced, and external entities and
+// parameter entities are never loaded.
+pub(crate) fn doctype_at<'a>(context: &Context, input: &'a str) -> ParseResult<'a, ()> {
+    let body = input.strip_prefix("<!DOCTYPE").ok_or(input)?;
+    let mut rest = body.trim_start();
+    if rest.len() == body.len() {
+        return Err(body);
+    }
+    rest = &rest[name_end(rest).ok_or(rest)?..];
+    loop {
+        rest = rest.trim_start();
+        match rest.chars().next() {
+            Some('>') => return Ok((&rest[1..], ())),
+            Some('[') => rest = internal_subset(context, &rest[1..])?,
+            Some('"') | Some('\'') => rest = quoted(rest)?.0,
+            Some(_) => match name_end(rest) {
+                // SYSTEM and PUBLIC with their literals.
+                Some(len) => rest = &rest[len..],
+                None => return Err(rest),
+            },
+            None => return Err(rest),
+        }
+    }
+}
+
+// The declarations up to and past the closing `]`.
+fn internal_subset<'a>(context: &Context, mut input: &'a str) -> Result<&'a str, &'a str> {
+    loop {
+        input = input.trim_start();
+        if let Some(rest) = input.strip_prefix(']') {
+            return Ok(rest);
+        }
+        if let Some(body) = input.strip_prefix("<!--") {
+            input = &body[body.find("-->").ok_or(input)? + 3..];
+        } else if input.starts_with("<?") {
+            input = &input[input.find("?>").ok_or(input)? + 2..];
+        } else if let Some(body) = input.strip_prefix("<!ENTITY") {
+            input = entity_declaration(context, body)?;
+        } else if input.starts_with("<!") {
+            input = declaration_end(input)?;
+        } else if let Some(body) = input.strip_prefix('%') {
+            input = &body[body.find(';').ok_or(input)? + 1..];
+        } else {
+            return Err(input);
+        }
+    }
+}
+
+fn entity_declaration<'a>(context: &Context, input: &'a str) -> Result<&'a str, &'a str> {
+    let rest = input.trim_start();
+    if rest.len() == input.len() {
+        return Err(input);
+    }
+    if rest.starts_with('%') {
+        return declaration_end(rest);
+    }
+    let len = name_end(rest).ok_or(rest)?;
+    let name = &rest[..len];
+    let rest = rest[len..].trim_start();
+    if !rest.starts_with(['"', '\'']) {
+        // An external entity, which is not loaded.
+        return declaration_end(rest);
+    }
+    let (rest, value) = quoted(rest)?;
+    let rest = rest.trim_start().strip_prefix('>').ok_or(rest)?;
+    context.define_entity(name, value);
+    Ok(rest)
+}
+
+// Skips to past the `>` ending a markup declaration, ignoring any inside
+// quoted literals.
+fn declaration_end(input: &str) -> Result<&str, &str> {
+    let mut quote = None;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (None, '>') => return Ok(&input[i + 1..]),
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+    }
+    Err(input)
+}
+
+fn quoted(input: &str) -> Result<(&str, &str), &str> {
+    let quote = input.chars().next().ok_or(input)?;
+    let end = input[1..].find(quote).ok_or(input)?;
+    Ok((&input[end + 2..], &input[1..end + 1]))
+}
+
+// Why an entity could not be expanded.
+pub(crate) enum Expansion {
+    Undefined,
+    OverLimit,
+}
+
+impl Context {
+    /// Appends the replacement text of entity `name` to `out`, expanding the
+    /// references inside it. The text is character data: markup in it is
+    /// kept literally.
+    pub(crate) fn expand_entity(&self, name: &str, out: &mut String) -> Result<(), Expansion> {
+        self.expand_at_depth(name, 1, out)
+    }
+
+    fn expand_at_depth(&self, name: &str, depth: usize, out: &mut String) -> Result<(), Expansion> {
+        let value = self.entity(name).ok_or(Expansion::Undefined)?;
+        if depth > self.options.entity_depth_limit() {
+            return Err(Expansion::OverLimit);
+        }
+        // Each reference costs at least its own length, so entities that
+        // expand to nothing can't fan out unchecked.
+        self.count_expansion(name.len() + 2)?;
+        let mut rest = value.as_str();
+        while !rest.is_empty() {
+            let run = rest.find('&').unwrap_or(rest.len());
+            self.count_expansion(run)?;
+            out.push_str(&rest[..run]);
+            rest = &rest[run..];
+            if rest.is_empty() {
+                break;
+            }
+            if let Ok((next, c)) = reference(rest) {
+                let c = c.unwrap_or('\u{FFFD}');
+                self.count_expansion(c.len_utf8())?;
+                out.push(c);
+                rest = next;
+                continue;
+            }
+            match entity_reference(rest) {
+                Some((next, inner)) => {
+                    match self.expand_at_depth(inner, depth + 1, out) {
+                        Err(Expansion::Undefined) => out.push_str(&rest[..rest.len() - next.len()]),
+                        result => result?,
+                    }
+                    rest = next;
+                }
+                None => {
+                    self.count_expansion(1)?;
+                    out.push('&');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn count_expansion(&self, bytes: usize) -> Result<(), Expansion> {
+        let total = self.expanded.get() + bytes;
+        self.expanded.set(total);
+        match total > self.options.entity_expansion_limit() {
+            true => Err(Expansion::OverLimit),
+            false => Ok(()),
+        }
+    }
+}
+
+// Splits a `&name;` reference off the start of `input`.
+pub(crate) fn entity_reference(input: &str) -> Option<(&str, &str)> {
+    let body = input.strip_prefix('&')?;
+    let len = name_end(body)?;
+    let rest = body[len..].strip_prefix(';')?;
+    Some((rest, &body[..len]))
+}
+
+#[test]
+fn entity_limits() {
+    use crate::{Document, Error, ParseOptions};
+
+    let doc = Document::parse(
+        "<!DOCTYPE a [\n  <!ELEMENT a ANY>\n  <!ENTITY who \"the &amp; <world>\">\n  <!ENTITY hi 'hello &who;'>\n]>\n<a>&hi;!</a>",
+    )
+    .unwrap();
+    assert_eq!(
+        "<a>hello the &amp; &lt;world&gt;!</a>",
+        doc.root().to_string()
+    );
+
+    // Each level multiplies the text tenfold.
+    let mut laughs = String::from("<!DOCTYPE lolz [<!ENTITY lol0 \"lol\">");
+    for i in 1..10 {
+        let refs = format!("&lol{};", i - 1).repeat(10);
+        laughs.push_str(&format!("<!ENTITY lol{} \"{}\">", i, refs));
+    }
+    laughs.push_str("]><lolz>&lol9;</lolz>");
+    let offset = laughs.find("&lol9;").unwrap();
+    assert_eq!(Err(Error::EntityLimit { offset }), Document::parse(&laughs));
+    assert_eq!(
+        Err(Error::EntityLimit { offset }),
+        Document::parse_with(&laughs, &ParseOptions::new().max_entity_depth(100))
+    );
+    // Empty leaves produce no text, but the references are still counted.
+    let mut empty = String::from("<!DOCTYPE a [<!ENTITY e0 \"\">");
+    for i in 1..8 {
+        let refs = format!("&e{};", i - 1).repeat(10);
+        empty.push_str(&format!("<!ENTITY e{} \"{}\">", i, refs));
+    }
+    empty.push_str("]><a>&e7;</a>");
+    let offset = empty.find("&e7;").unwrap();
+    assert_eq!(Err(Error::EntityLimit { offset }), Document::parse(&empty));
+    let deep = "<!DOCTYPE a [<!ENTITY a \"&b;\"><!ENTITY b \"&a;\">]><a>&a;</a>";
+    assert!(matches!(
+        Document::parse(deep),
+        Err(Error::EntityLimit { .. })
+    ));
+}
+
+#[test]
+fn custom_entities() {
+    use crate::{Document, ParseOptions};
+
+    let options = ParseOptions::new().with_entities(&[("nbsp", "\u{a0}"), ("co", "Acme&nbsp;Inc")]);
+    let doc = Document::parse_with("<p>&co; &amp; sons</p>", &options).unwrap();
+    assert_eq!("<p>Acme\u{a0}Inc &amp; sons</p>", doc.root().to_string());
+    let declared = "<!DOCTYPE p [<!ENTITY co \"Other\">]><p>&co;</p>";
+    let doc = Document::parse_with(declared, &options).unwrap();
+    assert_eq!("<p>Other</p>", doc.root().to_string());
+    assert!(Document::parse_with("<p>&copy;</p>", &options).is_err());
+}
+
+#[test]
+fn reused_parser_starts_over() {
+    use crate::{element_with, Node, ParseOptions, Parser};
+
+    // The limit is counted per input, not over the life of the parser.
+    let value = "x".repeat(1000);
+    let parser = element_with(ParseOptions::new().with_entities(&[("x", &value)]));
+    for _ in 0..2000 {
+        let (_, el) = parser.parse("<a>&x;</a>").unwrap();
+        assert_eq!(vec![Node::Text(value.clone())], el.children);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::prelude::*;
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn from_label(label: &str) -> Option<Encoding> {
+        match label.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "utf-16" | "utf-16be" => Some(Encoding::Utf16Be),
+            "utf-16le" => Some(Encoding::Utf16Le),
+            "iso-8859-1" | "iso_8859-1" | "latin1" | "latin-1" | "l1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            // The byte order is carried by the BOM, not the label.
+            Encoding::Utf16Le | Encoding::Utf16Be => "UTF-16",
+            Encoding::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Utf8 | Encoding::Latin1 => &[],
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, Error> {
+        let decode_error = |offset| Error::Decode {
+            encoding: *self,
+            offset,
+        };
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec())
+                .map_err(|e| decode_error(e.utf8_error().valid_up_to())),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if bytes.len() % 2 == 1 {
+                    return Err(decode_error(bytes.len() - 1));
+                }
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                let mut decoded = String::with_capacity(bytes.len() / 2);
+                let mut offset = 0;
+                for c in char::decode_utf16(units) {
+                    let c = c.map_err(|_| decode_error(offset))?;
+                    offset += c.len_utf16() * 2;
+                    decoded.push(c);
+                }
+                Ok(decoded)
+            }
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Encoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf16Le => Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16Be => Ok(text.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|ch| {
+                    if (ch as u32) < 0x100 {
+                        Ok(ch as u8)
+                    } else {
+                        Err(Error::Unencodable {
+                            encoding: *self,
+                            ch,
+                        })
+                    }
+                })
+                .collect(),
+        }
+    }
+}
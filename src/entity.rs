@@ -0,0 +1,72 @@
+use crate::ParseResult;
+
+pub fn is_xml_char(c: char) -> bool {
+    matches!(c,
+        '\u{9}' | '\u{A}' | '\u{D}'
+        | '\u{20}'..='\u{D7FF}'
+        | '\u{E000}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// Parses one of the predefined entity references or a character reference.
+/// A well-formed character reference to a code point that is not an XML
+/// `Char` yields `None`.
+pub fn reference(input: &str) -> ParseResult<'_, Option<char>> {
+    let body = match input.strip_prefix('&') {
+        Some(body) => body,
+        None => return Err(input),
+    };
+    let end = match body.find(';') {
+        Some(end) => end,
+        None => return Err(input),
+    };
+    let rest = &body[end + 1..];
+    let code = match &body[..end] {
+        "lt" => return Ok((rest, Some('<'))),
+        "gt" => return Ok((rest, Some('>'))),
+        "amp" => return Ok((rest, Some('&'))),
+        "apos" => return Ok((rest, Some('\''))),
+        "quot" => return Ok((rest, Some('"'))),
+        name => match name.strip_prefix('#') {
+            Some(hex) if hex.starts_with('x') => parse_code(&hex[1..], 16),
+            Some(decimal) => parse_code(decimal, 10),
+            None => None,
+        },
+    };
+    match code {
+        Some(code) => Ok((rest, char::from_u32(code).filter(|c| is_xml_char(*c)))),
+        None => Err(input),
+    }
+}
+
+// Unlike `u32::from_str_radix`, rejects signs and treats overflow as an
+// out-of-range code point rather than a malformed reference.
+fn parse_code(digits: &str, radix: u32) -> Option<u32> {
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    Some(u32::from_str_radix(digits, radix).unwrap_or(u32::MAX))
+}
+
+// The extent of a broken `&#...` reference: digits, letters and a closing `;`.
+pub(crate) fn malformed_char_reference(input: &str) -> Option<&str> {
+    let body = input.strip_prefix("&#")?;
+    let end = body
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(body.len());
+    let rest = &body[end..];
+    Some(rest.strip_prefix(';').unwrap_or(rest))
+}
+
+#[test]
+fn reference_parser() {
+    assert_eq!(Ok((" b", Some('<'))), reference("&lt; b"));
+    assert_eq!(Ok(("", Some('A'))), reference("&#65;"));
+    assert_eq!(Ok(("", Some('\u{1F600}'))), reference("&#x1F600;"));
+    assert_eq!(Ok(("", None)), reference("&#xD800;"));
+    assert_eq!(Ok(("", None)), reference("&#99999999999;"));
+    assert_eq!(Err("&#xZZ;"), reference("&#xZZ;"));
+    assert_eq!(Err("&#+1;"), reference("&#+1;"));
+    assert_eq!(Err("&nbsp;"), reference("&nbsp;"));
+    assert_eq!(Err("& b;"), reference("& b;"));
+}
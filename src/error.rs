@@ -0,0 +1,372 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::prelude::*;
+use crate::{Encoding, ParseResult};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The input is not well-formed; `offset` is the byte offset of the failure.
+    Syntax { offset: usize },
+    /// Expanding entities went past `ParseOptions::max_entity_depth` or
+    /// `max_entity_expansion` at the reference at `offset`.
+    EntityLimit { offset: usize },
+    /// An attribute hook rejected the value of attribute `name`, which
+    /// starts at `offset`.
+    InvalidAttributeValue { name: String, offset: usize },
+    /// Something other than whitespace comes before the first `<`, or
+    /// whitespace comes before the XML declaration; `offset` is where it
+    /// starts.
+    ContentBeforeStart { offset: usize },
+    /// The XML declaration names an encoding this crate cannot decode.
+    UnknownEncoding(String),
+    /// The bytes are not valid in the encoding they were declared as.
+    Decode { encoding: Encoding, offset: usize },
+    /// A character cannot be represented in the requested output encoding.
+    Unencodable { encoding: Encoding, ch: char },
+    /// Serialized output would exceed `SerializeOptions::max_size`.
+    OutputTooLarge { limit: usize },
+    /// Merging attributes found two different values for this name.
+    AttributeConflict(String),
+    /// A schema is inconsistent or uses a construct this crate does not support.
+    Schema(String),
+    /// An `xi:include` is malformed or leads back to itself.
+    Include(String),
+    /// An element does not have the shape a `FromXml` type expects.
+    Mapping(String),
+    /// A template has an unclosed placeholder, or no value was given for one.
+    Template(String),
+    /// An XML patch operation is malformed or its `sel` does not pick one
+    /// node.
+    Patch(String),
+    /// JSON passed to `Element::from_json`, or a tree passed to
+    /// `wasm::serialize`, does not describe an element.
+    #[cfg(feature = "json")]
+    Json(String),
+    /// Reading the input failed.
+    #[cfg(feature = "std")]
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io {
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A malformed or out-of-range character reference became U+FFFD.
+    InvalidCharacterReference,
+    /// An `&` that starts no known reference was kept as is.
+    UnescapedAmpersand,
+    /// An attribute value was not quoted.
+    UnquotedAttributeValue,
+    /// An attribute had no value and was given `ParseOptions::flag_value`.
+    ValuelessAttribute,
+    /// Bytes before the start of the document were skipped.
+    ContentBeforeStart,
+}
+
+/// Something lenient parsing accepted that strict parsing would reject.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub offset: usize,
+    pub kind: WarningKind,
+}
+
+/// Detaches a `ParseResult` error from the input, so a function that owns
+/// its input can still return the error: `element().parse(&text)
+/// .into_owned(&text)?` gives an element that outlives `text`.
+pub trait IntoOwned<'a, T> {
+    /// Replaces the remaining input of a failure with an `Error::Syntax`
+    /// at its offset into `input`, the input that was parsed.
+    fn into_owned(self, input: &'a str) -> Result<(&'a str, T), Error>;
+}
+
+impl<'a, T> IntoOwned<'a, T> for ParseResult<'a, T> {
+    fn into_owned(self, input: &'a str) -> Result<(&'a str, T), Error> {
+        self.map_err(|rest| Error::Syntax {
+            offset: input.len() - rest.len(),
+        })
+    }
+}
+
+impl Error {
+    /// The byte offset into the input this error refers to, if any.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::Syntax { offset }
+            | Error::EntityLimit { offset }
+            | Error::InvalidAttributeValue { offset, .. }
+            | Error::ContentBeforeStart { offset }
+            | Error::Decode { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The 1-based line and column in `source` this error points at.
+    pub fn line_column(&self, source: &str) -> Option<(usize, usize)> {
+        let (line_start, line, _) = locate(source, self.offset()?);
+        let column = source[line_start..floor_offset(source, self.offset()?)]
+            .chars()
+            .count();
+        Some((line + 1, column + 1))
+    }
+
+    /// A multi-line diagnostic quoting the offending line of `source`:
+    ///
+    /// ```text
+    /// error: syntax error at byte 6
+    ///  --> 2:3
+    ///   |
+    /// 2 |   <b></c>
+    ///   |   ^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        render(&format!("{}", self), source, self.offset())
+    }
+
+    /// Wraps the error so that its `Display` output is `render(source)`.
+    pub fn with_source<'a>(&'a self, source: &'a str) -> WithSource<'a> {
+        WithSource {
+            error: self,
+            source,
+        }
+    }
+}
+
+fn render(message: &str, source: &str, offset: Option<usize>) -> String {
+    let mut out = format!("error: {}\n", message);
+    let offset = match offset {
+        Some(offset) => offset,
+        None => return out,
+    };
+    let (line_start, line, text) = locate(source, offset);
+    let column = source[line_start..floor_offset(source, offset)]
+        .chars()
+        .count();
+    let number = (line + 1).to_string();
+    let gutter = " ".repeat(number.len());
+    out.push_str(&format!("{}--> {}:{}\n", gutter, line + 1, column + 1));
+    out.push_str(&format!("{} |\n", gutter));
+    out.push_str(&format!("{} | {}\n", number, text));
+    out.push_str(&format!("{} | {}^\n", gutter, " ".repeat(column)));
+    out
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxErrorKind {
+    /// A quoted attribute value has no closing quote.
+    UnterminatedValue,
+    /// Something other than a name, `=`, a value or the end of the tag.
+    ExpectedName,
+    /// The input ended inside a tag or comment.
+    UnexpectedEnd,
+    /// A closing tag does not match the open element.
+    MismatchedTag,
+    /// A reference names no character or declared entity, or expanding it
+    /// goes past the entity limits.
+    InvalidReference,
+    /// Markup or text where it is not allowed.
+    Unexpected,
+}
+
+/// The error of the pull path (`Tokens`). It is `Copy` and never allocates,
+/// so rejecting malformed input in a hot loop costs no more than accepting
+/// it; `into_detailed` does the expensive part for failures that get
+/// reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub offset: usize,
+    pub kind: SyntaxErrorKind,
+}
+
+impl SyntaxError {
+    /// Resolves the error against the input it came from.
+    pub fn into_detailed(self, source: &str) -> DetailedError {
+        let line_column = Error::from(self).line_column(source);
+        let (line, column) = line_column.unwrap_or((1, 1));
+        DetailedError {
+            error: self,
+            line,
+            column,
+            rendered: render(&format!("{}", self), source, Some(self.offset)),
+        }
+    }
+}
+
+impl From<SyntaxError> for Error {
+    fn from(error: SyntaxError) -> Error {
+        Error::Syntax {
+            offset: error.offset,
+        }
+    }
+}
+
+/// A `SyntaxError` with its 1-based position and a rendered diagnostic in
+/// the style of `Error::render`, which is also its `Display` output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetailedError {
+    pub error: SyntaxError,
+    pub line: usize,
+    pub column: usize,
+    rendered: String,
+}
+
+impl fmt::Display for DetailedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.rendered)
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            SyntaxErrorKind::UnterminatedValue => "unterminated attribute value",
+            SyntaxErrorKind::ExpectedName => "expected a name",
+            SyntaxErrorKind::UnexpectedEnd => "unexpected end of input",
+            SyntaxErrorKind::MismatchedTag => "mismatched closing tag",
+            SyntaxErrorKind::InvalidReference => "invalid reference",
+            SyntaxErrorKind::Unexpected => "unexpected input",
+        };
+        write!(f, "{} at byte {}", what, self.offset)
+    }
+}
+
+pub struct WithSource<'a> {
+    error: &'a Error,
+    source: &'a str,
+}
+
+impl<'a> fmt::Display for WithSource<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.error.render(self.source))
+    }
+}
+
+fn floor_offset(source: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+// Finds the start, 0-based number and text of the line containing `offset`.
+fn locate(source: &str, offset: usize) -> (usize, usize, &str) {
+    let offset = floor_offset(source, offset);
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = source[..line_start].matches('\n').count();
+    let line_end = source[line_start..]
+        .find('\n')
+        .map_or(source.len(), |i| line_start + i);
+    (
+        line_start,
+        line,
+        source[line_start..line_end].trim_end_matches('\r'),
+    )
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Syntax { offset } => write!(f, "syntax error at byte {}", offset),
+            Error::EntityLimit { offset } => {
+                write!(f, "entity expansion limit exceeded at byte {}", offset)
+            }
+            Error::InvalidAttributeValue { name, offset } => {
+                write!(
+                    f,
+                    "invalid value for attribute {:?} at byte {}",
+                    name, offset
+                )
+            }
+            Error::ContentBeforeStart { offset } => {
+                write!(
+                    f,
+                    "unexpected bytes before document start at byte {}",
+                    offset
+                )
+            }
+            Error::UnknownEncoding(label) => write!(f, "unsupported encoding {:?}", label),
+            Error::Decode { encoding, offset } => {
+                write!(f, "invalid {} data at byte {}", encoding.label(), offset)
+            }
+            Error::Unencodable { encoding, ch } => {
+                write!(f, "{:?} cannot be encoded as {}", ch, encoding.label())
+            }
+            Error::OutputTooLarge { limit } => {
+                write!(f, "serialized output exceeds {} bytes", limit)
+            }
+            Error::AttributeConflict(name) => {
+                write!(f, "conflicting values for attribute {:?}", name)
+            }
+            Error::Schema(message) => write!(f, "invalid schema: {}", message),
+            Error::Include(message) => write!(f, "cannot resolve include: {}", message),
+            Error::Mapping(message) => write!(f, "cannot map element: {}", message),
+            Error::Template(message) => write!(f, "cannot render template: {}", message),
+            Error::Patch(message) => write!(f, "cannot apply patch: {}", message),
+            #[cfg(feature = "json")]
+            Error::Json(message) => write!(f, "invalid JSON element: {}", message),
+            #[cfg(feature = "std")]
+            Error::Io { message, .. } => write!(f, "I/O error: {}", message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SyntaxError {}
+
+#[test]
+fn render_diagnostic() {
+    let source = "<a>\n  <b\u{e9}></c>\n</a>";
+    let error = source.parse::<crate::Element>().unwrap_err();
+    assert_eq!(Some((2, 7)), error.line_column(source));
+    assert_eq!(
+        "error: syntax error at byte 11\n --> 2:7\n  |\n2 |   <b\u{e9}></c>\n  |       ^\n",
+        error.render(source)
+    );
+    assert_eq!(error.render(source), error.with_source(source).to_string());
+    assert_eq!(
+        "error: serialized output exceeds 4 bytes\n",
+        Error::OutputTooLarge { limit: 4 }.render(source)
+    );
+}
+
+#[test]
+fn detailed_syntax_error() {
+    let error = SyntaxError {
+        offset: 9,
+        kind: SyntaxErrorKind::UnterminatedValue,
+    };
+    let detailed = error.into_detailed("<a>\n<b x=\"1></b>");
+    assert_eq!((2, 6), (detailed.line, detailed.column));
+    assert_eq!(
+        "error: unterminated attribute value at byte 9\n --> 2:6\n  |\n2 | <b x=\"1></b>\n  |      ^\n",
+        detailed.to_string()
+    );
+    assert_eq!(Error::Syntax { offset: 9 }, Error::from(error));
+}
+
+#[test]
+fn owned_parse_error() {
+    use crate::{element, Element, Parser};
+    fn load(text: String) -> Result<Element, Error> {
+        let (_, el) = element().parse(&text).into_owned(&text)?;
+        Ok(el)
+    }
+    assert_eq!(Err(Error::Syntax { offset: 3 }), load("<a>".to_string()));
+    assert_eq!(Ok(Element::new("a")), load("<a/>".to_string()));
+}
@@ -0,0 +1,61 @@
+use crate::prelude::*;
+use crate::{SyntaxError, Token, Tokens};
+
+/// Streams through `input` collecting the values of the `attr_names`
+/// attributes on every `element_name` start tag, as `(attribute, value)`
+/// pairs in document order, without building a tree. Values are borrowed
+/// from the input as written, with references undecoded, unlike
+/// `Element::attribute`.
+/// Tags are not checked for nesting, so this accepts some inputs the tree
+/// parsers reject. Declarations such as `<!DOCTYPE html>`, CDATA sections
+/// and processing instructions are skipped whole.
+pub fn extract_attributes<'a>(
+    input: &'a str,
+    element_name: &str,
+    attr_names: &[&str],
+) -> Result<Vec<(&'a str, &'a str)>, SyntaxError> {
+    let mut found = Vec::new();
+    let mut tag_open = false;
+    let mut matching = false;
+    let mut attribute = None;
+    for token in Tokens::new(input) {
+        match token?.0 {
+            Token::LAngle => tag_open = true,
+            Token::TagName(name) => {
+                matching = tag_open && name == element_name;
+                tag_open = false;
+            }
+            Token::AttrName(name) => attribute = Some(name),
+            Token::AttrValue(value) if matching => {
+                if let Some(name) = attribute.filter(|name| attr_names.contains(name)) {
+                    found.push((name, value));
+                }
+            }
+            Token::RAngle | Token::SelfClose => matching = false,
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+#[test]
+fn attribute_extraction() {
+    let input = "<html><a href=\"/one\" id=\"x\">1</a><!-- <a href=\"no\"/> -->\
+                 <p><a name=\"n\" href='/two'/></p><link href=\"no\"/></html>";
+    assert_eq!(
+        Ok(vec![("href", "/one"), ("href", "/two")]),
+        extract_attributes(input, "a", &["href"])
+    );
+    assert_eq!(
+        Ok(vec![("href", "/one"), ("id", "x"), ("href", "/two")]),
+        extract_attributes(input, "a", &["id", "href"])
+    );
+    assert!(extract_attributes("<a href=\"x>", "a", &["href"]).is_err());
+
+    let page = "<!DOCTYPE html>\n<?php if ($a > 1) echo '<a href=\"no\">'; ?>\
+                <html><![CDATA[<a href=\"no\">]]><a href=\"/three\"/></html>";
+    assert_eq!(
+        Ok(vec![("href", "/three")]),
+        extract_attributes(page, "a", &["href"])
+    );
+}
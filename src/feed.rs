@@ -0,0 +1,164 @@
+use core::str::FromStr;
+
+use crate::prelude::*;
+use crate::{Document, Element, Error, FromXml, Node};
+
+/// A syndication feed read from RSS 2.0 or Atom 1.0. Dates are kept as
+/// written: RFC 822 in RSS, RFC 3339 in Atom.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Feed {
+    pub title: String,
+    pub link: Option<String>,
+    /// RSS `lastBuildDate` or `pubDate`, Atom `updated`.
+    pub date: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+/// An RSS `item` or an Atom `entry`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Entry {
+    pub title: String,
+    pub link: Option<String>,
+    /// RSS `guid`, Atom `id`.
+    pub id: Option<String>,
+    /// RSS `pubDate`, Atom `published` or else `updated`.
+    pub date: Option<String>,
+    /// RSS `content:encoded` or else `description`, Atom `content` or else
+    /// `summary`, as text: HTML in it is not parsed.
+    pub content: Option<String>,
+}
+
+impl Feed {
+    pub fn parse(input: &str) -> Result<Feed, Error> {
+        Feed::from_xml(Document::parse(input)?.root())
+    }
+}
+
+impl FromStr for Feed {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Feed, Error> {
+        Feed::parse(input)
+    }
+}
+
+impl FromXml for Feed {
+    /// Reads an `<rss>` or Atom `<feed>` root, ignoring the elements it does
+    /// not know, including those of extension namespaces.
+    fn from_xml(el: &Element) -> Result<Feed, Error> {
+        match el.local_name() {
+            "rss" => {
+                let channel = children(el, "channel")
+                    .next()
+                    .ok_or_else(|| Error::Mapping("<rss> has no <channel>".to_string()))?;
+                Ok(Feed {
+                    title: text(channel, "title").unwrap_or_default(),
+                    link: text(channel, "link"),
+                    date: text(channel, "lastBuildDate").or_else(|| text(channel, "pubDate")),
+                    entries: children(channel, "item").map(rss_item).collect(),
+                })
+            }
+            "feed" => Ok(Feed {
+                title: text(el, "title").unwrap_or_default(),
+                link: atom_link(el),
+                date: text(el, "updated"),
+                entries: children(el, "entry").map(atom_entry).collect(),
+            }),
+            other => Err(Error::Mapping(format!(
+                "<{}> is neither an RSS nor an Atom feed",
+                other
+            ))),
+        }
+    }
+}
+
+fn rss_item(item: &Element) -> Entry {
+    Entry {
+        title: text(item, "title").unwrap_or_default(),
+        link: text(item, "link"),
+        id: text(item, "guid"),
+        date: text(item, "pubDate"),
+        content: text(item, "encoded").or_else(|| text(item, "description")),
+    }
+}
+
+fn atom_entry(entry: &Element) -> Entry {
+    Entry {
+        title: text(entry, "title").unwrap_or_default(),
+        link: atom_link(entry),
+        id: text(entry, "id"),
+        date: text(entry, "published").or_else(|| text(entry, "updated")),
+        content: text(entry, "content").or_else(|| text(entry, "summary")),
+    }
+}
+
+// The `href` of the `alternate` link, which is what a link without `rel` is.
+fn atom_link(el: &Element) -> Option<String> {
+    children(el, "link")
+        .find(|link| matches!(link.attribute("rel"), None | Some("alternate")))
+        .and_then(|link| link.attribute("href"))
+        .map(str::to_string)
+}
+
+// Children are matched by local name, so any prefix goes.
+fn children<'a>(el: &'a Element, local: &'a str) -> impl Iterator<Item = &'a Element> {
+    el.children.iter().filter_map(move |child| match child {
+        Node::Element(child) if child.local_name() == local => Some(child),
+        _ => None,
+    })
+}
+
+fn text(el: &Element, local: &str) -> Option<String> {
+    children(el, local).next().map(Element::text_content)
+}
+
+#[test]
+fn rss_and_atom() {
+    let rss = Feed::parse(
+        r#"<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+            <channel>
+                <title>News</title>
+                <link>https://example.org/</link>
+                <item>
+                    <title>First</title>
+                    <guid>1</guid>
+                    <pubDate>Mon, 06 Sep 2021 16:45:00 GMT</pubDate>
+                    <description>Short</description>
+                    <content:encoded>&lt;p&gt;Long&lt;/p&gt;</content:encoded>
+                </item>
+            </channel>
+        </rss>"#,
+    )
+    .unwrap();
+    assert_eq!("News", rss.title);
+    assert_eq!(Some("<p>Long</p>"), rss.entries[0].content.as_deref());
+    assert_eq!(Some("1"), rss.entries[0].id.as_deref());
+
+    let atom = Feed::parse(
+        r#"<feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Blog</title>
+            <link rel="self" href="https://example.org/feed"/>
+            <link href="https://example.org/"/>
+            <updated>2021-09-06T16:45:00Z</updated>
+            <entry>
+                <title>Post</title>
+                <id>urn:uuid:1</id>
+                <updated>2021-09-06T16:45:00Z</updated>
+                <summary>Hi</summary>
+            </entry>
+        </feed>"#,
+    )
+    .unwrap();
+    assert_eq!(Some("https://example.org/"), atom.link.as_deref());
+    assert_eq!(
+        Entry {
+            title: "Post".to_string(),
+            link: None,
+            id: Some("urn:uuid:1".to_string()),
+            date: Some("2021-09-06T16:45:00Z".to_string()),
+            content: Some("Hi".to_string()),
+        },
+        atom.entries[0]
+    );
+    assert!(matches!(Feed::parse("<html/>"), Err(Error::Mapping(_))));
+}
@@ -0,0 +1,88 @@
+use alloc::rc::Rc;
+
+use crate::prelude::*;
+use crate::{node_in, zero_or_more, Context, Element, Error, Node, ParseOptions, Parser, Warning};
+
+/// Parses a run of sibling nodes with no single root, such as the content
+/// of an element copied out of a larger document: any mix of elements,
+/// text, comments and processing instructions, or nothing at all.
+pub fn parse_fragment(input: &str) -> Result<Vec<Node>, Error> {
+    parse_fragment_with(input, &ParseOptions::default())
+}
+
+/// Drops the lenient-mode warnings, which `parse_fragment_with_warnings`
+/// returns.
+pub fn parse_fragment_with(input: &str, options: &ParseOptions) -> Result<Vec<Node>, Error> {
+    parse_fragment_with_warnings(input, options).map(|(nodes, _)| nodes)
+}
+
+/// The nodes, and the warnings lenient mode gave for them, as
+/// `Document::warnings` has them for a document.
+pub fn parse_fragment_with_warnings(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<Node>, Vec<Warning>), Error> {
+    let context = Context::new(options.clone());
+    parse_fragment_in(input, &fragment_in(context.clone()), &context)
+}
+
+// Whitespace and aliases are applied to the nodes as to the children of an
+// element without `xml:space`.
+pub(crate) fn fragment_in<'a>(context: Rc<Context>) -> impl Parser<'a, Vec<Node>> {
+    let nodes = zero_or_more(node_in(context.clone()));
+    move |input: &'a str| {
+        let (rest, nodes) = nodes.parse(input)?;
+        let mut wrapper = Element::new("");
+        wrapper.children = nodes;
+        context.options.finish(&mut wrapper);
+        Ok((rest, wrapper.children))
+    }
+}
+
+// Runs a parser built from `context`, which may be reused across inputs.
+pub(crate) fn parse_fragment_in<'a, P>(
+    input: &'a str,
+    parser: &P,
+    context: &Context,
+) -> Result<(Vec<Node>, Vec<Warning>), Error>
+where
+    P: Parser<'a, Vec<Node>>,
+{
+    context.reset();
+    let result = parser.parse_complete(input);
+    let warnings = context.take_warnings(input);
+    let nodes = context.take_fatal(input).map_or(result, Err)?;
+    Ok((nodes, warnings))
+}
+
+#[test]
+fn fragments() {
+    use crate::Whitespace;
+
+    let options = ParseOptions::new().whitespace(Whitespace::Preserve);
+    let nodes = parse_fragment_with("one <b>two</b><!--c--> <i/>", &options).unwrap();
+    assert_eq!(
+        vec![
+            Node::Text("one ".to_string()),
+            Node::Element("<b>two</b>".parse().unwrap()),
+            Node::Comment("c".to_string()),
+            Node::Text(" ".to_string()),
+            Node::Element(Element::new("i")),
+        ],
+        nodes
+    );
+    assert_eq!(Ok(vec![]), parse_fragment(""));
+    assert_eq!(Err(Error::Syntax { offset: 4 }), parse_fragment("<a/><b>"));
+
+    let lenient = ParseOptions::new().lenient(true);
+    let (nodes, warnings) = parse_fragment_with_warnings("a &b <i x=1/>", &lenient).unwrap();
+    assert_eq!(2, nodes.len());
+    let kinds: Vec<_> = warnings.iter().map(|w| (w.offset, w.kind)).collect();
+    assert_eq!(
+        vec![
+            (2, crate::WarningKind::UnescapedAmpersand),
+            (8, crate::WarningKind::UnquotedAttributeValue),
+        ],
+        kinds
+    );
+}
@@ -0,0 +1,120 @@
+use crate::prelude::*;
+use crate::{
+    document, element, extract_attributes, is_well_formed, parse_recovering,
+    split_at_char_boundary, truncate_chars, CstDocument, Document, Element, ParseOptions, Parser,
+    PushParser, Tokens, Whitespace,
+};
+
+/// Runs every entry point over `data`, for fuzzers: none of them may panic,
+/// whatever the input. Anything that parses must also survive being
+/// written out and parsed again.
+pub fn exercise(data: &[u8]) {
+    let _ = Document::from_bytes(data);
+    let mut push = PushParser::default();
+    let (head, tail) = data.split_at(data.len() / 2);
+    if push.feed(head).is_ok() && push.feed(tail).is_ok() {
+        let _ = push.finish();
+    }
+    let input = String::from_utf8_lossy(data);
+    let input = input.as_ref();
+    let lenient = ParseOptions::new()
+        .lenient(true)
+        .whitespace(Whitespace::Preserve);
+    for options in [
+        ParseOptions::default(),
+        lenient,
+        ParseOptions::new().html(true),
+    ] {
+        if let Ok(document) = Document::parse_with(input, &options) {
+            let _ = Document::parse(&document.to_string());
+        }
+        let _ = parse_recovering(input, &options);
+    }
+    let (result, used) = element().parse_partial(input);
+    let _ = &input[..used];
+    if let Err(error) = result {
+        let _ = error.render(input);
+        let _ = error.line_column(input);
+    }
+    let _ = document().parse(input);
+    assert_eq!(
+        Document::parse(input).is_ok(),
+        is_well_formed(input).is_ok(),
+        "is_well_formed agrees with Document::parse"
+    );
+    if let Ok(el) = input.parse::<Element>() {
+        let copy: Element = el.to_string().parse().expect("serialized output parses");
+        assert!(el.diff(&copy).is_empty());
+        let _ = el.canonicalize();
+    }
+    if let Ok(cst) = CstDocument::parse(input) {
+        assert_eq!(input, cst.to_string(), "concrete syntax trees are lossless");
+    }
+    for token in Tokens::new(input) {
+        if let Err(error) = token {
+            let _ = error.into_detailed(input);
+        }
+    }
+    let _ = extract_attributes(input, "a", &["href"]);
+    for n in 0..4 {
+        let _ = truncate_chars(input, n);
+        let _ = split_at_char_boundary(input, n * 3);
+    }
+}
+
+#[test]
+fn never_panics_on_random_input() {
+    // Fragments chosen to reach deep into the parsers, mixed with
+    // multi-byte characters that straddle every slicing decision.
+    const PIECES: [&str; 29] = [
+        "<",
+        ">",
+        "/",
+        "</",
+        "/>",
+        "<?",
+        "?>",
+        "<!--",
+        "-->",
+        "=",
+        "\"",
+        "'",
+        "&",
+        ";",
+        "#",
+        "&#x",
+        "&amp;",
+        " ",
+        "\n",
+        "a",
+        "b:c",
+        "xmlns",
+        "xml:space",
+        "\u{e9}",
+        "\u{20ac}",
+        "\u{1f600}",
+        "e\u{301}",
+        "\u{fffd}",
+        "\u{feff}",
+    ];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..3000 {
+        let mut input = String::new();
+        for _ in 0..next() % 40 {
+            input.push_str(PIECES[(next() % PIECES.len() as u64) as usize]);
+        }
+        exercise(input.as_bytes());
+        let mut bytes = input.into_bytes();
+        if !bytes.is_empty() {
+            let len = bytes.len() as u64;
+            bytes[(next() % len) as usize] = next() as u8;
+        }
+        exercise(&bytes);
+    }
+}
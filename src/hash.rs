@@ -0,0 +1,86 @@
+use crate::prelude::*;
+use crate::{Element, Node};
+
+impl Element {
+    /// A hash of the tree that ignores the order of attributes, so elements
+    /// differing only in attribute order hash alike. It is the same on every
+    /// run and platform (64-bit FNV-1a over the names and text), and can be
+    /// stored.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+        self.hash_structure(&mut hasher);
+        hasher.0
+    }
+
+    fn hash_structure(&self, state: &mut Fnv) {
+        state.str(&self.name);
+        let mut attributes: Vec<_> = self.attributes.iter().collect();
+        attributes.sort();
+        state.len(attributes.len());
+        for (name, value) in attributes {
+            state.str(name);
+            state.str(value);
+        }
+        state.len(self.children.len());
+        for child in &self.children {
+            match child {
+                Node::Element(el) => {
+                    state.write(&[0]);
+                    el.hash_structure(state);
+                }
+                Node::Text(text) => {
+                    state.write(&[1]);
+                    state.str(text);
+                }
+                Node::Comment(text) => {
+                    state.write(&[2]);
+                    state.str(text);
+                }
+                Node::ProcessingInstruction { target, data } => {
+                    state.write(&[3]);
+                    state.str(target);
+                    state.str(data);
+                }
+            }
+        }
+    }
+}
+
+// Fed only bytes the hash defines itself, so that it does not depend on how
+// the standard library hashes strings or enums.
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x100_0000_01b3);
+        }
+    }
+
+    // Lengths are hashed as eight bytes whatever the pointer width.
+    fn len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    fn str(&mut self, text: &str) {
+        self.len(text.len());
+        self.write(text.as_bytes());
+    }
+}
+
+#[test]
+fn hashing_and_ordering() {
+    use alloc::collections::BTreeSet;
+
+    let a: Element = "<a x=\"1\" y=\"2\"><b/>t</a>".parse().unwrap();
+    let b: Element = "<a y=\"2\" x=\"1\"><b/>t</a>".parse().unwrap();
+    let c: Element = "<a y=\"2\" x=\"1\"><b/>u</a>".parse().unwrap();
+    assert_eq!(a.structural_hash(), b.structural_hash());
+    assert_ne!(a.structural_hash(), c.structural_hash());
+    assert!(a < b && b < c);
+    // Stored hashes stay valid only while this does not change.
+    assert_eq!(14105852970466647716, a.structural_hash());
+
+    let set: BTreeSet<_> = vec![c.clone(), a.clone(), c].into_iter().collect();
+    assert_eq!(2, set.len());
+}
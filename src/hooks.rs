@@ -0,0 +1,135 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::cmp::Reverse;
+
+use crate::options::AttributeHook;
+use crate::prelude::*;
+use crate::{document_in, Context, Document, Error, ParseOptions, ParseResult};
+
+/// Parsers run on the values of chosen attributes while a document is
+/// parsed, so formats built on XML get typed values in the same pass.
+pub struct AttributeHooks<T> {
+    hooks: Vec<(String, Hook<T>)>,
+}
+
+type Hook<T> = Rc<dyn Fn(&str) -> Option<T>>;
+
+/// The output of a hook for one attribute, with where its value starts.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HookedValue<T> {
+    pub element: String,
+    pub attribute: String,
+    pub offset: usize,
+    pub value: T,
+}
+
+impl<T: 'static> AttributeHooks<T> {
+    pub fn new() -> AttributeHooks<T> {
+        AttributeHooks { hooks: Vec::new() }
+    }
+
+    /// Runs `parser` on the value of every attribute named `attribute`; a
+    /// combinator is registered as `|value| points().parse(value)`. It must
+    /// consume the whole value, or the parse fails with
+    /// `Error::InvalidAttributeValue`. The value is passed decoded and
+    /// normalized, as `Element::attribute` returns it.
+    pub fn on<F>(mut self, attribute: &str, parser: F) -> AttributeHooks<T>
+    where
+        F: Fn(&str) -> ParseResult<'_, T> + 'static,
+    {
+        let hook = move |value: &str| match parser(value) {
+            Ok(("", output)) => Some(output),
+            _ => None,
+        };
+        self.hooks.push((attribute.to_string(), Rc::new(hook)));
+        self
+    }
+
+    /// Parses a document, returning the hooks' outputs in document order.
+    pub fn parse(
+        &self,
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<(Document, Vec<HookedValue<T>>), Error> {
+        let hooks = self.hooks.clone();
+        // Keyed by the input left at the value: backtracking can pass the
+        // same attribute to the hook more than once.
+        let found = Rc::new(RefCell::new(BTreeMap::new()));
+        let sink = found.clone();
+        let hook = move |element: &str, attribute: &str, value: &str, remaining: usize| {
+            let parser = match hooks.iter().find(|(name, _)| name == attribute) {
+                Some((_, parser)) => parser,
+                None => return true,
+            };
+            match parser(value) {
+                Some(value) => {
+                    let hooked = (element.to_string(), attribute.to_string(), value);
+                    sink.borrow_mut().insert(Reverse(remaining), hooked);
+                    true
+                }
+                None => false,
+            }
+        };
+        let context = Context::with_attribute_hook(options.clone(), AttributeHook(Rc::new(hook)));
+        let document = Document::parse_in(input, &document_in(context.clone()), &context)?;
+        let values = found
+            .replace(BTreeMap::new())
+            .into_iter()
+            .map(
+                |(Reverse(remaining), (element, attribute, value))| HookedValue {
+                    element,
+                    attribute,
+                    offset: input.len() - remaining,
+                    value,
+                },
+            )
+            .collect();
+        Ok((document, values))
+    }
+}
+
+impl<T: 'static> Default for AttributeHooks<T> {
+    fn default() -> AttributeHooks<T> {
+        AttributeHooks::new()
+    }
+}
+
+#[test]
+fn svg_points() {
+    use crate::{left, match_literal, one_or_more, pair, right, zero_or_more, Parser};
+
+    fn number(input: &str) -> ParseResult<'_, f64> {
+        let end = input
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .unwrap_or(input.len());
+        match input[..end].parse() {
+            Ok(n) => Ok((&input[end..], n)),
+            Err(_) => Err(input),
+        }
+    }
+    fn points<'a>() -> impl Parser<'a, Vec<(f64, f64)>> {
+        one_or_more(left(
+            pair(number, right(match_literal(","), number)),
+            zero_or_more(match_literal(" ")),
+        ))
+    }
+    let hooks = AttributeHooks::new().on("points", |value| points().parse(value));
+
+    let input = r#"<svg><polygon points="1,2 3,4.5"/><polyline id="p" points="0,0"/></svg>"#;
+    let (doc, values) = hooks.parse(input, &ParseOptions::default()).unwrap();
+    assert_eq!("svg", doc.root().name());
+    assert_eq!(2, values.len());
+    assert_eq!(vec![(1.0, 2.0), (3.0, 4.5)], values[0].value);
+    assert_eq!("polygon", values[0].element);
+    assert_eq!(input.find("0,0").unwrap(), values[1].offset);
+
+    let bad = r#"<svg><polygon points="1,2 x"/></svg>"#;
+    assert_eq!(
+        Err(Error::InvalidAttributeValue {
+            name: "points".to_string(),
+            offset: bad.find("1,2").unwrap(),
+        }),
+        hooks.parse(bad, &ParseOptions::default()).map(|_| ())
+    );
+}
@@ -0,0 +1,130 @@
+use alloc::collections::BTreeMap;
+
+use crate::prelude::*;
+use crate::{Document, Element, ElementRef, Node};
+
+impl Element {
+    pub fn id(&self) -> Option<&str> {
+        self.attribute("xml:id")
+    }
+
+    /// The `xml:lang` written on this element itself. An empty value means
+    /// the language is explicitly unknown. `ElementRef::lang` gives the
+    /// language inherited from the ancestors as well.
+    pub fn lang(&self) -> Option<&str> {
+        self.attribute("xml:lang")
+    }
+
+    /// The language in effect for `descendant`, which must be this element
+    /// or one of its descendants (compared by address). Searches for it
+    /// first; when navigating already, `ElementRef::lang` does not.
+    pub fn lang_of(&self, descendant: &Element) -> Option<&str> {
+        self.navigate_to(descendant)?.lang()
+    }
+}
+
+impl<'d> ElementRef<'d> {
+    /// The language in effect here: the element's own `xml:lang`, or else
+    /// that of the nearest ancestor up to where navigation started. An
+    /// empty `xml:lang` undoes an inherited one.
+    pub fn lang(&self) -> Option<&'d str> {
+        let ancestors = self.path.iter().rev().map(|(el, _)| *el);
+        core::iter::once(self.element())
+            .chain(ancestors)
+            .find_map(Element::lang)
+            .filter(|lang| !lang.is_empty())
+    }
+}
+
+// Maps each `xml:id` to the child indices leading to its element. The
+// first element in document order wins if an id is repeated.
+pub(crate) fn id_index(root: &Element) -> BTreeMap<String, Vec<usize>> {
+    let mut index = BTreeMap::new();
+    if let Some(id) = root.id() {
+        index.insert(id.to_string(), Vec::new());
+    }
+    // Each element being visited with the index of its next child; `path`
+    // holds the indices leading to the top one.
+    let mut stack = vec![(root, 0)];
+    let mut path = Vec::new();
+    while let Some(&(el, next)) = stack.last() {
+        let depth = stack.len() - 1;
+        match el.children.get(next) {
+            Some(child) => {
+                stack[depth].1 += 1;
+                if let Node::Element(child) = child {
+                    path.push(next);
+                    if let Some(id) = child.id() {
+                        index.entry(id.to_string()).or_insert_with(|| path.clone());
+                    }
+                    stack.push((child, 0));
+                }
+            }
+            None => {
+                stack.pop();
+                path.pop();
+            }
+        }
+    }
+    index
+}
+
+impl Document {
+    /// Looks up an element by `xml:id` using the index built while parsing.
+    /// If the tree was edited since and the index is stale, falls back to a
+    /// search.
+    pub fn get_element_by_id(&self, id: &str) -> Option<&Element> {
+        let indexed = self.ids.get(id).and_then(|path| {
+            path.iter()
+                .try_fold(&self.root, |el, &i| match el.children.get(i) {
+                    Some(Node::Element(child)) => Some(child),
+                    _ => None,
+                })
+        });
+        match indexed {
+            Some(el) if el.id() == Some(id) => Some(el),
+            _ => self
+                .root
+                .iter_depth_first()
+                .map(|(_, el)| el)
+                .find(|el| el.id() == Some(id)),
+        }
+    }
+
+    pub fn lang_of(&self, element: &Element) -> Option<&str> {
+        self.root.lang_of(element)
+    }
+}
+
+#[test]
+fn lang_and_id() {
+    let mut doc = Document::parse(
+        r#"<book xml:lang="en">
+            <title xml:id="t1">Title</title>
+            <chapter xml:lang="fr"><p xml:id="p1"/></chapter>
+            <note xml:lang=""><p xml:id="p2"/></note>
+        </book>"#,
+    )
+    .unwrap();
+    let title = doc.get_element_by_id("t1").unwrap();
+    assert_eq!("title", title.name);
+    assert_eq!(None, title.lang());
+    assert_eq!(Some("en"), doc.lang_of(title));
+    assert_eq!(
+        Some("fr"),
+        doc.lang_of(doc.get_element_by_id("p1").unwrap())
+    );
+    assert_eq!(None, doc.lang_of(doc.get_element_by_id("p2").unwrap()));
+    assert_eq!(None, doc.get_element_by_id("missing"));
+    let chapter = doc.navigate().child_elements().nth(1).unwrap();
+    assert_eq!(Some("fr"), chapter.lang());
+    let p = chapter.child_elements().next().unwrap();
+    assert_eq!(None, p.element().lang());
+    assert_eq!(Some("fr"), p.lang());
+    let note = chapter.next_sibling().unwrap();
+    assert_eq!(None, note.child_elements().next().unwrap().lang());
+    assert_eq!(Some("en"), doc.navigate().lang());
+
+    doc.root_mut().children_mut().remove(0);
+    assert_eq!("p", doc.get_element_by_id("p1").unwrap().name);
+}
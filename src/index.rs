@@ -0,0 +1,104 @@
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as Map;
+#[cfg(feature = "std")]
+use std::collections::HashMap as Map;
+
+use crate::prelude::*;
+use crate::{Document, Element};
+
+/// Lookups into a document built in one pass over the tree, for repeated
+/// queries that would otherwise each search it. With `std` they are hash
+/// lookups, in constant time; without it the index uses ordered maps, and
+/// lookups take time logarithmic in the number of distinct names. The
+/// index borrows the document, so it can't go stale; build a new one after
+/// editing.
+#[derive(Clone, Debug, Default)]
+pub struct DocumentIndex<'d> {
+    by_name: Map<&'d str, Vec<&'d Element>>,
+    by_id: Map<&'d str, &'d Element>,
+    by_attribute: Map<&'d str, Vec<&'d Element>>,
+}
+
+impl Document {
+    pub fn index(&self) -> DocumentIndex<'_> {
+        DocumentIndex::new(self.root())
+    }
+}
+
+impl<'d> DocumentIndex<'d> {
+    /// Indexes `root` and its descendants.
+    pub fn new(root: &'d Element) -> DocumentIndex<'d> {
+        let mut index = DocumentIndex::default();
+        for (_, el) in root.iter_depth_first() {
+            index.by_name.entry(el.name.as_str()).or_default().push(el);
+            if let Some(id) = el.id() {
+                index.by_id.entry(id).or_insert(el);
+            }
+            for (name, _) in &el.attributes {
+                let elements = index.by_attribute.entry(name.as_str()).or_default();
+                // An element repeating an attribute is listed once.
+                if !matches!(elements.last(), Some(last) if core::ptr::eq(*last, el)) {
+                    elements.push(el);
+                }
+            }
+        }
+        index
+    }
+
+    /// The elements named `name`, in document order.
+    pub fn find_all(&self, name: &str) -> &[&'d Element] {
+        self.by_name.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The element with `xml:id` `id`, the first in document order if it is
+    /// repeated. Like `Document::get_element_by_id`.
+    pub fn get_element_by_id(&self, id: &str) -> Option<&'d Element> {
+        self.by_id.get(id).copied()
+    }
+
+    /// The elements with an attribute named `name`, in document order.
+    pub fn with_attribute(&self, name: &str) -> &[&'d Element] {
+        self.by_attribute.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The elements whose attribute `name` is `value`, in document order.
+    pub fn with_attribute_value<'i>(
+        &'i self,
+        name: &'i str,
+        value: &'i str,
+    ) -> impl Iterator<Item = &'d Element> + 'i {
+        self.with_attribute(name)
+            .iter()
+            .copied()
+            .filter(move |el| el.attribute(name) == Some(value))
+    }
+}
+
+#[test]
+fn document_index() {
+    let doc = Document::parse(
+        r#"<shop>
+            <item sku="a1" xml:id="first"><name>One</name></item>
+            <group><item sku="b2"/><item/></group>
+            <name lang="en">Shop</name>
+        </shop>"#,
+    )
+    .unwrap();
+    let index = doc.index();
+    let skus: Vec<_> = index
+        .find_all("item")
+        .iter()
+        .map(|el| el.attribute("sku"))
+        .collect();
+    assert_eq!(vec![Some("a1"), Some("b2"), None], skus);
+    assert_eq!(2, index.find_all("name").len());
+    assert!(index.find_all("missing").is_empty());
+    assert!(core::ptr::eq(
+        index.find_all("item")[0],
+        index.get_element_by_id("first").unwrap()
+    ));
+    assert_eq!(2, index.with_attribute("sku").len());
+    let found: Vec<_> = index.with_attribute_value("sku", "b2").collect();
+    assert!(core::ptr::eq(index.find_all("item")[1], found[0]));
+    assert_eq!(1, found.len());
+}
@@ -0,0 +1,139 @@
+use serde_json::{Map, Value};
+
+use crate::prelude::*;
+use crate::{Element, Error, Node};
+
+impl Element {
+    /// Converts the element to JSON as `{"name": body}`. An element with
+    /// neither attributes nor child elements becomes its text (or `null`
+    /// when empty); any other becomes an object with attributes under
+    /// `@name`, text under `#text`, and children under their names, as an
+    /// array when a name repeats. Attributes and children stay in document
+    /// order, except that the children of a repeated name are grouped where
+    /// the first of them was. The order of mixed content is lost, and
+    /// comments and processing instructions are dropped.
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert(self.name.clone(), body(self));
+        Value::Object(object)
+    }
+
+    /// Builds an element from JSON in the shape `to_json` produces. Numbers
+    /// and booleans are accepted wherever text is.
+    pub fn from_json(value: &Value) -> Result<Element, Error> {
+        let mut entries = value.as_object().into_iter().flatten();
+        match (entries.next(), entries.next()) {
+            (Some((name, body)), None) => element(name, body),
+            _ => Err(Error::Json(
+                "expected an object with a single key".to_string(),
+            )),
+        }
+    }
+}
+
+fn body(el: &Element) -> Value {
+    let text = el.immediate_text();
+    let has_elements = el
+        .children
+        .iter()
+        .any(|child| matches!(child, Node::Element(_)));
+    if el.attributes.is_empty() && !has_elements {
+        return match text.is_empty() {
+            true => Value::Null,
+            false => Value::String(text),
+        };
+    }
+    let mut object = Map::new();
+    for (name, value) in &el.attributes {
+        object.insert(format!("@{}", name), Value::String(value.clone()));
+    }
+    if !text.is_empty() {
+        object.insert("#text".to_string(), Value::String(text));
+    }
+    for child in &el.children {
+        let child = match child {
+            Node::Element(child) => child,
+            _ => continue,
+        };
+        match object.get_mut(&child.name) {
+            Some(Value::Array(items)) => items.push(body(child)),
+            Some(first) => {
+                let first = core::mem::take(first);
+                object.insert(child.name.clone(), Value::Array(vec![first, body(child)]));
+            }
+            None => {
+                object.insert(child.name.clone(), body(child));
+            }
+        }
+    }
+    Value::Object(object)
+}
+
+fn element(name: &str, body: &Value) -> Result<Element, Error> {
+    let mut el = Element::new(name);
+    let object = match body {
+        Value::Object(object) => object,
+        Value::Null => return Ok(el),
+        value => {
+            el.children.push(Node::Text(text(name, value)?));
+            return Ok(el);
+        }
+    };
+    for (key, value) in object {
+        if let Some(attribute) = key.strip_prefix('@') {
+            el.attributes
+                .push((attribute.to_string(), text(key, value)?));
+        } else if key == "#text" {
+            el.children.push(Node::Text(text(key, value)?));
+        } else if let Value::Array(items) = value {
+            for item in items {
+                el.children.push(Node::Element(element(key, item)?));
+            }
+        } else {
+            el.children.push(Node::Element(element(key, value)?));
+        }
+    }
+    Ok(el)
+}
+
+fn text(key: &str, value: &Value) -> Result<String, Error> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Number(number) => Ok(number.to_string()),
+        Value::Bool(flag) => Ok(flag.to_string()),
+        _ => Err(Error::Json(format!("{:?} must be text", key))),
+    }
+}
+
+#[test]
+fn json_round_trip() {
+    let el: Element = "<feed v=\"2\"><item>a</item><item><b/></item><title>t</title></feed>"
+        .parse()
+        .unwrap();
+    let json = el.to_json();
+    assert_eq!(
+        r##"{"feed":{"@v":"2","item":["a",{"b":null}],"title":"t"}}"##,
+        json.to_string()
+    );
+    assert_eq!(Ok(el), Element::from_json(&json));
+
+    let value: Value = serde_json::from_str(r#"{"port":8080}"#).unwrap();
+    assert_eq!(
+        "<port>8080</port>",
+        Element::from_json(&value).unwrap().to_string()
+    );
+    assert!(Element::from_json(&serde_json::json!({"a": {"@x": [1]}})).is_err());
+
+    let person: Element = "<person z=\"1\" a=\"2\"><name/><age/><name/></person>"
+        .parse()
+        .unwrap();
+    let json = person.to_json();
+    assert_eq!(
+        r##"{"person":{"@z":"1","@a":"2","name":[null,null],"age":null}}"##,
+        json.to_string()
+    );
+    assert_eq!(
+        "<person z=\"1\" a=\"2\"><name/><name/><age/></person>",
+        Element::from_json(&json).unwrap().to_string()
+    );
+}
@@ -1,39 +1,393 @@
+#![no_std]
+
+extern crate alloc;
+// Lets the code `simple-xmlparser-derive` generates name this crate from
+// inside it, as in the tests.
+extern crate self as simple_xmlparser;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[macro_use]
+mod trace;
+
+mod arena;
+#[cfg(feature = "async")]
+mod async_reader;
+mod attributes;
+mod bulk;
+mod canonical;
+mod cst;
+mod diff;
+mod document;
+mod dtd;
+mod encoding;
+mod entity;
+mod error;
+mod extract;
+#[cfg(feature = "feed")]
+mod feed;
+mod fragment;
+#[cfg(any(test, feature = "fuzz"))]
+mod fuzz;
+mod hash;
+mod hooks;
+mod ids;
+mod index;
+#[cfg(feature = "json")]
+mod json;
+mod mapping;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+mod namespace;
+mod navigate;
+mod options;
 mod parser;
+mod patch;
+mod path;
+mod push;
+#[cfg(feature = "rayon")]
+mod records;
+mod recover;
+mod schema;
+mod serialize;
+#[cfg(feature = "soap")]
+mod soap;
+mod stats;
+mod template;
+#[cfg(any(test, feature = "testing"))]
+pub mod testutil;
+mod text;
+pub mod tokenizer;
+mod transform;
+mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod wellformed;
+mod xinclude;
+mod xsd;
+
+mod prelude {
+    pub use alloc::boxed::Box;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use alloc::{format, vec};
+}
 
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::convert::TryFrom;
+use core::str::FromStr;
+
+use dtd::{doctype_at, entity_reference, Expansion};
+use options::Fatal;
+
+use prelude::*;
+
+pub use arena::*;
+#[cfg(feature = "async")]
+pub use async_reader::*;
+pub use attributes::*;
+pub use bulk::*;
+pub use cst::*;
+pub use diff::*;
+pub use document::*;
+pub use dtd::{DEFAULT_MAX_ENTITY_DEPTH, DEFAULT_MAX_ENTITY_EXPANSION};
+pub use encoding::*;
+pub use entity::*;
+pub use error::*;
+pub use extract::*;
+#[cfg(feature = "feed")]
+pub use feed::*;
+pub use fragment::*;
+#[cfg(feature = "fuzz")]
+pub use fuzz::*;
+pub use hooks::*;
+pub use index::*;
+pub use mapping::*;
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::*;
+pub use namespace::*;
+pub use navigate::*;
+pub use options::*;
 pub use parser::*;
+pub use push::*;
+#[cfg(feature = "rayon")]
+pub use records::*;
+pub use recover::*;
+pub use schema::*;
+pub use serialize::*;
+#[cfg(feature = "derive")]
+pub use simple_xmlparser_derive::{FromXml, ToXml};
+#[cfg(feature = "soap")]
+pub use soap::*;
+pub use stats::*;
+pub use template::*;
+pub use text::*;
+pub use tokenizer::{Span, Token, Tokens};
+pub use walk::*;
+pub use wellformed::*;
+pub use xinclude::*;
+pub use xsd::*;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Elements order by name, then attributes (as written, pair by pair), then
+/// children, each compared lexicographically.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Element {
     name: String,
     attributes: Vec<(String, String)>,
-    children: Vec<Element>,
+    children: Vec<Node>,
 }
 
+/// Nodes of different kinds order as declared: elements first, processing
+/// instructions last.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+    Comment(String),
+    ProcessingInstruction { target: String, data: String },
+}
+
+impl Element {
+    pub fn new<S: Into<String>>(name: S) -> Element {
+        Element {
+            name: name.into(),
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
+
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    pub fn children_mut(&mut self) -> &mut Vec<Node> {
+        &mut self.children
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl FromStr for Element {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Element, Error> {
+        element().parse_complete(input)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Element {
+    type Error = Error;
+
+    fn try_from(input: &'a str) -> Result<Element, Error> {
+        input.parse()
+    }
+}
+
+/// A double-quoted attribute value, normalized as XML requires: references
+/// are decoded, and every tab, newline or `\r\n` written in the value
+/// becomes a space. A quote in the value is written `&quot;`.
 pub fn quoted_string<'a>() -> impl Parser<'a, String> {
-    right(
-        match_literal("\""),
-        left(
-            zero_or_more(pred(any_char, |c| *c != '"')),
-            match_literal("\""),
-        ),
-    )
-    .map(|chars| chars.into_iter().collect())
+    quoted_string_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn quoted_string_in<'a>(context: Rc<Context>) -> impl Parser<'a, String> {
+    move |input: &'a str| quoted_string_at(&context, input)
+}
+
+// References are handled as in `text_at`. Characters from character
+// references are kept as they are, so `&#10;` is a newline; entity
+// replacement text is normalized along with the rest of the value.
+fn quoted_string_at<'a>(context: &Context, input: &'a str) -> ParseResult<'a, String> {
+    let mut value = String::new();
+    let mut rest = input.strip_prefix('"').ok_or(input)?;
+    loop {
+        let run = rest
+            .find(['"', '&', '\t', '\n', '\r'])
+            .ok_or(&rest[rest.len()..])?;
+        value.push_str(&rest[..run]);
+        rest = &rest[run..];
+        match rest.as_bytes()[0] {
+            b'"' => return Ok((&rest[1..], value)),
+            b'&' => {}
+            _ => {
+                value.push(' ');
+                rest = rest.strip_prefix("\r\n").unwrap_or(&rest[1..]);
+                continue;
+            }
+        }
+        if let Some((next, name)) = entity_reference(rest).filter(|_| reference(rest).is_err()) {
+            let start = value.len();
+            match context.expand_entity(name, &mut value) {
+                Ok(()) => {
+                    let expanded = value[start..].replace(['\t', '\n', '\r'], " ");
+                    value.truncate(start);
+                    value.push_str(&expanded);
+                    rest = next;
+                    continue;
+                }
+                Err(Expansion::OverLimit) => {
+                    context.fail(rest, Fatal::EntityLimit);
+                    return Err(rest);
+                }
+                Err(Expansion::Undefined) => {}
+            }
+        }
+        match (reference(rest), malformed_char_reference(rest)) {
+            (Ok((next, Some(c))), _) => {
+                value.push(c);
+                rest = next;
+            }
+            (Ok((next, None)), _) | (Err(_), Some(next)) if context.options.lenient => {
+                context.warn(rest, WarningKind::InvalidCharacterReference);
+                value.push('\u{FFFD}');
+                rest = next;
+            }
+            (Err(_), None) if context.options.lenient => {
+                context.warn(rest, WarningKind::UnescapedAmpersand);
+                value.push('&');
+                rest = &rest[1..];
+            }
+            _ => return Err(rest),
+        }
+    }
 }
 
 pub fn attribute_pair<'a>() -> impl Parser<'a, (String, String)> {
     pair(identifier, right(match_literal("="), quoted_string()))
 }
 
+// Lenient mode also accepts `name=value` and a bare `name`, the latter
+// taking `ParseOptions::flag_value`.
+fn attribute_pair_at<'a>(context: &Context, input: &'a str) -> ParseResult<'a, (String, String)> {
+    let value = |input: &'a str| quoted_string_at(context, input);
+    let strict = pair(identifier, right(match_literal("="), value)).parse(input);
+    if strict.is_ok() || !context.options.lenient {
+        return strict;
+    }
+    let (rest, name) = identifier(input)?;
+    match rest.strip_prefix('=') {
+        Some(value) => {
+            let (rest, value) = unquoted_value(value).map_err(|_| input)?;
+            context.warn(input, WarningKind::UnquotedAttributeValue);
+            Ok((rest, (name, value)))
+        }
+        None => {
+            context.warn(input, WarningKind::ValuelessAttribute);
+            Ok((rest, (name, context.options.flag_value.clone())))
+        }
+    }
+}
+
+fn unquoted_value(input: &str) -> ParseResult<'_, String> {
+    let end = input
+        .char_indices()
+        .find(|&(i, c)| {
+            c.is_whitespace()
+                || matches!(c, '"' | '\'' | '<' | '>' | '=' | '`')
+                || input[i..].starts_with("/>")
+        })
+        .map_or(input.len(), |(i, _)| i);
+    match end {
+        0 => Err(input),
+        _ => Ok((&input[end..], input[..end].to_string())),
+    }
+}
+
 pub fn attributes<'a>() -> impl Parser<'a, Vec<(String, String)>> {
-    zero_or_more(right(space1(), attribute_pair()))
+    attributes_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn attributes_in<'a>(context: Rc<Context>) -> impl Parser<'a, Vec<(String, String)>> {
+    move |input: &'a str| attributes_at(&context, "", input)
+}
+
+// `zero_or_more(right(space1(), attribute_pair_in(context)))`, unrolled,
+// passing each value of the start tag of `element` to the attribute hook.
+fn attributes_at<'a>(
+    context: &Context,
+    element: &str,
+    input: &'a str,
+) -> ParseResult<'a, Vec<(String, String)>> {
+    let mut attributes = Vec::new();
+    let mut rest = input;
+    loop {
+        let next = rest.trim_start();
+        if next.len() == rest.len() {
+            break;
+        }
+        match attribute_pair_at(context, next) {
+            Ok((after, (name, value))) => {
+                if let Some(hook) = &context.attribute_hook {
+                    let start = match next[name.len()..].strip_prefix('=') {
+                        Some(value) => value.strip_prefix(['"', '\'']).unwrap_or(value),
+                        None => &next[name.len()..],
+                    };
+                    if !(hook.0)(element, &name, &value, start.len()) {
+                        context.fail(start, Fatal::AttributeValue(name));
+                        return Err(start);
+                    }
+                }
+                attributes.push((name, value));
+                rest = after;
+            }
+            Err(_) => break,
+        }
+    }
+    Ok((rest, attributes))
 }
 
 pub fn element_start<'a>() -> impl Parser<'a, (String, Vec<(String, String)>)> {
-    right(match_literal("<"), pair(identifier, attributes()))
+    element_start_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn element_start_in<'a>(
+    context: Rc<Context>,
+) -> impl Parser<'a, (String, Vec<(String, String)>)> {
+    move |input: &'a str| element_start_at(&context, input)
+}
+
+fn element_start_at<'a>(
+    context: &Context,
+    input: &'a str,
+) -> ParseResult<'a, (String, Vec<(String, String)>)> {
+    let name = match context.options.fold_case {
+        true => identifier_ci,
+        false => identifier,
+    };
+    let (rest, name) = name(input.strip_prefix('<').ok_or(input)?)?;
+    let (rest, mut attributes) = attributes_at(context, &name, rest)?;
+    if context.options.fold_case {
+        for (name, _) in &mut attributes {
+            name.make_ascii_lowercase();
+        }
+    }
+    Ok((rest, (name, attributes)))
 }
 
 pub fn single_element<'a>() -> impl Parser<'a, Element> {
-    left(element_start(), match_literal("/>")).map(|(name, attributes)| Element {
+    single_element_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn single_element_in<'a>(context: Rc<Context>) -> impl Parser<'a, Element> {
+    left(element_start_in(context), match_literal("/>")).map(|(name, attributes)| Element {
         name,
         attributes,
         children: vec![],
@@ -41,8 +395,12 @@ pub fn single_element<'a>() -> impl Parser<'a, Element> {
 }
 
 pub fn open_element<'a>() -> impl Parser<'a, Element> {
+    open_element_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn open_element_in<'a>(context: Rc<Context>) -> impl Parser<'a, Element> {
     map(
-        left(element_start(), match_literal(">")),
+        left(element_start_in(context), match_literal(">")),
         |(name, attributes)| Element {
             name,
             attributes,
@@ -56,23 +414,370 @@ pub fn close_element<'a>(expected_name: String) -> impl Parser<'a, String> {
         .pred(move |name| name == &expected_name)
 }
 
+pub fn close_element_ci<'a>(expected_name: String) -> impl Parser<'a, String> {
+    right(match_literal("</"), left(identifier, match_literal(">")))
+        .pred(move |name| name.eq_ignore_ascii_case(&expected_name))
+}
+
+pub fn text<'a>() -> impl Parser<'a, String> {
+    text_in(Context::new(ParseOptions::default()))
+}
+
+// Character data up to the next `<`, with references decoded. A reference
+// that can't be decoded ends the text in strict mode, so the error lands on
+// it; lenient mode substitutes it and records a warning instead.
+pub(crate) fn text_in<'a>(context: Rc<Context>) -> impl Parser<'a, String> {
+    move |input: &'a str| text_at(&context, input)
+}
+
+fn text_at<'a>(context: &Context, input: &'a str) -> ParseResult<'a, String> {
+    let mut text = String::new();
+    let mut rest = input;
+    loop {
+        let run = rest.find(['<', '&']).unwrap_or(rest.len());
+        text.push_str(&rest[..run]);
+        rest = &rest[run..];
+        if !rest.starts_with('&') {
+            break;
+        }
+        if let Some((next, name)) = entity_reference(rest).filter(|_| reference(rest).is_err()) {
+            match context.expand_entity(name, &mut text) {
+                Ok(()) => {
+                    rest = next;
+                    continue;
+                }
+                Err(Expansion::OverLimit) => {
+                    context.fail(rest, Fatal::EntityLimit);
+                    return Err(rest);
+                }
+                Err(Expansion::Undefined) => {}
+            }
+        }
+        match (reference(rest), malformed_char_reference(rest)) {
+            (Ok((next, Some(c))), _) => {
+                text.push(c);
+                rest = next;
+            }
+            (Ok((next, None)), _) | (Err(_), Some(next)) if context.options.lenient => {
+                context.warn(rest, WarningKind::InvalidCharacterReference);
+                text.push('\u{FFFD}');
+                rest = next;
+            }
+            (Err(_), None) if context.options.lenient => {
+                context.warn(rest, WarningKind::UnescapedAmpersand);
+                text.push('&');
+                rest = &rest[1..];
+            }
+            _ => break,
+        }
+    }
+    if rest.len() == input.len() {
+        Err(input)
+    } else {
+        Ok((rest, text))
+    }
+}
+
+pub fn comment<'a>() -> impl Parser<'a, String> {
+    move |input: &'a str| {
+        let body = input.strip_prefix("<!--").ok_or(input)?;
+        let end = body.find("--").ok_or(input)?;
+        match body[end..].strip_prefix("-->") {
+            Some(rest) => Ok((rest, body[..end].to_string())),
+            None => Err(input),
+        }
+    }
+}
+
+// `<?target data?>`, where the target may not be `xml` in any case.
+pub fn processing_instruction<'a>() -> impl Parser<'a, (String, String)> {
+    move |input: &'a str| {
+        let (rest, target) = right(match_literal("<?"), identifier)
+            .parse(input)
+            .map_err(|_| input)?;
+        if target.eq_ignore_ascii_case("xml") {
+            return Err(input);
+        }
+        if let Some(rest) = rest.strip_prefix("?>") {
+            return Ok((rest, (target, String::new())));
+        }
+        let (body, _) = space1().parse(rest).map_err(|_| input)?;
+        let end = body.find("?>").ok_or(input)?;
+        Ok((&body[end + 2..], (target, body[..end].to_string())))
+    }
+}
+
+pub fn misc<'a>() -> impl Parser<'a, Node> {
+    misc_at
+}
+
+fn misc_at(input: &str) -> ParseResult<'_, Node> {
+    if let Ok((rest, comment)) = comment().parse(input) {
+        return Ok((rest, Node::Comment(comment)));
+    }
+    let (rest, (target, data)) = processing_instruction().parse(input)?;
+    Ok((rest, Node::ProcessingInstruction { target, data }))
+}
+
+pub fn node<'a>() -> impl Parser<'a, Node> {
+    node_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn node_in<'a>(context: Rc<Context>) -> impl Parser<'a, Node> {
+    move |input: &'a str| node_at(&context, input)
+}
+
+// The tree is parsed by these plain functions rather than by combinators,
+// so that recursing into children doesn't build new parsers per element.
+fn node_at<'a>(context: &Context, input: &'a str) -> ParseResult<'a, Node> {
+    let failed = match bare_element_at(context, input) {
+        Ok((rest, el)) => return Ok((rest, Node::Element(el))),
+        Err(failed) => failed,
+    };
+    misc_at(input)
+        .or_else(|_| {
+            let (rest, text) = text_at(context, input)?;
+            Ok((rest, Node::Text(text)))
+        })
+        .map_err(|other| furthest(failed, other))
+}
+
 pub fn parent_element<'a>() -> impl Parser<'a, Element> {
-    open_element().and_then(|el| {
-        map(
-            left(zero_or_more(element()), close_element(el.name.clone())),
-            move |children| {
-                let mut el = el.clone();
-                el.children = children;
-                el
-            },
-        )
-    })
+    parent_element_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn parent_element_in<'a>(context: Rc<Context>) -> impl Parser<'a, Element> {
+    move |input: &'a str| {
+        let (rest, (name, attributes)) = element_start_at(&context, input)?;
+        let rest = rest.strip_prefix('>').ok_or(rest)?;
+        let el = Element {
+            name,
+            attributes,
+            children: vec![],
+        };
+        element_body(&context, el, rest)
+    }
+}
+
+// Parses an element keeping all of its text verbatim.
+pub fn bare_element<'a>() -> impl Parser<'a, Element> {
+    bare_element_in(Context::new(ParseOptions::default()))
+}
+
+pub(crate) fn bare_element_in<'a>(context: Rc<Context>) -> impl Parser<'a, Element> {
+    move |input: &'a str| bare_element_at(&context, input)
+}
+
+// Either a self-closing element or a parent element, reading the start tag
+// only once.
+fn bare_element_at<'a>(context: &Context, input: &'a str) -> ParseResult<'a, Element> {
+    let (rest, (name, attributes)) = element_start_at(context, input)?;
+    let el = Element {
+        name,
+        attributes,
+        children: vec![],
+    };
+    if let Some(rest) = rest.strip_prefix("/>") {
+        return Ok((rest, el));
+    }
+    let body = rest
+        .strip_prefix('>')
+        .ok_or_else(|| tag_end_failure(context, rest))?;
+    element_body(context, el, body)
+}
+
+// Where a start tag that does not end at `rest` went wrong: inside the
+// attribute there, if one failed part way.
+fn tag_end_failure<'a>(context: &Context, rest: &'a str) -> &'a str {
+    match attribute_pair_at(context, rest.trim_start()) {
+        Err(failed) => furthest(rest, failed),
+        Ok(_) => rest,
+    }
+}
+
+// The children and closing tag of `el`, whose start tag has been read. In
+// HTML mode void elements have neither. In HTML and case-folding modes
+// closing tags match ignoring ASCII case.
+fn element_body<'a>(
+    context: &Context,
+    mut el: Element,
+    input: &'a str,
+) -> ParseResult<'a, Element> {
+    let html = context.options.html;
+    if html && is_void_element(&el.name) {
+        return Ok((input, el));
+    }
+    let mut rest = input;
+    // A child that fails part way explains a missing closing tag better
+    // than the position of the child does.
+    let failed = loop {
+        match node_at(context, rest) {
+            Ok((next, node)) => {
+                el.children.push(node);
+                rest = next;
+            }
+            Err(failed) => break failed,
+        }
+    };
+    let close = rest.strip_prefix("</").ok_or(furthest(failed, rest))?;
+    let name = close.get(..el.name.len()).ok_or(rest)?;
+    let matches = match html || context.options.fold_case {
+        true => name.eq_ignore_ascii_case(&el.name),
+        false => name == el.name,
+    };
+    match close[name.len()..].strip_prefix('>') {
+        Some(after) if matches => Ok((after, el)),
+        _ => Err(rest),
+    }
 }
 
 pub fn element<'a>() -> impl Parser<'a, Element> {
-    whitespace_wrap(either(single_element(), parent_element()))
+    element_with(ParseOptions::default())
+}
+
+pub fn element_with<'a>(options: ParseOptions) -> impl Parser<'a, Element> {
+    fresh(element_in, Context::new(options))
+}
+
+// Resets `context` before each parse, so that a parser kept and reused
+// does not inherit the entities and expansion count of the last input.
+fn fresh<'a, P, T>(build: impl Fn(Rc<Context>) -> P, context: Rc<Context>) -> impl Parser<'a, T>
+where
+    P: Parser<'a, T>,
+{
+    let parser = build(context.clone());
+    move |input: &'a str| {
+        context.reset();
+        parser.parse(input)
+    }
+}
+
+pub(crate) fn element_in<'a>(context: Rc<Context>) -> impl Parser<'a, Element> {
+    let root = whitespace_wrap(bare_element_in(context.clone()));
+    move |input: &'a str| {
+        let (rest, mut el) = {
+            let _span = span!("element", bytes = input.len());
+            root.parse(input)?
+        };
+        let _span = span!("finish");
+        context.options.finish(&mut el);
+        Ok((rest, el))
+    }
 }
 
+pub fn xml_declaration<'a>() -> impl Parser<'a, Declaration> {
+    right(
+        match_literal("<?xml"),
+        left(attributes(), right(space0(), match_literal("?>"))),
+    )
+    .pred(|attributes| {
+        attributes.iter().any(|(name, _)| name == "version")
+            && attributes
+                .iter()
+                .all(|(name, _)| ["version", "encoding", "standalone"].contains(&name.as_str()))
+    })
+    .map(|attributes| {
+        let mut declaration = Declaration {
+            version: String::new(),
+            encoding: None,
+            standalone: None,
+        };
+        for (name, value) in attributes {
+            match name.as_str() {
+                "version" => declaration.version = value,
+                "encoding" => declaration.encoding = Some(value),
+                _ => declaration.standalone = Some(value),
+            }
+        }
+        declaration
+    })
+}
+
+pub fn document<'a>() -> impl Parser<'a, (Option<Declaration>, Element)> {
+    document_with(ParseOptions::default())
+}
+
+pub fn document_with<'a>(options: ParseOptions) -> impl Parser<'a, (Option<Declaration>, Element)> {
+    fresh(document_in, Context::new(options)).map(|document| (document.declaration, document.root))
+}
+
+// Builds the document without its warnings and id index, which the caller
+// fills in once the whole input has been parsed.
+pub(crate) fn document_in<'a>(context: Rc<Context>) -> impl Parser<'a, Document> {
+    let prolog = prolog_in(context.clone());
+    let root = element_in(context.clone());
+    let epilog = zero_or_more(whitespace_wrap(misc()));
+    move |input: &'a str| {
+        let start = document_start_at(&context, input)?;
+        let (rest, (declaration, (prolog, doctype))) = {
+            let _span = span!("prolog");
+            prolog.parse(start)?
+        };
+        event!("prolog parsed", bytes = input.len() - rest.len());
+        let (rest, root) = root.parse(rest)?;
+        let (rest, epilog) = epilog.parse(rest)?;
+        let encoding = declaration
+            .as_ref()
+            .and_then(|decl| decl.encoding.as_deref())
+            .and_then(Encoding::from_label)
+            .unwrap_or(Encoding::Utf8);
+        Ok((
+            rest,
+            Document {
+                declaration,
+                prolog,
+                doctype,
+                root,
+                epilog,
+                encoding,
+                warnings: Vec::new(),
+                ids: BTreeMap::new(),
+            },
+        ))
+    }
+}
+
+// Where the document starts in `input`, past a byte order mark. Anything
+// but whitespace before the first `<`, or whitespace before the XML
+// declaration, is skipped with a warning in lenient mode and fatal
+// otherwise.
+pub(crate) fn document_start_at<'a>(context: &Context, input: &'a str) -> Result<&'a str, &'a str> {
+    let input = input.strip_prefix('\u{FEFF}').unwrap_or(input);
+    let (before, rest) = input.split_at(input.find('<').unwrap_or(input.len()));
+    let whitespace = before.trim_start().is_empty();
+    if before.is_empty() || whitespace && xml_declaration().parse(rest).is_err() {
+        return Ok(input);
+    }
+    if context.options.lenient {
+        context.warn(input, WarningKind::ContentBeforeStart);
+        return Ok(rest);
+    }
+    context.fail(input, Fatal::ContentBeforeStart);
+    Err(input)
+}
+
+// The XML declaration and what follows it up to the root element, reading
+// any document type into `context`. The document type comes back as
+// written, with the number of nodes before it.
+pub(crate) fn prolog_in<'a>(context: Rc<Context>) -> impl Parser<'a, Prolog> {
+    let misc_nodes = zero_or_more(whitespace_wrap(misc()));
+    pair(optional(xml_declaration()), move |input: &'a str| {
+        let (rest, mut nodes) = misc_nodes.parse(input)?;
+        let rest = rest.trim_start();
+        match doctype_at(&context, rest) {
+            Ok((after, ())) => {
+                let doctype = (nodes.len(), rest[..rest.len() - after.len()].to_string());
+                let (after, more) = misc_nodes.parse(after)?;
+                nodes.extend(more);
+                Ok((after, (nodes, Some(doctype))))
+            }
+            Err(_) => Ok((rest, (nodes, None))),
+        }
+    })
+}
+
+pub(crate) type Prolog = (Option<Declaration>, (Vec<Node>, Option<(usize, String)>));
+
 #[test]
 fn a_parser() {
     assert_eq!(the_letter_a("a"), Ok(("", ())));
@@ -101,6 +806,22 @@ fn identifier_parser() {
     assert_eq!(Err("!not an identifier"), identifier("!not an identifier"));
 }
 
+#[test]
+fn xml_name_identifier() {
+    for name in &[
+        "_private",
+        "data.point",
+        "\u{540d}\u{524d}",
+        "soap:Envelope",
+        "h2",
+    ] {
+        assert_eq!(Ok(("", name.to_string())), identifier(name));
+    }
+    assert_eq!(Err("2nd"), identifier("2nd"));
+    assert_eq!(Err("-dash"), identifier("-dash"));
+    assert_eq!(Err(".dot"), identifier(".dot"));
+}
+
 #[test]
 fn pair_combinator() {
     let tag_opener = pair(match_literal("<"), identifier);
@@ -135,12 +856,62 @@ fn predicate_combinator() {
     assert_eq!(Err("lol"), parser.parse("lol"));
 }
 
+#[test]
+fn either_reports_furthest_failure() {
+    let parser = either(
+        attribute_pair(),
+        map(match_literal("<"), |()| Default::default()),
+    );
+    assert_eq!(Err("1"), parser.parse("x=1"));
+    assert_eq!(
+        Err(Error::Syntax { offset: 8 }),
+        "<a><b x=1/></a>".parse::<Element>()
+    );
+}
+
 #[test]
 fn quoted_string_parser() {
     assert_eq!(
         Ok(("", "Hello Joe!".to_string())),
         quoted_string().parse("\"Hello Joe!\"")
     );
+    assert_eq!(
+        Ok(("", "say \"hi\"  to\tJoe & co".to_string())),
+        quoted_string().parse("\"say &quot;hi&quot;\r\n\tto&#9;Joe &amp; co\"")
+    );
+    assert_eq!(Err("&co\""), quoted_string().parse("\"Joe &co\""));
+    let el: Element = "<a x=\"1&#10;2\"/>".parse().unwrap();
+    assert_eq!(Some("1\n2"), el.attribute("x"));
+    assert_eq!(Ok(el.clone()), el.to_string().parse());
+}
+
+#[test]
+fn spanned_and_complete_parses() {
+    use tokenizer::Span;
+
+    let input = "<a x=\"1\"/> tail";
+    let parsed = attribute_pair().parse_spanned(input, 3).unwrap();
+    assert_eq!(("x".to_string(), "1".to_string()), parsed.value);
+    assert_eq!(Span { start: 3, end: 8 }, parsed.consumed_span);
+    assert_eq!("x=\"1\"", parsed.consumed_span.as_str(input));
+    assert_eq!("/> tail", parsed.rest);
+    assert_eq!(
+        Err(Error::Syntax { offset: 5 }),
+        attribute_pair().parse_spanned(input, 5)
+    );
+    assert_eq!(
+        Err(Error::Syntax { offset: 15 }),
+        attribute_pair().parse_spanned(input, 99)
+    );
+    assert_eq!(
+        Err(Error::Syntax { offset: 1 }),
+        element().parse_spanned("\u{e9}", 1)
+    );
+    assert_eq!(
+        Err(Error::Syntax { offset: 11 }),
+        element().parse_complete(input)
+    );
+    assert_eq!(Ok(Element::new("a")), element().parse_complete("<a/>"));
 }
 
 #[test]
@@ -185,20 +956,20 @@ fn xml_parser() {
         name: "top".to_string(),
         attributes: vec![("label".to_string(), "Top".to_string())],
         children: vec![
-            Element {
+            Node::Element(Element {
                 name: "semi-bottom".to_string(),
                 attributes: vec![("label".to_string(), "Bottom".to_string())],
                 children: vec![],
-            },
-            Element {
+            }),
+            Node::Element(Element {
                 name: "middle".to_string(),
                 attributes: vec![],
-                children: vec![Element {
+                children: vec![Node::Element(Element {
                     name: "bottom".to_string(),
                     attributes: vec![("label".to_string(), "Another bottom".to_string())],
                     children: vec![],
-                }],
-            },
+                })],
+            }),
         ],
     };
     assert_eq!(Ok(("", parsed_doc)), element().parse(doc));
@@ -212,3 +983,210 @@ fn mismatched_closing_tag() {
         </middle>"#;
     assert_eq!(Err("</middle>"), element().parse(doc));
 }
+
+#[test]
+fn declaration_parser() {
+    assert_eq!(
+        Ok((
+            "",
+            Declaration {
+                version: "1.0".to_string(),
+                encoding: Some("UTF-8".to_string()),
+                standalone: None,
+            }
+        )),
+        xml_declaration().parse("<?xml version=\"1.0\" encoding=\"UTF-8\"?>")
+    );
+    assert_eq!(
+        Err("<?xml encoding=\"UTF-8\"?>"),
+        xml_declaration().parse("<?xml encoding=\"UTF-8\"?>")
+    );
+}
+
+#[test]
+fn element_display() {
+    let el = Element {
+        name: "top".to_string(),
+        attributes: vec![("label".to_string(), "a < \"b\" & c".to_string())],
+        children: vec![
+            Node::Element(Element {
+                name: "bottom".to_string(),
+                attributes: vec![],
+                children: vec![],
+            }),
+            Node::Text("1 < 2 & 3".to_string()),
+        ],
+    };
+    assert_eq!(
+        "<top label=\"a &lt; &quot;b&quot; &amp; c\"><bottom/>1 &lt; 2 &amp; 3</top>",
+        el.to_string()
+    );
+}
+
+#[test]
+fn whitespace_policies() {
+    let doc = "<p> <b>  bold  </b>  text\n  here <pre xml:space=\"preserve\"> a  b </pre></p>";
+    let parse = |policy| {
+        element_with(ParseOptions::new().whitespace(policy))
+            .parse(doc)
+            .map(|(_, el)| el.to_string())
+    };
+    assert_eq!(
+        Ok(
+            "<p> <b>  bold  </b>  text\n  here <pre xml:space=\"preserve\"> a  b </pre></p>"
+                .to_string()
+        ),
+        parse(Whitespace::Preserve)
+    );
+    assert_eq!(
+        Ok("<p><b>bold</b>  text\n  here <pre xml:space=\"preserve\"> a  b </pre></p>".to_string()),
+        parse(Whitespace::Trim)
+    );
+    assert_eq!(
+        Ok("<p><b>bold</b> text here <pre xml:space=\"preserve\"> a  b </pre></p>".to_string()),
+        parse(Whitespace::Collapse)
+    );
+
+    // Mixed content keeps the spaces between words, and only XML whitespace
+    // is stripped.
+    let el: Element = "<p>\n  Hello <b>world</b>\n</p>".parse().unwrap();
+    assert_eq!("<p>Hello <b>world</b></p>", el.to_string());
+    let el: Element = "<p>\u{a0}x\u{3000}</p>".parse().unwrap();
+    assert_eq!("<p>\u{a0}x\u{3000}</p>", el.to_string());
+}
+
+#[test]
+fn element_from_str() {
+    let el: Element = " <a><b/></a> ".parse().unwrap();
+    assert_eq!("<a><b/></a>", el.to_string());
+    assert_eq!(Ok(el), Element::try_from("<a><b/></a>"));
+    assert_eq!(
+        Err(Error::Syntax { offset: 7 }),
+        "<a/>\n  trailing".parse::<Element>()
+    );
+    assert_eq!(Err(Error::Syntax { offset: 0 }), "oops".parse::<Element>());
+}
+
+#[test]
+fn text_references() {
+    assert_eq!(
+        Ok(("<", "a < b & \"c\" AB".to_string())),
+        text().parse("a &lt; b &amp; &quot;c&quot; &#65;&#x42;<")
+    );
+    assert_eq!(
+        Ok(("&#xD800;<", "bad ".to_string())),
+        text().parse("bad &#xD800;<")
+    );
+    assert_eq!(Err("&#1;"), text().parse("&#1;"));
+    assert_eq!(
+        Err(Error::Syntax { offset: 5 }),
+        "<a>AT&T</a>".parse::<Element>()
+    );
+}
+
+#[test]
+fn html_mode() {
+    let doc = "<DIV class=\"x\">line<BR>next<img src=\"a.png\"><p>hi</P></div>";
+    assert!(element().parse(doc).is_err());
+    assert_eq!(
+        Ok((
+            "",
+            "<DIV class=\"x\">line<BR/>next<img src=\"a.png\"/><p>hi</p></DIV>".to_string()
+        )),
+        element_with(ParseOptions::new().html(true))
+            .parse(doc)
+            .map(|(rest, el)| (rest, el.to_string()))
+    );
+}
+
+#[test]
+fn element_aliases() {
+    let options = ParseOptions::new()
+        .alias("Item", "item")
+        .alias("entry", "item");
+    assert_eq!(
+        Ok(("", "<feed><item/><item>a</item><other/></feed>".to_string())),
+        element_with(options)
+            .parse("<feed><Item/><entry>a</entry><other/></feed>")
+            .map(|(rest, el)| (rest, el.to_string()))
+    );
+}
+
+#[test]
+fn partial_parse() {
+    let input = "<a><b/></a>\x00binary trailer";
+    let (result, consumed) = bare_element().parse_partial(input);
+    assert_eq!(
+        Ok("<a><b/></a>".to_string()),
+        result.map(|el| el.to_string())
+    );
+    assert_eq!(11, consumed);
+    assert_eq!(
+        (Err(Error::Syntax { offset: 6 }), 0),
+        bare_element().parse_partial("<a><b></a>")
+    );
+}
+
+#[test]
+fn comments_and_processing_instructions() {
+    assert_eq!(
+        Ok(("", " a - b ".to_string())),
+        comment().parse("<!-- a - b -->")
+    );
+    assert_eq!(Err("<!-- a -- b -->"), comment().parse("<!-- a -- b -->"));
+    assert_eq!(
+        Ok(("", ("php".to_string(), "echo 1; ".to_string()))),
+        processing_instruction().parse("<?php echo 1; ?>")
+    );
+    assert_eq!(
+        Ok(("", ("end".to_string(), String::new()))),
+        processing_instruction().parse("<?end?>")
+    );
+    assert_eq!(
+        Err("<?XML version=\"1.0\"?>"),
+        processing_instruction().parse("<?XML version=\"1.0\"?>")
+    );
+    let el: Element = "<a>x<!--c--><?t d?></a>".parse().unwrap();
+    assert_eq!(
+        vec![
+            Node::Text("x".to_string()),
+            Node::Comment("c".to_string()),
+            Node::ProcessingInstruction {
+                target: "t".to_string(),
+                data: "d".to_string(),
+            },
+        ],
+        el.children
+    );
+    assert_eq!("<a>x<!--c--><?t d?></a>", el.to_string());
+}
+
+#[test]
+fn case_insensitive_matching() {
+    assert_eq!(Ok(("/>", ())), match_literal_ci("<br").parse("<BR/>"));
+    assert_eq!(Err("<b\u{e9}"), match_literal_ci("<br").parse("<b\u{e9}"));
+    assert_eq!(
+        Ok((" x", "my-element".to_string())),
+        identifier_ci("My-Element x")
+    );
+    let options = ParseOptions::new().fold_case(true).html(true);
+    let parse = |input| element_with(options.clone()).parse(input).map(|(_, el)| el);
+    assert_eq!(
+        parse("<p><br CLASS=\"a\"/></p>"),
+        parse("<P><BR class=\"a\"></p>")
+    );
+}
+
+#[test]
+fn prefixed_names() {
+    assert_eq!(
+        Ok((" x", "soap:Envelope".to_string())),
+        identifier("soap:Envelope x")
+    );
+    assert_eq!(Err("a::b"), identifier("a::b"));
+    assert_eq!(Err(":a"), identifier(":a"));
+    let el: Element = "<svg:a xlink:href=\"#x\"/>".parse().unwrap();
+    assert_eq!((Some("svg"), "a"), (el.prefix(), el.local_name()));
+    assert_eq!((Some("xlink"), "href"), split_name(&el.attributes[0].0));
+    assert!("<a:b:c/>".parse::<Element>().is_err());
+}
@@ -0,0 +1,132 @@
+use crate::{Element, Error};
+
+/// Builds a value from an element. With the `derive` feature,
+/// `#[derive(FromXml)]` implements it for structs whose fields are
+/// annotated with `#[xml(attr)]`, `#[xml(child = "name")]` or
+/// `#[xml(text)]`. The element's own name is not checked, so a type can be
+/// read from children with any name.
+pub trait FromXml: Sized {
+    fn from_xml(el: &Element) -> Result<Self, Error>;
+}
+
+/// Turns a value into an element; the counterpart of `FromXml`.
+pub trait ToXml {
+    fn to_xml(&self) -> Element;
+}
+
+// What `#[derive(FromXml, ToXml)]` expands to calls. Not a stable API.
+#[doc(hidden)]
+pub mod __private {
+    use core::fmt::Display;
+    use core::str::FromStr;
+
+    use super::{FromXml, ToXml};
+    use crate::prelude::*;
+    use crate::{Element, Error, Node};
+
+    pub fn attr<T: FromStr>(el: &Element, name: &str) -> Result<T, Error> {
+        optional_attr(el, name)?
+            .ok_or_else(|| Error::Mapping(format!("<{}> has no attribute {:?}", el.name, name)))
+    }
+
+    pub fn optional_attr<T: FromStr>(el: &Element, name: &str) -> Result<Option<T>, Error> {
+        el.attribute(name)
+            .map(|value| {
+                value.parse().map_err(|_| {
+                    Error::Mapping(format!(
+                        "invalid value {:?} for attribute {:?} of <{}>",
+                        value, name, el.name
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    pub fn child<T: FromXml>(el: &Element, name: &str) -> Result<T, Error> {
+        optional_child(el, name)?
+            .ok_or_else(|| Error::Mapping(format!("<{}> has no child <{}>", el.name, name)))
+    }
+
+    pub fn optional_child<T: FromXml>(el: &Element, name: &str) -> Result<Option<T>, Error> {
+        named(el, name).next().map(T::from_xml).transpose()
+    }
+
+    pub fn children<T: FromXml>(el: &Element, name: &str) -> Result<Vec<T>, Error> {
+        named(el, name).map(T::from_xml).collect()
+    }
+
+    pub fn text<T: FromStr>(el: &Element) -> Result<T, Error> {
+        let text = el.immediate_text();
+        text.parse()
+            .map_err(|_| Error::Mapping(format!("invalid text {:?} in <{}>", text, el.name)))
+    }
+
+    pub fn set_attr<T: Display>(el: &mut Element, name: &str, value: &T) {
+        el.attributes.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn push_child<T: ToXml>(el: &mut Element, name: &str, value: &T) {
+        let mut child = value.to_xml();
+        child.name = name.to_string();
+        el.children.push(Node::Element(child));
+    }
+
+    pub fn push_text<T: Display>(el: &mut Element, value: &T) {
+        el.children.push(Node::Text(value.to_string()));
+    }
+
+    fn named<'a>(el: &'a Element, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        el.children.iter().filter_map(move |child| match child {
+            Node::Element(child) if child.name == name => Some(child),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_mapping() {
+    use crate::prelude::*;
+    use crate::{FromXml, ToXml};
+
+    #[derive(Debug, PartialEq, FromXml, ToXml)]
+    #[xml(rename = "server")]
+    struct Server {
+        #[xml(attr)]
+        host: String,
+        #[xml(attr = "port")]
+        port: u16,
+        #[xml(attr)]
+        tls: Option<bool>,
+        #[xml(child = "alias")]
+        aliases: Vec<Alias>,
+        #[xml(child = "owner")]
+        owner: Option<Alias>,
+    }
+
+    #[derive(Debug, PartialEq, FromXml, ToXml)]
+    struct Alias {
+        #[xml(text)]
+        name: String,
+    }
+
+    let el: Element =
+        "<server host=\"example.org\" port=\"8080\"><alias>www</alias><alias>web</alias></server>"
+            .parse()
+            .unwrap();
+    let server = Server::from_xml(&el).unwrap();
+    assert_eq!(8080, server.port);
+    assert_eq!(None, server.tls);
+    assert_eq!(
+        vec!["www", "web"],
+        server
+            .aliases
+            .iter()
+            .map(|alias| alias.name.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(el, server.to_xml());
+
+    let bad: Element = "<server host=\"h\" port=\"x\"/>".parse().unwrap();
+    assert!(matches!(Server::from_xml(&bad), Err(Error::Mapping(_))));
+}
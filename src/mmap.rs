@@ -0,0 +1,154 @@
+use core::{ptr, slice};
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use crate::document::detect_encoding;
+use crate::{Document, Encoding, Error, Tokens};
+
+/// A file mapped read-only into memory. Its bytes are paged in as they are
+/// read rather than copied onto the heap, so `as_str` can be handed to the
+/// parsers, or to `extract_attributes` and `Tokens` to pull borrowed values
+/// out of a file far larger than memory.
+pub struct MappedFile {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl MappedFile {
+    /// Maps the file at `path`.
+    ///
+    /// # Safety
+    ///
+    /// Nothing may change or truncate the file while the mapping lives, in
+    /// this process or any other. The slices `as_bytes` and `as_str` hand out
+    /// would change under the borrow, and `as_str` could stop being UTF-8.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<MappedFile, Error> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        // Empty mappings are an error, and there is nothing to map anyway.
+        if len == 0 {
+            return Ok(MappedFile {
+                ptr: ptr::null_mut(),
+                len,
+            });
+        }
+        // A fresh private read-only mapping of an open file, which stays
+        // valid after the file is closed.
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(MappedFile { ptr, len })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: the mapping is `len` readable bytes until `drop`.
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    /// The mapped bytes as text, if they are UTF-8. A UTF-8 BOM is skipped.
+    pub fn as_str(&self) -> Result<&str, Error> {
+        let bytes = self.as_bytes();
+        let body = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+        core::str::from_utf8(body).map_err(|e| Error::Decode {
+            encoding: Encoding::Utf8,
+            offset: e.valid_up_to(),
+        })
+    }
+
+    /// The tokens of the mapped text. This is the zero-copy path: every
+    /// name, value and text run a token carries is a slice of the mapping,
+    /// left undecoded, and nothing is copied onto the heap.
+    pub fn tokens(&self) -> Result<Tokens<'_>, Error> {
+        Ok(Tokens::new(self.as_str()?))
+    }
+}
+
+// SAFETY: the mapping is only ever read.
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            // SAFETY: unmaps what `open` mapped, once.
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+impl Document {
+    /// Like `from_file`, but parses UTF-8 files straight from a memory map
+    /// instead of reading them into a buffer first. This saves the copy of
+    /// the input, not the tree's: `Document` owns its names and text, so
+    /// each is still copied into a `String`, and there is no borrowed tree.
+    /// For zero-copy reads, pull tokens with `MappedFile::tokens` instead.
+    /// Files in other encodings are decoded onto the heap.
+    ///
+    /// # Safety
+    ///
+    /// As for `MappedFile::open`: the file must not change until this
+    /// returns.
+    pub unsafe fn from_mmap<P: AsRef<Path>>(path: P) -> Result<Document, Error> {
+        let file = MappedFile::open(path)?;
+        match detect_encoding(file.as_bytes())? {
+            (Encoding::Utf8, _) => Document::parse(file.as_str()?),
+            _ => Document::from_bytes(file.as_bytes()),
+        }
+    }
+}
+
+#[test]
+fn mapped_files() {
+    use crate::{extract_attributes, prelude::*, Token};
+
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("simple-xmlparser-mmap-{}.xml", std::process::id()));
+    std::fs::write(&path, "\u{feff}<a><b id=\"1\"/><b id=\"2\"/></a>").unwrap();
+    let doc = unsafe { Document::from_mmap(&path) }.unwrap();
+    assert_eq!(Document::from_file(&path).unwrap(), doc);
+    let file = unsafe { MappedFile::open(&path) }.unwrap();
+    assert_eq!(
+        vec![("id", "1"), ("id", "2")],
+        extract_attributes(file.as_str().unwrap(), "b", &["id"]).unwrap()
+    );
+    let text = file.as_str().unwrap();
+    let values: Vec<_> = file
+        .tokens()
+        .unwrap()
+        .filter_map(|token| match token.unwrap().0 {
+            Token::AttrValue(value) => Some(value),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec!["1", "2"], values);
+    assert!(values
+        .iter()
+        .all(|value| text.as_bytes().as_ptr_range().contains(&value.as_ptr())));
+
+    std::fs::write(&path, "").unwrap();
+    assert_eq!(b"", unsafe { MappedFile::open(&path) }.unwrap().as_bytes());
+    assert!(matches!(
+        unsafe { Document::from_mmap(&path) },
+        Err(Error::Syntax { offset: 0 })
+    ));
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        unsafe { Document::from_mmap(&path) },
+        Err(Error::Io { .. })
+    ));
+}
@@ -0,0 +1,222 @@
+use crate::prelude::*;
+use crate::Element;
+
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExpandedName<'a> {
+    pub namespace: Option<&'a str>,
+    pub local: &'a str,
+}
+
+/// Splits a qualified name into its prefix and local part:
+/// `xlink:href` gives `(Some("xlink"), "href")`, `href` gives `(None, "href")`.
+pub fn split_name(name: &str) -> (Option<&str>, &str) {
+    match name.find(':') {
+        Some(index) => (Some(&name[..index]), &name[index + 1..]),
+        None => (None, name),
+    }
+}
+
+impl Element {
+    /// The prefix of the element's name, if it has one.
+    pub fn prefix(&self) -> Option<&str> {
+        split_name(&self.name).0
+    }
+
+    /// The element's name without its prefix.
+    pub fn local_name(&self) -> &str {
+        split_name(&self.name).1
+    }
+
+    /// The `xmlns` declarations made on this element as `(prefix, uri)`
+    /// pairs, where the default namespace has the empty prefix.
+    pub fn namespace_declarations(&self) -> Vec<(&str, &str)> {
+        self.attributes
+            .iter()
+            .filter_map(|(name, uri)| match split_name(name) {
+                (None, "xmlns") => Some(("", uri.as_str())),
+                (Some("xmlns"), prefix) => Some((prefix, uri.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The declarations on this element for URIs that `other` does not
+    /// declare under any prefix. Declarations are compared by URI, so
+    /// changing only a prefix is no change.
+    pub fn namespaces_missing_from(&self, other: &Element) -> Vec<(&str, &str)> {
+        let theirs = other.namespace_declarations();
+        self.namespace_declarations()
+            .into_iter()
+            .filter(|(_, uri)| !theirs.iter().any(|(_, u)| u == uri))
+            .collect()
+    }
+}
+
+pub(crate) fn is_namespace_declaration(name: &str) -> bool {
+    name == "xmlns" || name.starts_with("xmlns:")
+}
+
+/// Tracks the namespace bindings in effect while walking down a tree.
+#[derive(Clone, Debug)]
+pub struct NamespaceScope<'a> {
+    frames: Vec<Vec<(&'a str, &'a str)>>,
+}
+
+impl<'a> Default for NamespaceScope<'a> {
+    fn default() -> Self {
+        NamespaceScope {
+            frames: vec![vec![("xml", XML_NAMESPACE), ("xmlns", XMLNS_NAMESPACE)]],
+        }
+    }
+}
+
+impl<'a> NamespaceScope<'a> {
+    pub fn new() -> Self {
+        NamespaceScope::default()
+    }
+
+    pub fn push(&mut self, element: &'a Element) {
+        self.frames.push(element.namespace_declarations());
+    }
+
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    /// Resolves `prefix` (empty for the default namespace) to its URI. An
+    /// empty `xmlns=""` undeclares the default namespace.
+    pub fn resolve(&self, prefix: &str) -> Option<&'a str> {
+        self.frames
+            .iter()
+            .rev()
+            .flat_map(|frame| frame.iter().rev())
+            .find(|(bound, _)| *bound == prefix)
+            .map(|(_, uri)| *uri)
+            .filter(|uri| !uri.is_empty())
+    }
+
+    /// Each prefix bound by the elements pushed so far, with its URI.
+    pub fn in_scope(&self) -> Vec<(&'a str, &'a str)> {
+        let mut bindings: Vec<(&'a str, &'a str)> = Vec::new();
+        for &(prefix, _) in self.frames[1..].iter().flatten() {
+            if !bindings.iter().any(|(bound, _)| *bound == prefix) {
+                if let Some(uri) = self.resolve(prefix) {
+                    bindings.push((prefix, uri));
+                }
+            }
+        }
+        bindings
+    }
+
+    pub fn element_name(&self, element: &'a Element) -> ExpandedName<'a> {
+        let (prefix, local) = split_name(&element.name);
+        ExpandedName {
+            namespace: self.resolve(prefix.unwrap_or("")),
+            local,
+        }
+    }
+
+    /// The name of `element`, a child of the element pushed last, with its
+    /// own declarations taken into account.
+    pub fn child_name(&mut self, element: &'a Element) -> ExpandedName<'a> {
+        self.push(element);
+        let name = self.element_name(element);
+        self.pop();
+        name
+    }
+
+    /// The attributes of `element`, which must be the element pushed last,
+    /// with their expanded names, leaving out namespace declarations.
+    pub fn attributes(&self, element: &'a Element) -> Vec<(ExpandedName<'a>, &'a str, &'a str)> {
+        element
+            .attributes
+            .iter()
+            .filter(|(name, _)| !is_namespace_declaration(name))
+            .map(|(name, value)| (self.attribute_name(name), name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    // Unprefixed attributes are in no namespace, whatever the default is.
+    pub fn attribute_name(&self, name: &'a str) -> ExpandedName<'a> {
+        match split_name(name) {
+            (Some(prefix), local) => ExpandedName {
+                namespace: self.resolve(prefix),
+                local,
+            },
+            (None, local) => ExpandedName {
+                namespace: None,
+                local,
+            },
+        }
+    }
+}
+
+#[test]
+fn namespace_resolution() {
+    let root: Element = r#"<a:root xmlns:a="urn:x" xmlns="urn:default" id="1">
+            <child xmlns:b="urn:x" b:id="2"/>
+        </a:root>"#
+        .parse()
+        .unwrap();
+    let child = match &root.children[0] {
+        crate::Node::Element(el) => el,
+        _ => unreachable!(),
+    };
+    let mut scope = NamespaceScope::new();
+    scope.push(&root);
+    assert_eq!(
+        ExpandedName {
+            namespace: Some("urn:x"),
+            local: "root"
+        },
+        scope.element_name(&root)
+    );
+    assert_eq!(None, scope.attribute_name("id").namespace);
+    scope.push(child);
+    assert_eq!(Some("urn:default"), scope.element_name(child).namespace);
+    assert_eq!(scope.attribute_name("a:id"), scope.attribute_name("b:id"));
+    assert_eq!(Some(XML_NAMESPACE), scope.resolve("xml"));
+    scope.pop();
+    assert_eq!(None, scope.resolve("b"));
+}
+
+#[test]
+fn namespace_aware_diff() {
+    use crate::Diff;
+
+    let old: Element = r#"<a:root xmlns:a="urn:x" a:id="1"><a:child/></a:root>"#
+        .parse()
+        .unwrap();
+    let renamed: Element = r#"<root xmlns="urn:x" xmlns:b="urn:x" b:id="1"><child/></root>"#
+        .parse()
+        .unwrap();
+    assert_eq!(Vec::<Diff>::new(), old.diff(&renamed));
+
+    let moved: Element = r#"<a:root xmlns:a="urn:y" a:id="1"><a:child/></a:root>"#
+        .parse()
+        .unwrap();
+    let diffs = old.diff(&moved);
+    assert!(diffs.contains(&Diff::NamespaceRemoved {
+        path: "/a:root".to_string(),
+        prefix: "a".to_string(),
+        uri: "urn:x".to_string()
+    }));
+    assert!(diffs.contains(&Diff::NamespaceAdded {
+        path: "/a:root".to_string(),
+        prefix: "a".to_string(),
+        uri: "urn:y".to_string()
+    }));
+    assert!(diffs.contains(&Diff::NamespaceChanged {
+        path: "/a:root".to_string(),
+        from: Some("urn:x".to_string()),
+        to: Some("urn:y".to_string())
+    }));
+    assert!(!diffs
+        .iter()
+        .any(|diff| matches!(diff, Diff::Renamed { .. })));
+}
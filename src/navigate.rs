@@ -0,0 +1,221 @@
+use core::ops::Deref;
+
+use crate::prelude::*;
+use crate::{Document, Element, Node};
+
+/// An element together with the way down to it from the element navigation
+/// started at, so that it can move to parents and siblings as well as
+/// children. Siblings are elements: text, comments and processing
+/// instructions between them are skipped. Each step copies the way down,
+/// which is as long as the element is deep.
+#[derive(Clone, Debug)]
+pub struct ElementRef<'d> {
+    element: &'d Element,
+    // Each ancestor, outermost first, with the index among its children of
+    // the next element down.
+    pub(crate) path: Vec<(&'d Element, usize)>,
+}
+
+impl Element {
+    /// Starts navigating at this element, which has no parent.
+    pub fn navigate(&self) -> ElementRef<'_> {
+        ElementRef {
+            element: self,
+            path: Vec::new(),
+        }
+    }
+
+    /// Starts navigating at `descendant`, which must be this element or one
+    /// of its descendants (compared by address), with this element as the
+    /// outermost ancestor.
+    pub fn navigate_to<'d>(&'d self, descendant: &Element) -> Option<ElementRef<'d>> {
+        let mut stack = vec![self.navigate()];
+        while let Some(el) = stack.pop() {
+            if core::ptr::eq(el.element, descendant) {
+                return Some(el);
+            }
+            let children: Vec<_> = el.child_elements().collect();
+            stack.extend(children.into_iter().rev());
+        }
+        None
+    }
+}
+
+impl Document {
+    pub fn navigate(&self) -> ElementRef<'_> {
+        self.root().navigate()
+    }
+
+    /// Like `Element::navigate_to` from the root, for an element found some
+    /// other way, such as with `get_element_by_id`.
+    pub fn navigate_to(&self, element: &Element) -> Option<ElementRef<'_>> {
+        self.root().navigate_to(element)
+    }
+}
+
+impl<'d> ElementRef<'d> {
+    pub fn element(&self) -> &'d Element {
+        self.element
+    }
+
+    /// How many ancestors are above this element: 0 where navigation
+    /// started.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    pub fn parent(&self) -> Option<ElementRef<'d>> {
+        let mut path = self.path.clone();
+        let (parent, _) = path.pop()?;
+        Some(ElementRef {
+            element: parent,
+            path,
+        })
+    }
+
+    /// The parent, its parent and so on, up to where navigation started.
+    pub fn ancestors(&self) -> Ancestors<'d> {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    /// The child elements, in document order. Unlike `Element::children`
+    /// they can be navigated from in turn.
+    pub fn child_elements(&self) -> impl Iterator<Item = ElementRef<'d>> + '_ {
+        let element = self.element;
+        element
+            .children
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, child)| match child {
+                Node::Element(child) => Some(self.child(child, i)),
+                _ => None,
+            })
+    }
+
+    pub fn next_sibling(&self) -> Option<ElementRef<'d>> {
+        let &(parent, index) = self.path.last()?;
+        parent.children[index + 1..]
+            .iter()
+            .enumerate()
+            .find_map(|(i, child)| match child {
+                Node::Element(child) => Some(self.sibling(child, index + 1 + i)),
+                _ => None,
+            })
+    }
+
+    pub fn previous_sibling(&self) -> Option<ElementRef<'d>> {
+        let &(parent, index) = self.path.last()?;
+        parent.children[..index]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(i, child)| match child {
+                Node::Element(child) => Some(self.sibling(child, i)),
+                _ => None,
+            })
+    }
+
+    /// The elements after this one in document order, leaving out its own
+    /// descendants, as far as the end of the element navigation started at.
+    pub fn following(&self) -> Following<'d> {
+        Following {
+            next: self.after_subtree(),
+        }
+    }
+
+    fn child(&self, child: &'d Element, index: usize) -> ElementRef<'d> {
+        let mut path = self.path.clone();
+        path.push((self.element, index));
+        ElementRef {
+            element: child,
+            path,
+        }
+    }
+
+    fn sibling(&self, sibling: &'d Element, index: usize) -> ElementRef<'d> {
+        let mut path = self.path.clone();
+        if let Some(last) = path.last_mut() {
+            last.1 = index;
+        }
+        ElementRef {
+            element: sibling,
+            path,
+        }
+    }
+
+    // The first element after this one that is not inside it.
+    fn after_subtree(&self) -> Option<ElementRef<'d>> {
+        let mut el = self.clone();
+        loop {
+            match el.next_sibling() {
+                Some(sibling) => return Some(sibling),
+                None => el = el.parent()?,
+            }
+        }
+    }
+}
+
+impl<'d> Deref for ElementRef<'d> {
+    type Target = Element;
+
+    fn deref(&self) -> &Element {
+        self.element
+    }
+}
+
+pub struct Ancestors<'d> {
+    next: Option<ElementRef<'d>>,
+}
+
+impl<'d> Iterator for Ancestors<'d> {
+    type Item = ElementRef<'d>;
+
+    fn next(&mut self) -> Option<ElementRef<'d>> {
+        let el = self.next.take()?;
+        self.next = el.parent();
+        Some(el)
+    }
+}
+
+pub struct Following<'d> {
+    next: Option<ElementRef<'d>>,
+}
+
+impl<'d> Iterator for Following<'d> {
+    type Item = ElementRef<'d>;
+
+    fn next(&mut self) -> Option<ElementRef<'d>> {
+        let el = self.next.take()?;
+        self.next = el.child_elements().next().or_else(|| el.after_subtree());
+        Some(el)
+    }
+}
+
+#[test]
+fn navigation() {
+    let doc = Document::parse("<a><b><c/>text<d xml:id=\"d\"/></b><!-- x --><e><f/></e><g/></a>")
+        .unwrap();
+    let names = |els: &mut dyn Iterator<Item = ElementRef<'_>>| {
+        els.map(|el| el.name.clone()).collect::<Vec<_>>().join(" ")
+    };
+    let d = doc
+        .navigate_to(doc.get_element_by_id("d").unwrap())
+        .unwrap();
+    assert_eq!(2, d.depth());
+    assert_eq!("b a", names(&mut d.ancestors()));
+    assert_eq!("c", d.previous_sibling().unwrap().name);
+    assert!(d.next_sibling().is_none());
+    assert_eq!("e f g", names(&mut d.following()));
+
+    let b = d.parent().unwrap();
+    assert_eq!("e", b.next_sibling().unwrap().name);
+    assert_eq!("c d", names(&mut b.child_elements()));
+    assert_eq!("e f g", names(&mut b.following()));
+    let e = b.next_sibling().unwrap();
+    assert_eq!("b", e.previous_sibling().unwrap().name);
+    assert_eq!("g", names(&mut e.following()));
+    assert!(doc.navigate().parent().is_none());
+    assert!(doc.navigate_to(&Element::new("a")).is_none());
+}
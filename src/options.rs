@@ -0,0 +1,387 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+
+use crate::prelude::*;
+use crate::{
+    Element, Error, Node, Warning, WarningKind, DEFAULT_MAX_ENTITY_DEPTH,
+    DEFAULT_MAX_ENTITY_EXPANSION,
+};
+
+// The characters XML counts as whitespace.
+pub(crate) const XML_WHITESPACE: [char; 4] = [' ', '\t', '\r', '\n'];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Whitespace {
+    /// Keep text exactly as written, including whitespace-only runs.
+    Preserve,
+    /// Drop whitespace-only text, and strip whitespace where text meets the
+    /// start or end tag of its element. Whitespace next to a sibling, as in
+    /// `<p>Hello <b>world</b></p>`, is kept.
+    #[default]
+    Trim,
+    /// Like `Trim`, and also collapse whitespace runs to one space.
+    Collapse,
+}
+
+impl Whitespace {
+    // Rewrites `text` in place, only allocating when it changes. `first` and
+    // `last` tell whether it is the first and last child of its element.
+    fn apply(&self, text: &mut String, first: bool, last: bool) {
+        if *self == Whitespace::Preserve {
+            return;
+        }
+        if text.trim_matches(XML_WHITESPACE).is_empty() {
+            text.clear();
+            return;
+        }
+        let mut kept = text.as_str();
+        if first {
+            kept = kept.trim_start_matches(XML_WHITESPACE);
+        }
+        if last {
+            kept = kept.trim_end_matches(XML_WHITESPACE);
+        }
+        match self {
+            Whitespace::Collapse => {
+                let words: Vec<_> = kept
+                    .split(XML_WHITESPACE)
+                    .filter(|word| !word.is_empty())
+                    .collect();
+                let before = if kept.starts_with(XML_WHITESPACE) {
+                    " "
+                } else {
+                    ""
+                };
+                let after = if kept.ends_with(XML_WHITESPACE) {
+                    " "
+                } else {
+                    ""
+                };
+                let collapsed = format!("{}{}{}", before, words.join(" "), after);
+                if collapsed != *text {
+                    *text = collapsed;
+                }
+            }
+            _ if kept.len() == text.len() => {}
+            _ => *text = kept.to_string(),
+        }
+    }
+}
+
+pub fn is_void_element(name: &str) -> bool {
+    const VOID_ELEMENTS: [&str; 14] = [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ];
+    VOID_ELEMENTS
+        .iter()
+        .any(|void| void.eq_ignore_ascii_case(name))
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) whitespace: Whitespace,
+    pub(crate) lenient: bool,
+    pub(crate) html: bool,
+    pub(crate) fold_case: bool,
+    pub(crate) flag_value: String,
+    pub(crate) aliases: BTreeMap<String, String>,
+    pub(crate) entities: BTreeMap<String, String>,
+    max_entity_depth: Option<usize>,
+    max_entity_expansion: Option<usize>,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    /// Sets the policy for text outside `xml:space="preserve"` subtrees.
+    pub fn whitespace(mut self, policy: Whitespace) -> Self {
+        self.whitespace = policy;
+        self
+    }
+
+    /// In lenient mode, broken references are kept or replaced with U+FFFD,
+    /// and unquoted or missing attribute values are accepted. Each is
+    /// reported as a warning rather than failing the parse.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// The value given to attributes written without one, like `checked`,
+    /// which lenient mode accepts. Defaults to the empty string.
+    pub fn flag_value<S: Into<String>>(mut self, value: S) -> Self {
+        self.flag_value = value.into();
+        self
+    }
+
+    /// In HTML mode, void elements such as `<br>` need no `/>` or closing
+    /// tag, and closing tags match their opening tag case-insensitively.
+    pub fn html(mut self, html: bool) -> Self {
+        self.html = html;
+        self
+    }
+
+    /// Folds element and attribute names to ASCII lowercase as they are
+    /// parsed, so `<BR/>` and `<br/>` give the same tree and closing tags
+    /// match whatever their case.
+    pub fn fold_case(mut self, fold_case: bool) -> Self {
+        self.fold_case = fold_case;
+        self
+    }
+
+    /// Renames elements called `name` to `canonical` once they are parsed.
+    /// Tags are still matched as written, so `<Item>` must close with
+    /// `</Item>`.
+    pub fn alias<N, C>(mut self, name: N, canonical: C) -> Self
+    where
+        N: Into<String>,
+        C: Into<String>,
+    {
+        self.aliases.insert(name.into(), canonical.into());
+        self
+    }
+
+    /// Defines entities such as `("nbsp", "\u{a0}")` for documents that use
+    /// them without declaring them. The replacement text may refer to other
+    /// entities, and counts against the entity limits. A document's own
+    /// `<!ENTITY>` declarations take precedence.
+    pub fn with_entities(mut self, entities: &[(&str, &str)]) -> Self {
+        self.entities.extend(
+            entities
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        self
+    }
+
+    /// How many entities may be nested inside one another, as in `&a;`
+    /// whose text refers to `&b;`. Defaults to `DEFAULT_MAX_ENTITY_DEPTH`.
+    /// Exceeding it fails the parse with `Error::EntityLimit`.
+    pub fn max_entity_depth(mut self, depth: usize) -> Self {
+        self.max_entity_depth = Some(depth);
+        self
+    }
+
+    /// How many bytes of text expanding entities may produce over the whole
+    /// document, each reference counting at least its own length, which
+    /// stops a few small declarations from blowing up into gigabytes
+    /// ("billion laughs"). Defaults to `DEFAULT_MAX_ENTITY_EXPANSION`;
+    /// exceeding it fails the parse with `Error::EntityLimit`.
+    pub fn max_entity_expansion(mut self, bytes: usize) -> Self {
+        self.max_entity_expansion = Some(bytes);
+        self
+    }
+
+    pub(crate) fn entity_depth_limit(&self) -> usize {
+        self.max_entity_depth.unwrap_or(DEFAULT_MAX_ENTITY_DEPTH)
+    }
+
+    pub(crate) fn entity_expansion_limit(&self) -> usize {
+        self.max_entity_expansion
+            .unwrap_or(DEFAULT_MAX_ENTITY_EXPANSION)
+    }
+
+    // Applies everything that is done to the tree once parsing succeeded.
+    pub(crate) fn finish(&self, element: &mut Element) {
+        self.finish_element(element, self.whitespace);
+    }
+
+    fn finish_element(&self, element: &mut Element, inherited: Whitespace) {
+        if let Some(canonical) = self.aliases.get(&element.name) {
+            element.name = canonical.clone();
+        }
+        let policy = match element.attribute("xml:space") {
+            Some("preserve") => Whitespace::Preserve,
+            Some("default") => self.whitespace,
+            _ => inherited,
+        };
+        let count = element.children.len();
+        let mut index = 0;
+        element.children.retain_mut(|child| {
+            index += 1;
+            match child {
+                Node::Element(el) => {
+                    self.finish_element(el, policy);
+                    true
+                }
+                Node::Text(text) => {
+                    policy.apply(text, index == 1, index == count);
+                    !text.is_empty()
+                }
+                _ => true,
+            }
+        });
+    }
+}
+
+// State shared by the parsers of a single parse.
+#[derive(Debug, Default)]
+pub(crate) struct Context {
+    pub(crate) options: ParseOptions,
+    // Warnings are keyed by the length of the remaining input, since the
+    // parsers never see where the input started.
+    warnings: RefCell<Vec<(usize, WarningKind)>>,
+    // The general entities declared by the document type.
+    entities: RefCell<BTreeMap<String, String>>,
+    // Bytes produced by expanding entities so far.
+    pub(crate) expanded: Cell<usize>,
+    // An error that must win over whatever the failed parse reports, with
+    // the position of the failure keyed like warnings.
+    fatal: RefCell<Option<(usize, Fatal)>>,
+    pub(crate) attribute_hook: Option<AttributeHook>,
+}
+
+// What a `Context` keeps between the calls of a parser fed its input in
+// pieces. Unlike the context itself it can be sent to another thread.
+#[derive(Debug, Default)]
+pub(crate) struct Suspended {
+    pub(crate) options: ParseOptions,
+    entities: BTreeMap<String, String>,
+    expanded: usize,
+}
+
+impl Suspended {
+    pub(crate) fn new(options: ParseOptions) -> Suspended {
+        Suspended {
+            options,
+            ..Suspended::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Fatal {
+    EntityLimit,
+    AttributeValue(String),
+    ContentBeforeStart,
+}
+
+// Called with the element name, attribute name, value, and the length of
+// the input left at the value; false fails the parse there.
+pub(crate) struct AttributeHook(pub(crate) Rc<HookFn>);
+
+type HookFn = dyn Fn(&str, &str, &str, usize) -> bool;
+
+impl fmt::Debug for AttributeHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AttributeHook")
+    }
+}
+
+impl Context {
+    pub(crate) fn new(options: ParseOptions) -> Rc<Context> {
+        Rc::new(Context {
+            options,
+            warnings: RefCell::new(Vec::new()),
+            entities: RefCell::new(BTreeMap::new()),
+            expanded: Cell::new(0),
+            fatal: RefCell::new(None),
+            attribute_hook: None,
+        })
+    }
+
+    // Picks up where `suspend` left off, with no warnings pending.
+    pub(crate) fn resume(state: Suspended) -> Rc<Context> {
+        Rc::new(Context {
+            options: state.options,
+            warnings: RefCell::new(Vec::new()),
+            entities: RefCell::new(state.entities),
+            expanded: Cell::new(state.expanded),
+            fatal: RefCell::new(None),
+            attribute_hook: None,
+        })
+    }
+
+    // Takes the state back out, which only has to be copied if a parser
+    // built from the context is still alive.
+    pub(crate) fn suspend(context: Rc<Context>) -> Suspended {
+        match Rc::try_unwrap(context) {
+            Ok(context) => Suspended {
+                options: context.options,
+                entities: context.entities.into_inner(),
+                expanded: context.expanded.get(),
+            },
+            Err(context) => Suspended {
+                options: context.options.clone(),
+                entities: context.entities.borrow().clone(),
+                expanded: context.expanded.get(),
+            },
+        }
+    }
+
+    pub(crate) fn with_attribute_hook(options: ParseOptions, hook: AttributeHook) -> Rc<Context> {
+        let mut context = Context::new(options);
+        if let Some(context) = Rc::get_mut(&mut context) {
+            context.attribute_hook = Some(hook);
+        }
+        context
+    }
+
+    // The first declaration of an entity is the one that counts.
+    pub(crate) fn define_entity(&self, name: &str, value: &str) {
+        self.entities
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_insert_with(|| value.to_string());
+    }
+
+    #[cfg(feature = "rayon")]
+    pub(crate) fn declared_entities(&self) -> BTreeMap<String, String> {
+        self.entities.borrow().clone()
+    }
+
+    // Forgets what the last document declared, expanded and reported, so
+    // the context can parse another.
+    pub(crate) fn reset(&self) {
+        self.entities.borrow_mut().clear();
+        self.expanded.set(0);
+        self.warnings.borrow_mut().clear();
+        self.fatal.replace(None);
+    }
+
+    pub(crate) fn entity(&self, name: &str) -> Option<String> {
+        let declared = self.entities.borrow().get(name).cloned();
+        declared.or_else(|| self.options.entities.get(name).cloned())
+    }
+
+    pub(crate) fn fail(&self, rest: &str, fatal: Fatal) {
+        let mut slot = self.fatal.borrow_mut();
+        if slot.is_none() {
+            *slot = Some((rest.len(), fatal));
+        }
+    }
+
+    // The error recorded by `fail`, if any, with its offset into `input`.
+    pub(crate) fn take_fatal(&self, input: &str) -> Option<Error> {
+        let (remaining, fatal) = self.fatal.borrow_mut().take()?;
+        let offset = input.len() - remaining;
+        Some(match fatal {
+            Fatal::EntityLimit => Error::EntityLimit { offset },
+            Fatal::AttributeValue(name) => Error::InvalidAttributeValue { name, offset },
+            Fatal::ContentBeforeStart => Error::ContentBeforeStart { offset },
+        })
+    }
+
+    pub(crate) fn warn(&self, rest: &str, kind: WarningKind) {
+        self.warnings.borrow_mut().push((rest.len(), kind));
+    }
+
+    // Backtracking can parse the same input twice, so duplicates are dropped.
+    pub(crate) fn take_warnings(&self, input: &str) -> Vec<Warning> {
+        let mut warnings = self.warnings.replace(Vec::new());
+        warnings.sort_by_key(|(remaining, _)| core::cmp::Reverse(*remaining));
+        warnings.dedup();
+        warnings
+            .into_iter()
+            .map(|(remaining, kind)| Warning {
+                offset: input.len() - remaining,
+                kind,
+            })
+            .collect()
+    }
+}
@@ -1,8 +1,80 @@
+use crate::prelude::*;
+use crate::tokenizer::Span;
+use crate::{Error, IntoOwned};
+
+/// What parsers return: the input left after the value, or on failure the
+/// input left where parsing stopped. `Parser::parse_spanned` gives the same
+/// result as a `Parsed`.
 pub type ParseResult<'a, Output> = Result<(&'a str, Output), &'a str>;
 
+/// A value read by `Parser::parse_spanned`, with where it was read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Parsed<'a, T> {
+    pub value: T,
+    /// The input left after the value.
+    pub rest: &'a str,
+    /// The bytes of the whole input the value was read from.
+    pub consumed_span: Span,
+}
+
+impl<'a, T> Parsed<'a, T> {
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Parsed<'a, U> {
+        Parsed {
+            value: f(self.value),
+            rest: self.rest,
+            consumed_span: self.consumed_span,
+        }
+    }
+
+    /// The `(rest, value)` pair a `ParseResult` holds.
+    pub fn into_tuple(self) -> (&'a str, T) {
+        (self.rest, self.value)
+    }
+}
+
 pub trait Parser<'a, Output> {
     fn parse(&self, input: &'a str) -> ParseResult<'a, Output>;
 
+    /// Parses all of `input`, failing with the offset of the first byte left
+    /// over if the parser stops early.
+    fn parse_complete(&self, input: &'a str) -> Result<Output, Error> {
+        match self.parse(input) {
+            Ok(("", value)) => Ok(value),
+            Ok((rest, _)) | Err(rest) => Err(Error::Syntax {
+                offset: input.len() - rest.len(),
+            }),
+        }
+    }
+
+    /// Parses `input` from byte `at`, giving the span read and errors as
+    /// offsets into the whole of `input`, so that calling code can keep
+    /// track of where it is without slicing. An `at` past the end of
+    /// `input`, or inside a character, fails there.
+    fn parse_spanned(&self, input: &'a str, at: usize) -> Result<Parsed<'a, Output>, Error> {
+        let start = input.get(at..).ok_or(Error::Syntax {
+            offset: at.min(input.len()),
+        })?;
+        let (rest, value) = self.parse(start).into_owned(input)?;
+        Ok(Parsed {
+            value,
+            rest,
+            consumed_span: Span {
+                start: at,
+                end: input.len() - rest.len(),
+            },
+        })
+    }
+
+    /// Parses a prefix of `input` and says how many bytes it used, for input
+    /// that continues past what this parser should read. On failure nothing
+    /// is consumed and the error says where parsing stopped.
+    fn parse_partial(&self, input: &'a str) -> (Result<Output, Error>, usize) {
+        match self.parse(input).into_owned(input) {
+            Ok((rest, value)) => (Ok(value), input.len() - rest.len()),
+            Err(error) => (Err(error), 0),
+        }
+    }
+
     fn map<F, NewOutput>(self, map_fn: F) -> BoxedParser<'a, NewOutput>
     where
         Self: Sized + 'a,
@@ -61,41 +133,82 @@ impl<'a, Output> Parser<'a, Output> for BoxedParser<'a, Output> {
     }
 }
 
-pub fn the_letter_a(input: &str) -> ParseResult<()> {
+pub fn the_letter_a(input: &str) -> ParseResult<'_, ()> {
     match input.chars().next() {
         Some('a') => Ok((&input['a'.len_utf8()..], ())),
         _ => Err(input),
     }
 }
 
-pub fn any_char(input: &str) -> ParseResult<char> {
+pub fn any_char(input: &str) -> ParseResult<'_, char> {
     match input.chars().next() {
         Some(c) => Ok((&input[c.len_utf8()..], c)),
         _ => Err(input),
     }
 }
 
-pub fn identifier(input: &str) -> ParseResult<String> {
-    let mut matched = String::new();
-    let mut chars = input.chars();
+pub fn is_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}'
+        | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}'
+        | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
 
-    // The first character needs to be alphabetic.
-    match chars.next() {
-        Some(next) if next.is_alphabetic() => matched.push(next),
-        _ => return Err(input),
+pub fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(c,
+            '-' | '.' | '0'..='9' | '\u{B7}'
+            | '\u{300}'..='\u{36F}'
+            | '\u{203F}'..='\u{2040}')
+}
+
+/// A name with at most one colon, which must separate two non-empty parts
+/// (`soap:Envelope`, `xlink:href`); see `split_name`.
+pub fn identifier(input: &str) -> ParseResult<'_, String> {
+    let end = name_end(input).ok_or(input)?;
+    Ok((&input[end..], input[..end].to_string()))
+}
+
+// The length of the name `identifier` accepts at the start of `input`.
+pub(crate) fn name_end(input: &str) -> Option<usize> {
+    let end = local_name_end(input)?;
+    match input[end..].strip_prefix(':') {
+        Some(local) => Some(end + 1 + local_name_end(local)?),
+        None => Some(end),
     }
+}
 
-    // Next characters need to be either alphanumeric or a dash.
-    while let Some(next) = chars.next() {
-        if next.is_alphanumeric() || next == '-' {
-            matched.push(next);
-        } else {
-            break;
-        }
+// The length of the colon-free name at the start of `input`, if any. The
+// first character needs to be a NameStartChar, the rest NameChars.
+fn local_name_end(input: &str) -> Option<usize> {
+    let mut chars = input.char_indices();
+    match chars.next() {
+        Some((_, first)) if first != ':' && is_name_start_char(first) => {}
+        _ => return None,
     }
+    Some(
+        chars
+            .find(|&(_, next)| next == ':' || !is_name_char(next))
+            .map_or(input.len(), |(index, _)| index),
+    )
+}
 
-    let next_index = matched.len();
-    Ok((&input[next_index..], matched))
+/// Like `identifier`, but folds the name to ASCII lowercase so that `BR`,
+/// `Br` and `br` all produce `br`.
+pub fn identifier_ci(input: &str) -> ParseResult<'_, String> {
+    let (rest, mut name) = identifier(input)?;
+    name.make_ascii_lowercase();
+    Ok((rest, name))
 }
 
 pub fn match_literal<'a>(expected: &'static str) -> impl Parser<'a, ()> {
@@ -105,6 +218,14 @@ pub fn match_literal<'a>(expected: &'static str) -> impl Parser<'a, ()> {
     }
 }
 
+/// Like `match_literal`, ignoring ASCII case.
+pub fn match_literal_ci<'a>(expected: &'static str) -> impl Parser<'a, ()> {
+    move |input: &'a str| match input.get(..expected.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(expected) => Ok((&input[expected.len()..], ())),
+        _ => Err(input),
+    }
+}
+
 pub fn pair<'a, P1, P2, R1, R2>(parser1: P1, parser2: P2) -> impl Parser<'a, (R1, R2)>
 where
     P1: Parser<'a, R1>,
@@ -186,6 +307,16 @@ where
     }
 }
 
+pub fn optional<'a, P, A>(parser: P) -> impl Parser<'a, Option<A>>
+where
+    P: Parser<'a, A>,
+{
+    move |input| match parser.parse(input) {
+        Ok((next, value)) => Ok((next, Some(value))),
+        Err(_) => Ok((input, None)),
+    }
+}
+
 pub fn pred<'a, P, A, F>(parser: P, predicate: F) -> impl Parser<'a, A>
 where
     P: Parser<'a, A>,
@@ -213,19 +344,42 @@ pub fn space1<'a>() -> impl Parser<'a, Vec<char>> {
     one_or_more(whitespace())
 }
 
+// Same as `right(space0(), left(parser, space0()))`, without collecting
+// the whitespace.
 pub fn whitespace_wrap<'a, P, A>(parser: P) -> impl Parser<'a, A>
 where
     P: Parser<'a, A>,
 {
-    right(space0(), left(parser, space0()))
+    move |input: &'a str| {
+        let (rest, value) = parser.parse(input.trim_start())?;
+        Ok((rest.trim_start(), value))
+    }
 }
 
+/// Tries `parser1`, then `parser2`. If both fail, the error is the one that
+/// got further into the input, which is usually the branch that was meant.
 pub fn either<'a, P1, P2, A>(parser1: P1, parser2: P2) -> impl Parser<'a, A>
 where
     P1: Parser<'a, A>,
     P2: Parser<'a, A>,
 {
-    move |input| parser1.parse(input).or_else(|_| parser2.parse(input))
+    move |input| {
+        parser1.parse(input).or_else(|first| {
+            parser2
+                .parse(input)
+                .map_err(|second| furthest(first, second))
+        })
+    }
+}
+
+/// Of two failures on the same input, the one further along, preferring
+/// `second` on a tie.
+pub fn furthest<'a>(first: &'a str, second: &'a str) -> &'a str {
+    if first.len() < second.len() {
+        first
+    } else {
+        second
+    }
 }
 
 pub fn and_then<'a, P, Q, F, A, B>(parser: P, f: F) -> impl Parser<'a, B>
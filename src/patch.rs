@@ -0,0 +1,275 @@
+use crate::ids::id_index;
+use crate::prelude::*;
+use crate::{Document, Element, Error, Node};
+
+impl Element {
+    /// Applies an XML patch (RFC 5261): the `add`, `replace` and `remove`
+    /// children of `patch`, in order. Each `sel` is an absolute path from
+    /// this element (`/config/server[2]/port`), whose steps may carry
+    /// `[n]` or `[@name="value"]`, and may end in `/@name` to pick an
+    /// attribute. Every step must match exactly one element. `add` takes
+    /// `pos` (`before`, `after` or `prepend`; by default the content is
+    /// appended) or `type="@name"` to add an attribute. If any operation
+    /// fails the element is left as it was.
+    pub fn apply_patch(&mut self, patch: &Element) -> Result<(), Error> {
+        let mut patched = self.clone();
+        for op in patch.children.iter().filter_map(|child| match child {
+            Node::Element(op) => Some(op),
+            _ => None,
+        }) {
+            apply(&mut patched, op)?;
+        }
+        *self = patched;
+        Ok(())
+    }
+}
+
+impl Document {
+    /// `Element::apply_patch` on the root, keeping `get_element_by_id` fast.
+    pub fn apply_patch(&mut self, patch: &Element) -> Result<(), Error> {
+        self.root.apply_patch(patch)?;
+        self.ids = id_index(&self.root);
+        Ok(())
+    }
+}
+
+fn fail<T>(op: &Element, message: &str) -> Result<T, Error> {
+    let sel = op.attribute("sel").unwrap_or_default();
+    Err(Error::Patch(format!(
+        "<{} sel=\"{}\">: {}",
+        op.name, sel, message
+    )))
+}
+
+// What a `sel` picks: the element at the child indices `path` from the
+// root, or one of its attributes.
+struct Target<'s> {
+    path: Vec<usize>,
+    attribute: Option<&'s str>,
+}
+
+fn apply(root: &mut Element, op: &Element) -> Result<(), Error> {
+    let sel = match op.attribute("sel") {
+        Some(sel) => sel,
+        None => return fail(op, "no sel"),
+    };
+    let target = match select(root, sel) {
+        Ok(target) => target,
+        Err(message) => return fail(op, &message),
+    };
+    let content = || op.children.clone();
+    match (op.name.as_str(), target.attribute) {
+        ("add", None) => {
+            let el = element_at(root, &target.path);
+            if let Some(name) = op.attribute("type") {
+                let name = match name.strip_prefix('@') {
+                    Some(name) => name,
+                    None => return fail(op, "only attributes can be added by type"),
+                };
+                if el.attribute(name).is_some() {
+                    return fail(op, "the attribute is already there");
+                }
+                el.set_attribute(name, op.text_content());
+                return Ok(());
+            }
+            match op.attribute("pos") {
+                None => el.children.extend(content()),
+                Some("prepend") => {
+                    el.children.splice(0..0, content());
+                }
+                Some(pos @ ("before" | "after")) => {
+                    let (index, parent) = match target.path.split_last() {
+                        Some((&index, parent)) => (index, parent),
+                        None => return fail(op, "the root has no siblings"),
+                    };
+                    let at = if pos == "before" { index } else { index + 1 };
+                    element_at(root, parent).children.splice(at..at, content());
+                }
+                Some(_) => return fail(op, "pos must be before, after or prepend"),
+            }
+        }
+        ("replace", None) => {
+            let mut elements = op.children.iter().filter_map(|child| match child {
+                Node::Element(el) => Some(el),
+                _ => None,
+            });
+            match (elements.next(), elements.next()) {
+                (Some(el), None) => *element_at(root, &target.path) = el.clone(),
+                _ => return fail(op, "an element is replaced by exactly one element"),
+            }
+        }
+        ("remove", None) => match target.path.split_last() {
+            Some((&index, parent)) => {
+                element_at(root, parent).children.remove(index);
+            }
+            None => return fail(op, "the root can't be removed"),
+        },
+        ("replace", Some(name)) => {
+            let el = element_at(root, &target.path);
+            match el.attribute(name) {
+                Some(_) => el.set_attribute(name, op.text_content()),
+                None => return fail(op, "no such attribute"),
+            }
+        }
+        ("remove", Some(name)) => {
+            if element_at(root, &target.path)
+                .remove_attribute(name)
+                .is_none()
+            {
+                return fail(op, "no such attribute");
+            }
+        }
+        ("add", Some(_)) => return fail(op, "content can only be added to elements"),
+        _ => return fail(op, "unknown operation"),
+    }
+    Ok(())
+}
+
+fn element_at<'e>(root: &'e mut Element, path: &[usize]) -> &'e mut Element {
+    path.iter().fold(root, |el, &i| match &mut el.children[i] {
+        Node::Element(child) => child,
+        _ => unreachable!("paths lead through elements"),
+    })
+}
+
+fn select<'s>(root: &Element, sel: &'s str) -> Result<Target<'s>, String> {
+    let mut steps = split_steps(sel.strip_prefix('/').ok_or("sel must start with /")?)?;
+    let attribute = match steps.last().and_then(|step| step.strip_prefix('@')) {
+        Some(name) if name.contains('[') => return Err(format!("bad step @{}", name)),
+        Some(name) => {
+            steps.pop();
+            Some(name)
+        }
+        None => None,
+    };
+    let (first, rest) = steps.split_first().ok_or("sel names no element")?;
+    // The root is matched as the only child of a parent.
+    one(core::iter::once((0, root)), first)?;
+    let mut el = root;
+    let mut path = Vec::new();
+    for step in rest {
+        let children = el
+            .children
+            .iter()
+            .enumerate()
+            .filter_map(|(i, child)| match child {
+                Node::Element(child) => Some((i, child)),
+                _ => None,
+            });
+        let index = one(children, step)?;
+        path.push(index);
+        el = match &el.children[index] {
+            Node::Element(child) => child,
+            _ => unreachable!(),
+        };
+    }
+    Ok(Target { path, attribute })
+}
+
+// The index of the one element among `elements` matching `step`.
+fn one<'e, I>(elements: I, step: &str) -> Result<usize, String>
+where
+    I: Iterator<Item = (usize, &'e Element)>,
+{
+    let (name, predicate) = match step.find('[') {
+        Some(open) if step.ends_with(']') => (&step[..open], Some(&step[open + 1..step.len() - 1])),
+        Some(_) => return Err(format!("bad step {}", step)),
+        None => (step, None),
+    };
+    let named = elements.filter(|(_, el)| el.name == name);
+    let matches: Vec<usize> = match predicate {
+        None => named.map(|(i, _)| i).collect(),
+        Some(predicate) => match predicate.parse::<usize>() {
+            Ok(n) => named
+                .map(|(i, _)| i)
+                .skip(n.wrapping_sub(1))
+                .take(1)
+                .collect(),
+            Err(_) => {
+                let (attribute, value) = attribute_predicate(predicate)
+                    .ok_or_else(|| format!("bad predicate [{}]", predicate))?;
+                named
+                    .filter(|(_, el)| el.attribute(attribute) == Some(value))
+                    .map(|(i, _)| i)
+                    .collect()
+            }
+        },
+    };
+    match matches.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(format!("{} matches nothing", step)),
+        _ => Err(format!("{} matches {} elements", step, matches.len())),
+    }
+}
+
+// `@name="value"` or `@name='value'`.
+fn attribute_predicate(predicate: &str) -> Option<(&str, &str)> {
+    let (name, value) = predicate.strip_prefix('@')?.split_once('=')?;
+    let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let value = value.strip_prefix(quote)?.strip_suffix(quote)?;
+    Some((name.trim(), value))
+}
+
+// Splits at the slashes outside quoted predicate values.
+fn split_steps(sel: &str) -> Result<Vec<&str>, String> {
+    let mut steps = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in sel.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '/') => {
+                steps.push(&sel[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    steps.push(&sel[start..]);
+    match steps.iter().any(|step| step.is_empty()) {
+        true => Err(format!("bad sel /{}", sel)),
+        false => Ok(steps),
+    }
+}
+
+#[test]
+fn xml_patch() {
+    let mut doc = Document::parse(
+        r#"<config>
+            <server name="a"><port>80</port></server>
+            <server name="b" debug="1"><port>81</port></server>
+        </config>"#,
+    )
+    .unwrap();
+    let patch: Element = r#"<diff>
+            <replace sel="/config/server[@name='a']/port"><port>8080</port></replace>
+            <add sel="/config/server[2]" type="@tls">on</add>
+            <remove sel="/config/server[2]/@debug"/>
+            <add sel="/config/server[1]" pos="after"><server name="c"/></add>
+            <add sel="/config" pos="prepend"><!-- generated --></add>
+        </diff>"#
+        .parse()
+        .unwrap();
+    doc.apply_patch(&patch).unwrap();
+    assert_eq!(
+        "<config><!-- generated --><server name=\"a\"><port>8080</port></server>\
+         <server name=\"c\"/><server name=\"b\" tls=\"on\"><port>81</port></server></config>",
+        doc.root().to_string()
+    );
+
+    let before = doc.root().clone();
+    let failing: Element = r#"<diff>
+            <remove sel="/config/server[@name='c']"/>
+            <remove sel="/config/server/port"/>
+        </diff>"#
+        .parse()
+        .unwrap();
+    assert_eq!(
+        Err(Error::Patch(
+            "<remove sel=\"/config/server/port\">: server matches 2 elements".to_string()
+        )),
+        doc.apply_patch(&failing)
+    );
+    assert_eq!(&before, doc.root());
+}
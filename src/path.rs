@@ -0,0 +1,74 @@
+use crate::prelude::*;
+use crate::{Element, Node};
+
+impl Element {
+    /// The text of the element at `path`, a `/`-separated chain of child
+    /// names below this element (so `"server/port"` on `<config>` reads
+    /// `<config><server><port>`). Each step takes the first child with
+    /// that name; the text is its `immediate_text`.
+    pub fn get_path(&self, path: &str) -> Option<String> {
+        self.find_path(path).map(Element::immediate_text)
+    }
+
+    /// The element at `path`, found as by `get_path`.
+    pub fn find_path(&self, path: &str) -> Option<&Element> {
+        let mut el = self;
+        for name in steps(path) {
+            el = el.children.iter().find_map(|child| match child {
+                Node::Element(child) if child.name == name => Some(child),
+                _ => None,
+            })?;
+        }
+        Some(el)
+    }
+
+    /// Replaces the children of the element at `path` with `value`,
+    /// creating any missing elements on the way, and returns that element.
+    /// Steps follow the same first-match rule as `get_path`.
+    pub fn set_path<S: Into<String>>(&mut self, path: &str, value: S) -> &mut Element {
+        let mut el = self;
+        for name in steps(path) {
+            let found = el
+                .children
+                .iter()
+                .position(|child| matches!(child, Node::Element(child) if child.name == name));
+            let index = match found {
+                Some(index) => index,
+                None => {
+                    el.children.push(Node::Element(Element::new(name)));
+                    el.children.len() - 1
+                }
+            };
+            el = match &mut el.children[index] {
+                Node::Element(child) => child,
+                _ => unreachable!(),
+            };
+        }
+        el.children = vec![Node::Text(value.into())];
+        el
+    }
+}
+
+fn steps(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|step| !step.is_empty())
+}
+
+#[test]
+fn path_get_and_set() {
+    let mut config: Element = "<config><server><port>80</port></server></config>"
+        .parse()
+        .unwrap();
+    assert_eq!(Some("80".to_string()), config.get_path("server/port"));
+    assert_eq!(None, config.get_path("server/host"));
+
+    config.set_path("server/port", "8080");
+    config.set_path("server/tls/enabled", "true");
+    assert_eq!(
+        "<config><server><port>8080</port><tls><enabled>true</enabled></tls></server></config>",
+        config.to_string()
+    );
+    assert_eq!(
+        Some("true".to_string()),
+        config.get_path("/server/tls/enabled")
+    );
+}
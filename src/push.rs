@@ -0,0 +1,515 @@
+use alloc::rc::Rc;
+
+use crate::prelude::*;
+use crate::{
+    comment, doctype_at, identifier, identifier_ci, is_void_element, left, match_literal,
+    open_element_in, processing_instruction, right, single_element_in, space0, text_in,
+    xml_declaration, Context, Declaration, Element, Encoding, Error, Node, ParseOptions, Parser,
+    Suspended, Warning,
+};
+
+/// What a `PushParser` has seen complete so far.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Declaration(Declaration),
+    /// A start tag, as an element without children. A self-closing tag is
+    /// reported as `Start` followed by `End`.
+    Start(Element),
+    End(String),
+    /// Character data as written, with references decoded. The whitespace
+    /// policy is only applied to the tree.
+    Text(String),
+    Comment(String),
+    ProcessingInstruction {
+        target: String,
+        data: String,
+    },
+}
+
+/// Parses UTF-8 input that arrives in chunks, such as a stream read from a
+/// socket. Each `feed` returns the events completed by its chunk, holding
+/// back an incomplete token (or character) until more input arrives, and
+/// `finish` returns the tree built from them. A leading byte order mark is
+/// skipped, as by `Document::parse`. A document type is not reported as an
+/// event, but the entities it declares are expanded. For endless streams
+/// such as XMPP, `events_only` stops the tree from growing without bound.
+pub struct PushParser {
+    // The parsers' context is resumed from this for each chunk, so that the
+    // parser stays `Send`.
+    state: Suspended,
+    // The input not consumed yet, up to the last whole character.
+    text: String,
+    // The start of a character split across chunks.
+    partial: Vec<u8>,
+    // How far the token at the start of `text` has been searched for its
+    // end, so each chunk is only looked at once.
+    scan: Scan,
+    // Bytes of input consumed before `text`.
+    consumed: usize,
+    // Where the document starts, past any byte order mark.
+    start: usize,
+    stack: Vec<Element>,
+    root: Option<Element>,
+    seen_doctype: bool,
+    seen_root: bool,
+    build_tree: bool,
+    warnings: Vec<Warning>,
+    failed: Option<Error>,
+}
+
+enum Step {
+    Event(usize, Event),
+    // A self-closing tag, reported as a start and an end.
+    Empty(usize, Element),
+    Skip(usize),
+    Doctype(usize),
+    Incomplete,
+}
+
+// Where a search for the end of a token stopped, and the quoted value or
+// internal subset it stopped inside.
+#[derive(Clone, Copy, Debug, Default)]
+struct Scan {
+    at: usize,
+    quote: Option<u8>,
+    depth: usize,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new(ParseOptions::default())
+    }
+}
+
+impl PushParser {
+    pub fn new(options: ParseOptions) -> Self {
+        PushParser {
+            state: Suspended::new(options),
+            text: String::new(),
+            partial: Vec::new(),
+            scan: Scan::default(),
+            consumed: 0,
+            start: 0,
+            stack: Vec::new(),
+            root: None,
+            seen_doctype: false,
+            seen_root: false,
+            build_tree: true,
+            warnings: Vec::new(),
+            failed: None,
+        }
+    }
+
+    /// Reports events without keeping a tree; `finish` then fails.
+    pub fn events_only(mut self) -> Self {
+        self.build_tree = false;
+        self
+    }
+
+    /// The lenient-mode warnings for the input fed so far.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Adds `chunk` to the input and returns the events it completed. After
+    /// an error every later call returns the same error.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Event>, Error> {
+        if let Some(error) = &self.failed {
+            return Err(error.clone());
+        }
+        let mut events = Vec::new();
+        let result = self.decode(chunk).and_then(|_| self.drain(&mut events));
+        if let Err(error) = &result {
+            self.failed = Some(error.clone());
+        }
+        result.map(|_| events)
+    }
+
+    /// Ends the input, returning the root once it has been closed.
+    pub fn finish(mut self) -> Result<Element, Error> {
+        self.end()?;
+        let offset = self.consumed;
+        match self.root.take() {
+            Some(mut root) if self.build_tree => {
+                self.state.options.finish(&mut root);
+                Ok(root)
+            }
+            _ => Err(Error::Syntax { offset }),
+        }
+    }
+
+    // Checks that the input fed so far is a whole document.
+    pub(crate) fn end(&self) -> Result<(), Error> {
+        if let Some(error) = &self.failed {
+            return Err(error.clone());
+        }
+        let offset = self.consumed;
+        if !self.partial.is_empty() {
+            return Err(Error::Decode {
+                encoding: Encoding::Utf8,
+                offset: offset + self.text.len(),
+            });
+        }
+        let trailing = &self.text;
+        let skipped = trailing.len() - trailing.trim_start().len();
+        if !trailing.trim().is_empty() || !self.stack.is_empty() || !self.seen_root {
+            return Err(Error::Syntax {
+                offset: offset + skipped,
+            });
+        }
+        Ok(())
+    }
+
+    // Adds the whole characters of `chunk` to `text`, holding back one
+    // split by the end of the chunk.
+    fn decode(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        let joined;
+        let bytes = match self.partial.is_empty() {
+            true => chunk,
+            false => {
+                joined = [core::mem::take(&mut self.partial).as_slice(), chunk].concat();
+                joined.as_slice()
+            }
+        };
+        let (valid, error) = match core::str::from_utf8(bytes) {
+            Ok(text) => (text, None),
+            Err(error) => {
+                let (valid, _) = bytes.split_at(error.valid_up_to());
+                let valid = core::str::from_utf8(valid).unwrap_or_default();
+                (valid, Some(error))
+            }
+        };
+        self.text.push_str(valid);
+        match error {
+            Some(error) if error.error_len().is_some() => Err(Error::Decode {
+                encoding: Encoding::Utf8,
+                offset: self.consumed + self.text.len(),
+            }),
+            Some(_) => {
+                self.partial = bytes[valid.len()..].to_vec();
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn drain(&mut self, events: &mut Vec<Event>) -> Result<(), Error> {
+        let mut text = core::mem::take(&mut self.text);
+        let context = Context::resume(core::mem::take(&mut self.state));
+        let mut used = 0;
+        if self.consumed == 0 && text.starts_with('\u{FEFF}') {
+            used = '\u{FEFF}'.len_utf8();
+            self.consumed = used;
+            self.start = used;
+        }
+        let result = loop {
+            let (len, completed) = match self.step(&context, &text[used..]) {
+                Ok(Step::Event(len, event)) => (len, vec![event]),
+                Ok(Step::Empty(len, el)) => {
+                    let end = Event::End(el.name.clone());
+                    (len, vec![Event::Start(el), end])
+                }
+                Ok(Step::Skip(len)) => (len, Vec::new()),
+                Ok(Step::Doctype(len)) => {
+                    self.seen_doctype = true;
+                    (len, Vec::new())
+                }
+                Ok(Step::Incomplete) => break Ok(()),
+                Err(error) => break Err(error),
+            };
+            self.scan = Scan::default();
+            self.take_warnings(&context, &text[used..used + len]);
+            let html = context.options.html;
+            if let Err(error) = completed
+                .iter()
+                .try_for_each(|event| self.apply(html, event))
+            {
+                break Err(error);
+            }
+            self.consumed += len;
+            used += len;
+            events.extend(completed);
+        };
+        text.drain(..used);
+        self.text = text;
+        self.state = Context::suspend(context);
+        result
+    }
+
+    // Recognizes the token at the start of `input`, if it is complete.
+    fn step(&mut self, context: &Rc<Context>, input: &str) -> Result<Step, Error> {
+        let consumed = self.consumed;
+        let fail = |rest: &str| Error::Syntax {
+            offset: consumed + input.len() - rest.len(),
+        };
+        if input.is_empty() {
+            return Ok(Step::Incomplete);
+        }
+        if !input.starts_with('<') {
+            // Outside the root only whitespace may come between tokens.
+            if self.stack.is_empty() {
+                return match input.len() - input.trim_start().len() {
+                    0 => Err(fail(input)),
+                    skip => Ok(Step::Skip(skip)),
+                };
+            }
+            // Text only ends at a `<`, which may not have arrived yet.
+            let end = match self.scan.find(input, "<") {
+                Some(end) => end - 1,
+                None => return Ok(Step::Incomplete),
+            };
+            let (rest, text) = text_in(context.clone())
+                .parse(&input[..end])
+                .map_err(fail)?;
+            if !rest.is_empty() {
+                return Err(fail(rest));
+            }
+            return Ok(Step::Event(end, Event::Text(text)));
+        }
+        let doctype = "<!DOCTYPE";
+        let end = if input.starts_with("<!--") {
+            self.scan.find(input, "-->")
+        } else if input.starts_with("<?") {
+            self.scan.find(input, "?>")
+        } else if input.starts_with("</") {
+            self.scan.find(input, ">")
+        } else if doctype
+            .as_bytes()
+            .starts_with(&input.as_bytes()[..input.len().min(doctype.len())])
+        {
+            match input.len() < doctype.len() {
+                true => None,
+                false => self.scan.tag_end(input, true),
+            }
+        } else {
+            self.scan.tag_end(input, false)
+        };
+        let token = match end {
+            Some(end) => &input[..end],
+            None => return Ok(Step::Incomplete),
+        };
+        if token.starts_with(doctype) {
+            if self.seen_doctype || self.seen_root {
+                return Err(fail(input));
+            }
+            return match doctype_at(context, token) {
+                Ok(("", ())) => Ok(Step::Doctype(token.len())),
+                Ok((rest, _)) | Err(rest) => Err(fail(&input[token.len() - rest.len()..])),
+            };
+        }
+        let parsed = if token.starts_with("<!--") {
+            comment().map(Event::Comment).parse(token)
+        } else if token.starts_with("<?") {
+            match xml_declaration().parse(token) {
+                Ok((rest, declaration)) if self.consumed == self.start => {
+                    Ok((rest, Event::Declaration(declaration)))
+                }
+                _ => processing_instruction()
+                    .map(|(target, data)| Event::ProcessingInstruction { target, data })
+                    .parse(token),
+            }
+        } else if token.starts_with("</") {
+            let name = match context.options.fold_case {
+                true => identifier_ci,
+                false => identifier,
+            };
+            right(
+                match_literal("</"),
+                left(name, right(space0(), match_literal(">"))),
+            )
+            .map(Event::End)
+            .parse(token)
+        } else if let Ok(("", el)) = single_element_in(context.clone()).parse(token) {
+            return Ok(Step::Empty(token.len(), el));
+        } else {
+            open_element_in(context.clone())
+                .map(Event::Start)
+                .parse(token)
+        };
+        match parsed {
+            Ok(("", event)) => Ok(Step::Event(token.len(), event)),
+            Ok((rest, _)) | Err(rest) => Err(fail(&input[token.len() - rest.len()..])),
+        }
+    }
+
+    // Checks `event` against the open elements and adds it to the tree. A
+    // self-closing or HTML void start tag is closed straight away.
+    fn apply(&mut self, html: bool, event: &Event) -> Result<(), Error> {
+        let offset = self.consumed;
+        match event {
+            Event::Start(el) => {
+                if self.stack.is_empty() && self.seen_root {
+                    return Err(Error::Syntax { offset });
+                }
+                self.seen_root = true;
+                self.stack.push(el.clone());
+                if html && is_void_element(&el.name) {
+                    self.close();
+                }
+            }
+            Event::End(name) => {
+                match self.stack.last() {
+                    Some(open) if open.name == *name => {}
+                    Some(open) if html && open.name.eq_ignore_ascii_case(name) => {}
+                    _ => return Err(Error::Syntax { offset }),
+                }
+                self.close();
+            }
+            Event::Text(text) => self.append(Node::Text(text.clone())),
+            Event::Comment(text) => self.append(Node::Comment(text.clone())),
+            Event::ProcessingInstruction { target, data } => {
+                self.append(Node::ProcessingInstruction {
+                    target: target.clone(),
+                    data: data.clone(),
+                })
+            }
+            Event::Declaration(_) => {}
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        let el = match self.stack.pop() {
+            Some(el) => el,
+            None => return,
+        };
+        if !self.build_tree {
+            return;
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push(Node::Element(el)),
+            None => self.root = Some(el),
+        }
+    }
+
+    fn append(&mut self, node: Node) {
+        if let (true, Some(parent)) = (self.build_tree, self.stack.last_mut()) {
+            parent.children.push(node);
+        }
+    }
+
+    fn take_warnings(&mut self, context: &Context, token: &str) {
+        let consumed = self.consumed;
+        self.warnings
+            .extend(context.take_warnings(token).into_iter().map(|w| Warning {
+                offset: consumed + w.offset,
+                ..w
+            }));
+    }
+}
+
+impl Scan {
+    // The end of the first `delimiter` in `input`, or None having
+    // remembered how far there is no whole one.
+    fn find(&mut self, input: &str, delimiter: &str) -> Option<usize> {
+        let from = self.at.saturating_sub(delimiter.len() - 1);
+        let found = input.as_bytes()[from..]
+            .windows(delimiter.len())
+            .position(|window| window == delimiter.as_bytes());
+        match found {
+            Some(at) => Some(from + at + delimiter.len()),
+            None => {
+                self.at = input.len();
+                None
+            }
+        }
+    }
+
+    // The end of the tag at the start of `input`, past its `>` but not one
+    // inside a quoted value or, for a document type, the internal subset.
+    fn tag_end(&mut self, input: &str, subset: bool) -> Option<usize> {
+        for (i, &b) in input.as_bytes().iter().enumerate().skip(self.at) {
+            match (self.quote, b) {
+                (Some(q), b) if q == b => self.quote = None,
+                (Some(_), _) => {}
+                (None, b'"') | (None, b'\'') => self.quote = Some(b),
+                (None, b'[') if subset => self.depth += 1,
+                (None, b']') if subset => self.depth = self.depth.saturating_sub(1),
+                (None, b'>') if self.depth == 0 => return Some(i + 1),
+                _ => {}
+            }
+        }
+        self.at = input.len();
+        None
+    }
+}
+
+#[test]
+fn push_parser_chunks() {
+    let input = "<?xml version=\"1.0\"?><a x=\"1\">h\u{e9} &amp; <!--c--><b/></a>\n";
+    let mut parser = PushParser::default();
+    let mut events = Vec::new();
+    for byte in input.as_bytes() {
+        events.extend(parser.feed(&[*byte]).unwrap());
+    }
+    assert_eq!(7, events.len());
+    assert_eq!(Event::Text("h\u{e9} & ".to_string()), events[2]);
+    assert_eq!(Event::End("b".to_string()), events[5]);
+    let document = crate::Document::parse(input).unwrap();
+    assert_eq!(Ok(document.root().clone()), parser.finish());
+}
+
+#[test]
+fn push_parser_bom() {
+    let input = "\u{FEFF}<?xml version=\"1.0\"?><a/>".as_bytes();
+    let mut parser = PushParser::default();
+    let mut events = Vec::new();
+    for byte in input {
+        events.extend(parser.feed(&[*byte]).unwrap());
+    }
+    assert!(matches!(events[0], Event::Declaration(_)));
+    assert_eq!(Ok(Element::new("a")), parser.finish());
+    // Offsets count the mark, and only a leading one is skipped.
+    let mut parser = PushParser::default();
+    assert_eq!(
+        Err(Error::Syntax { offset: 6 }),
+        parser.feed("\u{FEFF}<a></b>".as_bytes())
+    );
+    let mut parser = PushParser::default();
+    assert!(parser.feed(b" \xEF\xBB\xBF<a/>").is_err());
+}
+
+#[test]
+fn push_parser_doctype() {
+    let input = "<!DOCTYPE a [<!ENTITY e \"x > y\">]>\n<a>&e;</a>";
+    let mut parser = PushParser::default();
+    let mut events = Vec::new();
+    for byte in input.as_bytes() {
+        events.extend(parser.feed(&[*byte]).unwrap());
+    }
+    assert_eq!(Event::Text("x > y".to_string()), events[1]);
+    let document = crate::Document::parse(input).unwrap();
+    assert_eq!(Ok(document.root().clone()), parser.finish());
+
+    let mut parser = PushParser::default();
+    assert_eq!(2, parser.feed(b"<!DOCTYPE a><a/>").unwrap().len());
+    let mut parser = PushParser::default();
+    assert_eq!(
+        Err(Error::Syntax { offset: 4 }),
+        parser.feed(b"<a/><!DOCTYPE a>")
+    );
+}
+
+#[test]
+fn push_parser_errors() {
+    let mut parser = PushParser::default();
+    assert_eq!(
+        Ok(vec![
+            Event::Start(Element::new("a")),
+            Event::Start(Element::new("b"))
+        ]),
+        parser.feed(b"<a><b>")
+    );
+    assert_eq!(Err(Error::Syntax { offset: 6 }), parser.feed(b"</a>"));
+    assert_eq!(Err(Error::Syntax { offset: 6 }), parser.feed(b"</b>"));
+
+    let mut stream = PushParser::default().events_only();
+    assert!(stream.feed(b"<stream><message>hi</message>").is_ok());
+    assert!(stream.finish().is_err());
+}
+
+#[test]
+fn push_parser_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<PushParser>();
+}
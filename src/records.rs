@@ -0,0 +1,197 @@
+use rayon::prelude::*;
+
+use crate::ids::id_index;
+use crate::parser::name_end;
+use crate::prelude::*;
+use crate::{
+    document_in, element_start_in, misc, node_in, prolog_in, whitespace_wrap, zero_or_more,
+    Context, Document, Element, Error, Node, ParseOptions, Parser, Warning,
+};
+
+// Content is not cut into pieces smaller than this, so small documents are
+// parsed in one go.
+const MIN_PIECE: usize = 1 << 16;
+
+/// Parses a document whose root holds many sibling records, such as
+/// `<records><r/>...</records>`, by cutting the root's content before the
+/// start tags of records and parsing the pieces on the rayon thread pool.
+/// The result is what `Document::parse_with` returns, which is called
+/// instead when the content cannot be cut, and when a piece fails so that
+/// errors report the same offsets.
+pub fn parse_records_parallel(input: &str, options: &ParseOptions) -> Result<Document, Error> {
+    let _span = span!("parse_records_parallel", bytes = input.len());
+    match cuts(input, options).and_then(|cuts| parse_pieces(input, options, &cuts)) {
+        Some(document) => Ok(document),
+        None => Document::parse_with(input, options),
+    }
+}
+
+// The offsets at which the root's content starts, is cut, and ends.
+fn cuts(input: &str, options: &ParseOptions) -> Option<Vec<usize>> {
+    // Void elements and implied closing tags leave no safe place to cut.
+    if options.html {
+        return None;
+    }
+    let context = Context::new(options.clone());
+    let (rest, _) = prolog_in(context.clone()).parse(input).ok()?;
+    let (rest, (name, _)) = element_start_in(context).parse(rest.trim_start()).ok()?;
+    let rest = rest.strip_prefix('>')?;
+    let start = input.len() - rest.len();
+    let content = &rest[..close_tag(rest, &name, options.fold_case)?];
+    let record = record_name(content)?;
+    let pieces = (content.len() / MIN_PIECE).min(rayon::current_num_threads() * 4);
+    if pieces < 2 {
+        return None;
+    }
+    let mut cuts = vec![start];
+    for i in 1..pieces {
+        let mut at = i * content.len() / pieces;
+        while !content.is_char_boundary(at) {
+            at += 1;
+        }
+        if let Some(found) = record_start(&content[at..], record) {
+            let cut = start + at + found;
+            if cut > cuts[cuts.len() - 1] {
+                cuts.push(cut);
+            }
+        }
+    }
+    cuts.push(start + content.len());
+    Some(cuts)
+}
+
+// Where the `</name>` closing the root starts: the last one followed by
+// nothing but an epilog.
+fn close_tag(content: &str, name: &str, fold_case: bool) -> Option<usize> {
+    let epilog = zero_or_more(whitespace_wrap(misc()));
+    let mut end = content.len();
+    while let Some(at) = content[..end].rfind("</") {
+        let tag = &content[at + 2..];
+        let matches = match tag.get(..name.len()) {
+            Some(written) if fold_case => written.eq_ignore_ascii_case(name),
+            Some(written) => written == name,
+            None => false,
+        };
+        if let Some(after) = tag[name.len()..].strip_prefix('>').filter(|_| matches) {
+            if let Ok((rest, _)) = epilog.parse(after.trim_start()) {
+                if rest.trim().is_empty() {
+                    return Some(at);
+                }
+            }
+        }
+        end = at;
+    }
+    None
+}
+
+// The name of the first element in the content, taken to be the record.
+fn record_name(content: &str) -> Option<&str> {
+    let mut rest = content;
+    loop {
+        rest = &rest[rest.find('<')? + 1..];
+        if let Some(len) = name_end(rest) {
+            return Some(&rest[..len]);
+        }
+    }
+}
+
+fn record_start(content: &str, record: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(found) = content[from..].find('<') {
+        let at = from + found;
+        let tag = &content[at + 1..];
+        let ends = |c: char| c.is_whitespace() || c == '/' || c == '>';
+        if tag.starts_with(record) && tag[record.len()..].starts_with(ends) {
+            return Some(at);
+        }
+        from = at + 1;
+    }
+    None
+}
+
+// Parses the document with the root's content cut out, then each piece with
+// the entities it declared, and puts the content back.
+fn parse_pieces(input: &str, options: &ParseOptions, cuts: &[usize]) -> Option<Document> {
+    let (start, end) = (cuts[0], cuts[cuts.len() - 1]);
+    let shell = format!("{}{}", &input[..start], &input[end..]);
+    let context = Context::new(options.clone());
+    let mut document = Document::parse_in(&shell, &document_in(context.clone()), &context).ok()?;
+    let mut piece_options = options.clone();
+    piece_options.entities.extend(context.declared_entities());
+    let pieces = cuts
+        .par_windows(2)
+        .map(|cut| parse_piece(&input[cut[0]..cut[1]], cut[0], &piece_options))
+        .collect::<Option<Vec<_>>>()?;
+
+    // Each piece counted its own expansion, so the limit is checked on the
+    // total.
+    let expanded = context.expanded.get() + pieces.iter().map(|p| p.2).sum::<usize>();
+    if expanded > options.entity_expansion_limit() {
+        return None;
+    }
+    let mut content = Element::new("");
+    content.attributes = document.root.attributes.clone();
+    let mut warnings = core::mem::take(&mut document.warnings);
+    for warning in &mut warnings {
+        if warning.offset >= start {
+            warning.offset += end - start;
+        }
+    }
+    for (nodes, piece_warnings, _) in pieces {
+        content.children.extend(nodes);
+        warnings.extend(piece_warnings);
+    }
+    warnings.sort_by_key(|warning| warning.offset);
+    options.finish(&mut content);
+    document.root.children = content.children;
+    document.warnings = warnings;
+    document.ids = id_index(&document.root);
+    Some(document)
+}
+
+fn parse_piece(
+    piece: &str,
+    offset: usize,
+    options: &ParseOptions,
+) -> Option<(Vec<Node>, Vec<Warning>, usize)> {
+    let context = Context::new(options.clone());
+    let nodes = match zero_or_more(node_in(context.clone())).parse(piece) {
+        Ok(("", nodes)) => nodes,
+        _ => return None,
+    };
+    if context.take_fatal(piece).is_some() {
+        return None;
+    }
+    let mut warnings = context.take_warnings(piece);
+    for warning in &mut warnings {
+        warning.offset += offset;
+    }
+    Some((nodes, warnings, context.expanded.get()))
+}
+
+#[test]
+fn parallel_records() {
+    let mut input = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE records [<!ENTITY v \"value\">]>\n<records xml:space=\"preserve\">\n",
+    );
+    for i in 0..20_000 {
+        input.push_str(&format!("  <r xml:id=\"r{}\"><v>&v; {}</v></r>\n", i, i));
+    }
+    input.push_str("</records>\n<!-- </records> -->\n");
+    assert!(cuts(&input, &ParseOptions::default()).map_or(0, |cuts| cuts.len()) > 2);
+    let options = ParseOptions::default();
+    let doc = parse_records_parallel(&input, &options).unwrap();
+    assert_eq!(Document::parse_with(&input, &options).unwrap(), doc);
+    let last = doc
+        .get_element_by_id("r19999")
+        .and_then(|r| r.get_path("v"));
+    assert_eq!(Some("value 19999"), last.as_deref());
+
+    // A piece that fails sends the whole input to a single parse, so the
+    // error is reported at the same offset.
+    let broken = input.replacen("<v>", "<r>", 1);
+    assert_eq!(
+        Document::parse_with(&broken, &options),
+        parse_records_parallel(&broken, &options)
+    );
+}
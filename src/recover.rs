@@ -0,0 +1,186 @@
+use crate::prelude::*;
+use crate::{
+    doctype_at, identifier, identifier_ci, is_void_element, left, match_literal, misc,
+    open_element_in, right, single_element_in, text_in, xml_declaration, Context, Element, Error,
+    Node, ParseOptions, Parser,
+};
+
+/// Parses `input` without stopping at the first error. Malformed markup is
+/// reported and skipped up to the next `<`, mismatched or missing closing
+/// tags close the open elements they imply, and whatever could be built is
+/// returned alongside every error found, in input order. `options` apply as
+/// they do to `Document::parse_with`, but lenient-mode warnings are not
+/// returned.
+pub fn parse_recovering(input: &str, options: &ParseOptions) -> (Option<Element>, Vec<Error>) {
+    let context = Context::new(options.clone());
+    let fold_case = options.html || options.fold_case;
+    let mut recovery = Recovery {
+        input,
+        fold_case,
+        stack: Vec::new(),
+        root: None,
+        errors: Vec::new(),
+    };
+    let name = match fold_case {
+        true => identifier_ci,
+        false => identifier,
+    };
+    let closing_tag = right(match_literal("</"), left(name, match_literal(">")));
+    let single_element = single_element_in(context.clone());
+    let open_element = open_element_in(context.clone());
+    let text = text_in(context.clone());
+    let mut rest = match xml_declaration().parse(input) {
+        Ok((next, _)) => next,
+        Err(_) => input,
+    };
+    let mut seen_doctype = false;
+
+    while !rest.is_empty() {
+        let start = recovery.offset(rest);
+        let prolog = !seen_doctype && recovery.root.is_none() && recovery.stack.is_empty();
+        if let (true, Ok((next, ()))) = (prolog, doctype_at(&context, rest)) {
+            seen_doctype = true;
+            rest = next;
+        } else if let Ok((next, name)) = closing_tag.parse(rest) {
+            recovery.close(&name, start);
+            rest = next;
+        } else if let Ok((next, el)) = single_element.parse(rest) {
+            recovery.attach(el, start);
+            rest = next;
+        } else if let Ok((next, el)) = open_element.parse(rest) {
+            match options.html && is_void_element(&el.name) {
+                true => recovery.attach(el, start),
+                false => recovery.stack.push((start, el)),
+            }
+            rest = next;
+        } else if let Ok((next, node)) = misc().parse(rest) {
+            // Outside the root these are dropped, as there is nowhere to keep them.
+            if let Some((_, el)) = recovery.stack.last_mut() {
+                el.children.push(node);
+            }
+            rest = next;
+        } else if let Ok((next, text)) = text.parse(rest) {
+            match recovery.stack.last_mut() {
+                Some((_, el)) => el.children.push(Node::Text(text)),
+                None if text.trim().is_empty() => {}
+                None => recovery.error(start),
+            }
+            rest = next;
+        } else {
+            // A `<` that starts nothing we understand: resynchronize.
+            recovery.error(start);
+            rest = match rest[1..].find('<') {
+                Some(index) => &rest[index + 1..],
+                None => "",
+            };
+        }
+    }
+
+    while let Some((start, el)) = recovery.stack.pop() {
+        recovery.error(start);
+        recovery.attach(el, start);
+    }
+    let mut root = recovery.root;
+    if let Some(root) = &mut root {
+        options.finish(root);
+    }
+    let mut errors = recovery.errors;
+    errors.extend(context.take_fatal(input));
+    errors.sort_by_key(|e| e.offset());
+    (root, errors)
+}
+
+struct Recovery<'a> {
+    input: &'a str,
+    fold_case: bool,
+    stack: Vec<(usize, Element)>,
+    root: Option<Element>,
+    errors: Vec<Error>,
+}
+
+impl<'a> Recovery<'a> {
+    fn offset(&self, rest: &str) -> usize {
+        self.input.len() - rest.len()
+    }
+
+    fn error(&mut self, offset: usize) {
+        self.errors.push(Error::Syntax { offset });
+    }
+
+    fn attach(&mut self, el: Element, start: usize) {
+        match self.stack.last_mut() {
+            Some((_, parent)) => parent.children.push(Node::Element(el)),
+            None if self.root.is_none() => self.root = Some(el),
+            None => self.error(start),
+        }
+    }
+
+    // Closes the innermost open element called `name`, reporting every
+    // element left unclosed inside it. A tag that closes nothing is dropped.
+    fn close(&mut self, name: &str, start: usize) {
+        let fold_case = self.fold_case;
+        let matches = |el: &Element| match fold_case {
+            true => el.name.eq_ignore_ascii_case(name),
+            false => el.name == name,
+        };
+        match self.stack.iter().rposition(|(_, el)| matches(el)) {
+            Some(index) => {
+                while self.stack.len() > index + 1 {
+                    let (open, el) = self.stack.pop().unwrap();
+                    self.error(open);
+                    self.attach(el, open);
+                }
+                let (open, el) = self.stack.pop().unwrap();
+                self.attach(el, open);
+            }
+            None => self.error(start),
+        }
+    }
+}
+
+#[test]
+fn recovering_parser() {
+    let doc = "<a><b><c/></x><!oops><d>text</b><e></a>";
+    let (root, errors) = parse_recovering(doc, &ParseOptions::default());
+    assert_eq!(
+        "<a><b><c/><d>text</d></b><e/></a>",
+        root.unwrap().to_string()
+    );
+    assert_eq!(
+        vec![
+            Error::Syntax { offset: 10 },
+            Error::Syntax { offset: 14 },
+            Error::Syntax { offset: 21 },
+            Error::Syntax { offset: 32 },
+        ],
+        errors
+    );
+}
+
+#[test]
+fn recovering_parser_on_valid_input() {
+    let doc = "<a> <b/> </a>";
+    let (root, errors) = parse_recovering(doc, &ParseOptions::default());
+    assert_eq!(Ok(root.unwrap()), doc.parse());
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn recovering_parser_options() {
+    let doc = "<!DOCTYPE a [<!ENTITY e \"x\">]><a>&e; &amp; &nbsp;<b></a>";
+    let options = ParseOptions::new().with_entities(&[("nbsp", "\u{a0}")]);
+    let (root, errors) = parse_recovering(doc, &options);
+    assert_eq!("<a>x &amp; \u{a0}<b/></a>", root.unwrap().to_string());
+    let offset = doc.find("<b>").unwrap();
+    assert_eq!(vec![Error::Syntax { offset }], errors);
+
+    let lenient = ParseOptions::new().lenient(true);
+    let (root, errors) = parse_recovering("<a x=1>Q&A</a>", &lenient);
+    assert_eq!("<a x=\"1\">Q&amp;A</a>", root.unwrap().to_string());
+    assert!(errors.is_empty());
+
+    let html = ParseOptions::new().html(true);
+    let (root, errors) = parse_recovering("<P>one<BR>two</p>", &html);
+    assert_eq!("<P>one<BR/>two</P>", root.unwrap().to_string());
+    assert!(errors.is_empty());
+}
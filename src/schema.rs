@@ -0,0 +1,338 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use crate::diff::child_paths;
+use crate::prelude::*;
+use crate::{Element, Node};
+
+/// How many times a child element may appear within its parent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cardinality {
+    One,
+    Optional,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+impl Cardinality {
+    fn bounds(self) -> (usize, Option<usize>) {
+        match self {
+            Cardinality::One => (1, Some(1)),
+            Cardinality::Optional => (0, Some(1)),
+            Cardinality::ZeroOrMore => (0, None),
+            Cardinality::OneOrMore => (1, None),
+        }
+    }
+}
+
+/// What an element's own text, all its text children joined, may contain.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum TextRule {
+    /// Only whitespace.
+    #[default]
+    Empty,
+    Any,
+    /// Any text the function accepts, including none at all.
+    Matches(fn(&str) -> bool),
+}
+
+/// The rules for one element name. Children and attributes not declared
+/// here are errors, unless `any_attributes` is set. Namespace declarations
+/// are always allowed.
+#[derive(Clone, Debug, Default)]
+pub struct ElementRule {
+    attributes: Vec<(String, bool)>,
+    any_attributes: bool,
+    children: Vec<(String, Cardinality)>,
+    text: TextRule,
+}
+
+impl ElementRule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required(mut self, attribute: &str) -> Self {
+        self.attributes.push((attribute.to_string(), true));
+        self
+    }
+
+    pub fn optional(mut self, attribute: &str) -> Self {
+        self.attributes.push((attribute.to_string(), false));
+        self
+    }
+
+    pub fn any_attributes(mut self) -> Self {
+        self.any_attributes = true;
+        self
+    }
+
+    pub fn child(mut self, name: &str, cardinality: Cardinality) -> Self {
+        self.children.push((name.to_string(), cardinality));
+        self
+    }
+
+    pub fn text(mut self, rule: TextRule) -> Self {
+        self.text = rule;
+        self
+    }
+}
+
+/// A set of element rules checked against a tree by `validate`. Every
+/// element that appears must have a rule of its own.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    root: String,
+    elements: BTreeMap<String, ElementRule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationErrorKind {
+    UnexpectedRoot(String),
+    UndeclaredElement(String),
+    UnexpectedChild(String),
+    MissingAttribute(String),
+    UnexpectedAttribute(String),
+    TooFew {
+        name: String,
+        min: usize,
+        found: usize,
+    },
+    TooMany {
+        name: String,
+        max: usize,
+        found: usize,
+    },
+    UnexpectedText,
+    InvalidText(String),
+    /// The child elements don't follow the declared sequence or choice.
+    InvalidContent,
+    InvalidAttributeValue {
+        name: String,
+        value: String,
+    },
+}
+
+/// A rule broken by the element at `path` (`/a/b[2]`, as in `Diff`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: String,
+    pub kind: ValidationErrorKind,
+}
+
+impl Schema {
+    pub fn new(root: &str) -> Self {
+        Schema {
+            root: root.to_string(),
+            elements: BTreeMap::new(),
+        }
+    }
+
+    pub fn element(mut self, name: &str, rule: ElementRule) -> Self {
+        self.elements.insert(name.to_string(), rule);
+        self
+    }
+
+    /// Checks the whole tree, reporting every broken rule in document order.
+    pub fn validate(&self, root: &Element) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let path = format!("/{}", root.name);
+        if root.name != self.root {
+            errors.push(ValidationError {
+                path: path.clone(),
+                kind: ValidationErrorKind::UnexpectedRoot(root.name.clone()),
+            });
+        }
+        let mut stack = vec![(root, path)];
+        while let Some((el, path)) = stack.pop() {
+            let rule = match self.elements.get(&el.name) {
+                Some(rule) => rule,
+                None => {
+                    errors.push(ValidationError {
+                        path,
+                        kind: ValidationErrorKind::UndeclaredElement(el.name.clone()),
+                    });
+                    continue;
+                }
+            };
+            let mut error = |kind| {
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    kind,
+                })
+            };
+            check_attributes(el, rule, &mut error);
+            check_text(el, rule, &mut error);
+            check_children(el, rule, &mut error);
+            let children = child_paths(&path, &el.children);
+            let nested = el.children.iter().zip(children).rev();
+            stack.extend(nested.filter_map(|(child, path)| match child {
+                Node::Element(child) if rule.children.iter().any(|(n, _)| *n == child.name) => {
+                    Some((child, path))
+                }
+                _ => None,
+            }));
+        }
+        errors
+    }
+}
+
+fn check_attributes(el: &Element, rule: &ElementRule, error: &mut impl FnMut(ValidationErrorKind)) {
+    for (name, required) in &rule.attributes {
+        if *required && el.attribute(name).is_none() {
+            error(ValidationErrorKind::MissingAttribute(name.clone()));
+        }
+    }
+    if rule.any_attributes {
+        return;
+    }
+    for (name, _) in &el.attributes {
+        let declared = rule.attributes.iter().any(|(n, _)| n == name);
+        if !declared && name != "xmlns" && !name.starts_with("xmlns:") {
+            error(ValidationErrorKind::UnexpectedAttribute(name.clone()));
+        }
+    }
+}
+
+fn check_text(el: &Element, rule: &ElementRule, error: &mut impl FnMut(ValidationErrorKind)) {
+    let text: String = el
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            Node::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    match rule.text {
+        TextRule::Empty if !text.trim().is_empty() => error(ValidationErrorKind::UnexpectedText),
+        TextRule::Matches(accepts) if !accepts(&text) => {
+            error(ValidationErrorKind::InvalidText(text))
+        }
+        _ => {}
+    }
+}
+
+fn check_children(el: &Element, rule: &ElementRule, error: &mut impl FnMut(ValidationErrorKind)) {
+    for child in &el.children {
+        if let Node::Element(child) = child {
+            if !rule.children.iter().any(|(name, _)| *name == child.name) {
+                error(ValidationErrorKind::UnexpectedChild(child.name.clone()));
+            }
+        }
+    }
+    for (name, cardinality) in &rule.children {
+        let found = el
+            .children
+            .iter()
+            .filter(|child| matches!(child, Node::Element(c) if c.name == *name))
+            .count();
+        let (min, max) = cardinality.bounds();
+        if found < min {
+            error(ValidationErrorKind::TooFew {
+                name: name.clone(),
+                min,
+                found,
+            });
+        }
+        match max {
+            Some(max) if found > max => error(ValidationErrorKind::TooMany {
+                name: name.clone(),
+                max,
+                found,
+            }),
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.kind {
+            ValidationErrorKind::UnexpectedRoot(name) => {
+                write!(f, "unexpected root element `{}`", name)
+            }
+            ValidationErrorKind::UndeclaredElement(name) => {
+                write!(f, "no rule for element `{}`", name)
+            }
+            ValidationErrorKind::UnexpectedChild(name) => {
+                write!(f, "unexpected child element `{}`", name)
+            }
+            ValidationErrorKind::MissingAttribute(name) => {
+                write!(f, "missing required attribute `{}`", name)
+            }
+            ValidationErrorKind::UnexpectedAttribute(name) => {
+                write!(f, "unexpected attribute `{}`", name)
+            }
+            ValidationErrorKind::TooFew { name, min, found } => write!(
+                f,
+                "expected at least {} `{}` element(s), found {}",
+                min, name, found
+            ),
+            ValidationErrorKind::TooMany { name, max, found } => write!(
+                f,
+                "expected at most {} `{}` element(s), found {}",
+                max, name, found
+            ),
+            ValidationErrorKind::UnexpectedText => f.write_str("unexpected text content"),
+            ValidationErrorKind::InvalidText(text) => write!(f, "invalid text `{}`", text),
+            ValidationErrorKind::InvalidContent => {
+                f.write_str("child elements do not match the content model")
+            }
+            ValidationErrorKind::InvalidAttributeValue { name, value } => {
+                write!(f, "invalid value `{}` for attribute `{}`", value, name)
+            }
+        }
+    }
+}
+
+#[test]
+fn schema_validation() {
+    let schema = Schema::new("config")
+        .element(
+            "config",
+            ElementRule::new()
+                .required("version")
+                .child("server", Cardinality::OneOrMore)
+                .child("debug", Cardinality::Optional),
+        )
+        .element(
+            "server",
+            ElementRule::new()
+                .required("host")
+                .optional("port")
+                .text(TextRule::Empty),
+        )
+        .element(
+            "debug",
+            ElementRule::new().text(TextRule::Matches(|text| text == "true" || text == "false")),
+        );
+
+    let ok: Element = "<config version=\"1\"><server host=\"a\"/><debug>true</debug></config>"
+        .parse()
+        .unwrap();
+    assert!(schema.validate(&ok).is_empty());
+
+    let bad: Element = "<config><server port=\"1\" tls=\"y\"/><server host=\"b\">x</server>\
+                        <debug>yes</debug><debug>no</debug><extra/></config>"
+        .parse()
+        .unwrap();
+    let errors: Vec<_> = schema
+        .validate(&bad)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "/config: missing required attribute `version`",
+            "/config: unexpected child element `extra`",
+            "/config: expected at most 1 `debug` element(s), found 2",
+            "/config/server[1]: missing required attribute `host`",
+            "/config/server[1]: unexpected attribute `tls`",
+            "/config/server[2]: unexpected text content",
+            "/config/debug[1]: invalid text `yes`",
+            "/config/debug[2]: invalid text `no`",
+        ],
+        errors
+    );
+}
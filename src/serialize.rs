@@ -0,0 +1,196 @@
+use core::fmt;
+
+use crate::prelude::*;
+use crate::{Element, Encoding, Error, Node};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SerializeOptions {
+    pub(crate) encoding: Option<Encoding>,
+    pub(crate) max_size: Option<usize>,
+    pub(crate) indent: Option<String>,
+}
+
+impl SerializeOptions {
+    pub fn new() -> Self {
+        SerializeOptions::default()
+    }
+
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Fails serialization with `Error::OutputTooLarge` once the output
+    /// would exceed `bytes`, instead of producing it in full.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Puts each child on its own line, indented by `unit` once per level.
+    /// Elements with text among their children are written on one line, as
+    /// the added whitespace would change the text.
+    pub fn indent<S: Into<String>>(mut self, unit: S) -> Self {
+        self.indent = Some(unit.into());
+        self
+    }
+
+    pub(crate) fn write_element<W: fmt::Write>(&self, out: &mut W, el: &Element) -> fmt::Result {
+        match &self.indent {
+            Some(unit) => write_indented(out, el, unit, 0),
+            None => write!(out, "{}", el),
+        }
+    }
+
+    pub(crate) fn check_size(&self, size: usize) -> Result<(), Error> {
+        match self.max_size {
+            Some(limit) if size > limit => Err(Error::OutputTooLarge { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn write<F>(&self, write_fn: F) -> Result<String, Error>
+    where
+        F: FnOnce(&mut LimitedWriter) -> fmt::Result,
+    {
+        let mut out = LimitedWriter {
+            out: String::new(),
+            limit: self.max_size,
+        };
+        // The writer is the only thing that can fail, and only on the limit.
+        write_fn(&mut out).map_err(|_| Error::OutputTooLarge {
+            limit: self.max_size.unwrap_or(0),
+        })?;
+        Ok(out.out)
+    }
+}
+
+pub(crate) struct LimitedWriter {
+    out: String,
+    limit: Option<usize>,
+}
+
+impl fmt::Write for LimitedWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.limit {
+            Some(limit) if self.out.len() + s.len() > limit => Err(fmt::Error),
+            _ => {
+                self.out.push_str(s);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Element {
+    pub fn to_string_with(&self, options: &SerializeOptions) -> Result<String, Error> {
+        options.write(|out| options.write_element(out, self))
+    }
+}
+
+fn write_indented<W: fmt::Write>(
+    out: &mut W,
+    el: &Element,
+    unit: &str,
+    depth: usize,
+) -> fmt::Result {
+    let text = el
+        .children
+        .iter()
+        .any(|child| matches!(child, Node::Text(_)));
+    if el.children.is_empty() || text {
+        return write!(out, "{}", el);
+    }
+    write_start_tag(out, el)?;
+    out.write_str(">")?;
+    for child in &el.children {
+        write!(out, "\n{}", unit.repeat(depth + 1))?;
+        match child {
+            Node::Element(child) => write_indented(out, child, unit, depth + 1)?,
+            other => write!(out, "{}", other)?,
+        }
+    }
+    write!(out, "\n{}</{}>", unit.repeat(depth), el.name)
+}
+
+fn write_start_tag<W: fmt::Write>(out: &mut W, el: &Element) -> fmt::Result {
+    write!(out, "<{}", el.name)?;
+    for (name, value) in &el.attributes {
+        write!(out, " {}=\"{}\"", name, escape_attribute(value))?;
+    }
+    Ok(())
+}
+
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Written as references so that normalization keeps them.
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_start_tag(f, self)?;
+        if self.children.is_empty() {
+            return f.write_str("/>");
+        }
+        f.write_str(">")?;
+        for child in &self.children {
+            write!(f, "{}", child)?;
+        }
+        write!(f, "</{}>", self.name)
+    }
+}
+
+// Comment and processing instruction bodies are written verbatim: the
+// parser never produces one that could not be read back.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Node::Element(el) => write!(f, "{}", el),
+            Node::Text(text) => f.write_str(&escape_text(text)),
+            Node::Comment(comment) => write!(f, "<!--{}-->", comment),
+            Node::ProcessingInstruction { target, data } if data.is_empty() => {
+                write!(f, "<?{}?>", target)
+            }
+            Node::ProcessingInstruction { target, data } => write!(f, "<?{} {}?>", target, data),
+        }
+    }
+}
+
+#[test]
+fn indented_output() {
+    let el: Element = "<a><b x=\"1\"><c/></b><!--n--><p>mixed<i>text</i></p></a>"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        Ok(
+            "<a>\n  <b x=\"1\">\n    <c/>\n  </b>\n  <!--n-->\n  <p>mixed<i>text</i></p>\n</a>"
+                .to_string()
+        ),
+        el.to_string_with(&SerializeOptions::new().indent("  "))
+    );
+}
@@ -0,0 +1,269 @@
+use core::str::FromStr;
+
+use crate::prelude::*;
+use crate::{split_name, Document, Element, Error, FromXml, NamespaceScope, Node, ToXml};
+
+pub const SOAP11_NAMESPACE: &str = "http://schemas.xmlsoap.org/soap/envelope/";
+pub const SOAP12_NAMESPACE: &str = "http://www.w3.org/2003/05/soap-envelope";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoapVersion {
+    Soap11,
+    Soap12,
+}
+
+impl SoapVersion {
+    pub fn namespace(&self) -> &'static str {
+        match self {
+            SoapVersion::Soap11 => SOAP11_NAMESPACE,
+            SoapVersion::Soap12 => SOAP12_NAMESPACE,
+        }
+    }
+
+    fn from_namespace(uri: &str) -> Option<SoapVersion> {
+        match uri {
+            SOAP11_NAMESPACE => Some(SoapVersion::Soap11),
+            SOAP12_NAMESPACE => Some(SoapVersion::Soap12),
+            _ => None,
+        }
+    }
+}
+
+/// A SOAP 1.1 or 1.2 envelope. Entries taken out of a parsed envelope keep
+/// the namespace declarations that were in scope, so each stands alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    pub version: SoapVersion,
+    pub header: Vec<Element>,
+    pub body: Vec<Element>,
+    /// The `Fault` in the body, which is also in `body`.
+    pub fault: Option<Fault>,
+}
+
+/// A SOAP fault. In SOAP 1.1 the code is `faultcode` and the reason
+/// `faultstring`; in SOAP 1.2 they are the first `Code/Value` and
+/// `Reason/Text`. The code is a qualified name such as `soap:Server`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub code: String,
+    pub reason: String,
+    pub detail: Option<Element>,
+}
+
+impl Envelope {
+    /// A SOAP 1.1 envelope with `body` as its only body entry.
+    pub fn wrap(body: Element) -> Envelope {
+        Envelope {
+            version: SoapVersion::Soap11,
+            header: Vec::new(),
+            body: vec![body],
+            fault: None,
+        }
+    }
+
+    pub fn version(mut self, version: SoapVersion) -> Envelope {
+        self.version = version;
+        self
+    }
+
+    pub fn with_header(mut self, entry: Element) -> Envelope {
+        self.header.push(entry);
+        self
+    }
+
+    pub fn parse(input: &str) -> Result<Envelope, Error> {
+        Envelope::from_xml(Document::parse(input)?.root())
+    }
+}
+
+impl FromStr for Envelope {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Envelope, Error> {
+        Envelope::parse(input)
+    }
+}
+
+impl FromXml for Envelope {
+    /// Reads an `Envelope` in either SOAP namespace, which must hold a
+    /// `Body` and may hold a `Header` before it.
+    fn from_xml(el: &Element) -> Result<Envelope, Error> {
+        let mut scope = NamespaceScope::new();
+        scope.push(el);
+        let name = scope.element_name(el);
+        let version = name
+            .namespace
+            .and_then(SoapVersion::from_namespace)
+            .filter(|_| name.local == "Envelope")
+            .ok_or_else(|| Error::Mapping(format!("<{}> is not a SOAP envelope", el.name)))?;
+        let mut envelope = Envelope {
+            version,
+            header: Vec::new(),
+            body: Vec::new(),
+            fault: None,
+        };
+        let mut body = None;
+        for child in elements(el) {
+            let name = scope.element_name(child);
+            if name.namespace != Some(version.namespace()) {
+                continue;
+            }
+            let mut scope = scope.clone();
+            scope.push(child);
+            let entries = elements(child).map(|entry| standalone(entry, &scope));
+            match name.local {
+                "Header" if body.is_none() => envelope.header.extend(entries),
+                "Body" if body.is_none() => {
+                    envelope.body.extend(entries);
+                    envelope.fault = elements(child)
+                        .find(|entry| {
+                            let mut scope = scope.clone();
+                            scope.push(entry);
+                            let name = scope.element_name(entry);
+                            name.namespace == Some(version.namespace()) && name.local == "Fault"
+                        })
+                        .map(|fault| read_fault(fault, version));
+                    body = Some(child);
+                }
+                _ => {}
+            }
+        }
+        match body {
+            Some(_) => Ok(envelope),
+            None => Err(Error::Mapping("SOAP envelope has no Body".to_string())),
+        }
+    }
+}
+
+impl ToXml for Envelope {
+    /// Writes the envelope with the `soap` prefix bound to its namespace.
+    fn to_xml(&self) -> Element {
+        let mut envelope = Element::new("soap:Envelope");
+        envelope.set_attribute("xmlns:soap", self.version.namespace());
+        if !self.header.is_empty() {
+            let mut header = Element::new("soap:Header");
+            header.children = self.header.iter().cloned().map(Node::Element).collect();
+            envelope.children.push(Node::Element(header));
+        }
+        let mut body = Element::new("soap:Body");
+        body.children = self.body.iter().cloned().map(Node::Element).collect();
+        envelope.children.push(Node::Element(body));
+        envelope
+    }
+}
+
+fn elements(el: &Element) -> impl Iterator<Item = &Element> {
+    el.children.iter().filter_map(|child| match child {
+        Node::Element(child) => Some(child),
+        _ => None,
+    })
+}
+
+// A copy of `entry` declaring the bindings from `scope` that it uses.
+fn standalone(entry: &Element, scope: &NamespaceScope<'_>) -> Element {
+    let mut used = Vec::new();
+    used_prefixes(entry, &mut used);
+    let mut copy = entry.clone();
+    let own = entry.namespace_declarations();
+    for (prefix, uri) in scope.in_scope() {
+        if !used.contains(&prefix) || own.iter().any(|(declared, _)| *declared == prefix) {
+            continue;
+        }
+        match prefix {
+            "" => copy.set_attribute("xmlns", uri),
+            prefix => copy.set_attribute(format!("xmlns:{}", prefix), uri),
+        }
+    }
+    copy
+}
+
+fn used_prefixes<'e>(el: &'e Element, used: &mut Vec<&'e str>) {
+    let names = core::iter::once(el.name.as_str()).chain(
+        el.attributes
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| *name != "xmlns" && !name.starts_with("xmlns:")),
+    );
+    for (i, name) in names.enumerate() {
+        // Unprefixed attributes are in no namespace.
+        let prefix = match split_name(name) {
+            (Some(prefix), _) => prefix,
+            (None, _) if i == 0 => "",
+            (None, _) => continue,
+        };
+        if !used.contains(&prefix) {
+            used.push(prefix);
+        }
+    }
+    for child in elements(el) {
+        used_prefixes(child, used);
+    }
+}
+
+// Fault children are matched by local name: SOAP 1.1 leaves them
+// unqualified.
+fn read_fault(fault: &Element, version: SoapVersion) -> Fault {
+    let child = |el: &'_ Element, local: &str| -> Option<Element> {
+        elements(el)
+            .find(|child| child.local_name() == local)
+            .cloned()
+    };
+    let text = |el: Option<Element>| el.map(|el| el.text_content().trim().to_string());
+    match version {
+        SoapVersion::Soap11 => Fault {
+            code: text(child(fault, "faultcode")).unwrap_or_default(),
+            reason: text(child(fault, "faultstring")).unwrap_or_default(),
+            detail: child(fault, "detail"),
+        },
+        SoapVersion::Soap12 => Fault {
+            code: text(child(fault, "Code").and_then(|code| child(&code, "Value")))
+                .unwrap_or_default(),
+            reason: text(child(fault, "Reason").and_then(|reason| child(&reason, "Text")))
+                .unwrap_or_default(),
+            detail: child(fault, "Detail"),
+        },
+    }
+}
+
+#[test]
+fn soap_envelopes() {
+    let request = Envelope::wrap(
+        "<m:GetPrice xmlns:m=\"urn:shop\"><m:Item>Apple</m:Item></m:GetPrice>"
+            .parse()
+            .unwrap(),
+    );
+    let written = request.to_xml().to_string();
+    assert_eq!(
+        "<soap:Envelope xmlns:soap=\"http://schemas.xmlsoap.org/soap/envelope/\"><soap:Body><m:GetPrice xmlns:m=\"urn:shop\"><m:Item>Apple</m:Item></m:GetPrice></soap:Body></soap:Envelope>",
+        written
+    );
+    let parsed = Envelope::parse(&written).unwrap();
+    assert_eq!(request, parsed);
+
+    let response = Envelope::parse(
+        r#"<env:Envelope xmlns:env="http://www.w3.org/2003/05/soap-envelope" xmlns:t="urn:tx">
+            <env:Header><t:Transaction>5</t:Transaction></env:Header>
+            <env:Body>
+                <env:Fault>
+                    <env:Code><env:Value>env:Sender</env:Value></env:Code>
+                    <env:Reason><env:Text xml:lang="en">Bad item</env:Text></env:Reason>
+                </env:Fault>
+            </env:Body>
+        </env:Envelope>"#,
+    )
+    .unwrap();
+    assert_eq!(SoapVersion::Soap12, response.version);
+    assert_eq!(Some("urn:tx"), response.header[0].attribute("xmlns:t"));
+    assert_eq!(
+        Some(Fault {
+            code: "env:Sender".to_string(),
+            reason: "Bad item".to_string(),
+            detail: None,
+        }),
+        response.fault
+    );
+    assert!(matches!(
+        Envelope::parse("<Envelope><Body/></Envelope>"),
+        Err(Error::Mapping(_))
+    ));
+}
@@ -0,0 +1,79 @@
+use alloc::collections::BTreeMap;
+
+use crate::prelude::*;
+use crate::{Document, Element, Node};
+
+/// Counts describing the shape of a tree, from `Element::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub elements: usize,
+    /// The most elements nested one inside another: 1 for a lone element.
+    pub max_depth: usize,
+    pub attributes: usize,
+    /// The length in bytes of all text, after references are decoded.
+    pub text_bytes: usize,
+    /// How many elements have each name.
+    pub names: BTreeMap<String, usize>,
+}
+
+impl Element {
+    /// Counts the elements, attributes and text of this element and its
+    /// descendants in one pass.
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        stats.count(self, 0);
+        for (depth, node) in self.iter_nodes() {
+            match node {
+                Node::Element(el) => stats.count(el, depth),
+                Node::Text(text) => stats.text_bytes += text.len(),
+                _ => {}
+            }
+        }
+        stats
+    }
+}
+
+impl Stats {
+    fn count(&mut self, el: &Element, depth: usize) {
+        self.elements += 1;
+        self.max_depth = self.max_depth.max(depth + 1);
+        self.attributes += el.attributes.len();
+        match self.names.get_mut(el.name.as_str()) {
+            Some(n) => *n += 1,
+            None => {
+                self.names.insert(el.name.clone(), 1);
+            }
+        }
+    }
+}
+
+impl Document {
+    /// `Element::stats` for the root. The prolog and epilog hold no
+    /// elements or text.
+    pub fn stats(&self) -> Stats {
+        self.root.stats()
+    }
+}
+
+#[test]
+fn document_stats() {
+    let doc = Document::parse(
+        "<feed version=\"2\"><entry id=\"1\"><title>One</title></entry>\
+         <entry id=\"2\" lang=\"en\"><title>Two &amp; more</title></entry><!-- end --></feed>",
+    )
+    .unwrap();
+    let stats = doc.stats();
+    assert_eq!(5, stats.elements);
+    assert_eq!(3, stats.max_depth);
+    assert_eq!(4, stats.attributes);
+    assert_eq!("One".len() + "Two & more".len(), stats.text_bytes);
+    assert_eq!(
+        vec![("entry", 2), ("feed", 1), ("title", 2)],
+        stats
+            .names
+            .iter()
+            .map(|(name, n)| (name.as_str(), *n))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(1, Element::new("a").stats().max_depth);
+}
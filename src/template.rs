@@ -0,0 +1,176 @@
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::prelude::*;
+use crate::{Element, Error, Node};
+
+/// An element with `{name}` placeholders in its text and attribute values,
+/// to be filled in by `render`. Values become text and attribute values of
+/// the tree, so whatever markup they contain is escaped when the result is
+/// serialized. `{{` and `}}` stand for literal braces.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    root: Element,
+}
+
+impl Template {
+    /// Parses the template, failing on an unclosed placeholder.
+    pub fn parse(input: &str) -> Result<Template, Error> {
+        let root: Element = input.parse()?;
+        check(&root)?;
+        Ok(Template { root })
+    }
+
+    /// The names of the placeholders, in document order with repeats.
+    pub fn placeholders(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        collect(&self.root, &mut names);
+        names
+    }
+
+    #[cfg(feature = "std")]
+    pub fn render(&self, values: &HashMap<&str, &str>) -> Result<Element, Error> {
+        self.render_with(|name| values.get(name).copied())
+    }
+
+    /// Fills every placeholder with what `lookup` returns for its name,
+    /// failing on the first it returns `None` for.
+    pub fn render_with<'v, F>(&self, mut lookup: F) -> Result<Element, Error>
+    where
+        F: FnMut(&str) -> Option<&'v str>,
+    {
+        let mut root = self.root.clone();
+        fill_element(&mut root, &mut lookup)?;
+        Ok(root)
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Template, Error> {
+        Template::parse(input)
+    }
+}
+
+// The literal and placeholder parts of `text`, in order.
+enum Part<'a> {
+    Literal(&'a str),
+    Placeholder(&'a str),
+}
+
+fn parts(text: &str) -> Result<Vec<Part<'_>>, Error> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(at) = rest.find(['{', '}']) {
+        parts.push(Part::Literal(&rest[..at]));
+        let after = &rest[at + 1..];
+        if after.starts_with(&rest[at..at + 1]) {
+            parts.push(Part::Literal(&rest[at..at + 1]));
+            rest = &after[1..];
+        } else if rest[at..].starts_with('}') {
+            return Err(Error::Template(format!("unmatched '}}' in {:?}", text)));
+        } else {
+            let end = after
+                .find('}')
+                .ok_or_else(|| Error::Template(format!("unclosed placeholder in {:?}", text)))?;
+            parts.push(Part::Placeholder(after[..end].trim()));
+            rest = &after[end + 1..];
+        }
+    }
+    parts.push(Part::Literal(rest));
+    Ok(parts)
+}
+
+fn each_text<'e>(
+    el: &'e Element,
+    f: &mut dyn FnMut(&'e str) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for (_, value) in &el.attributes {
+        f(value)?;
+    }
+    for child in &el.children {
+        match child {
+            Node::Element(child) => each_text(child, f)?,
+            Node::Text(text) => f(text)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check(root: &Element) -> Result<(), Error> {
+    each_text(root, &mut |text| parts(text).map(|_| ()))
+}
+
+fn collect<'e>(root: &'e Element, names: &mut Vec<&'e str>) {
+    let _ = each_text(root, &mut |text| {
+        for part in parts(text)? {
+            if let Part::Placeholder(name) = part {
+                names.push(name);
+            }
+        }
+        Ok(())
+    });
+}
+
+fn fill<'v>(
+    text: &mut String,
+    lookup: &mut dyn FnMut(&str) -> Option<&'v str>,
+) -> Result<(), Error> {
+    let mut filled = String::with_capacity(text.len());
+    for part in parts(text)? {
+        match part {
+            Part::Literal(literal) => filled.push_str(literal),
+            Part::Placeholder(name) => filled.push_str(
+                lookup(name).ok_or_else(|| Error::Template(format!("no value for {:?}", name)))?,
+            ),
+        }
+    }
+    *text = filled;
+    Ok(())
+}
+
+fn fill_element<'v>(
+    el: &mut Element,
+    lookup: &mut dyn FnMut(&str) -> Option<&'v str>,
+) -> Result<(), Error> {
+    for (_, value) in &mut el.attributes {
+        fill(value, lookup)?;
+    }
+    for child in &mut el.children {
+        match child {
+            Node::Element(child) => fill_element(child, lookup)?,
+            Node::Text(text) => fill(text, lookup)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn template_rendering() {
+    let template: Template =
+        "<order id=\"{id}\"><item>{ name }</item><note>{{literal}}</note></order>"
+            .parse()
+            .unwrap();
+    assert_eq!(vec!["id", "name"], template.placeholders());
+    let values = [("id", "7\" injected=\"1"), ("name", "</item><evil/>")];
+    let el = template
+        .render_with(|name| values.iter().find(|(n, _)| *n == name).map(|(_, v)| *v))
+        .unwrap();
+    assert_eq!(
+        "<order id=\"7&quot; injected=&quot;1\"><item>&lt;/item&gt;&lt;evil/&gt;</item><note>{literal}</note></order>",
+        el.to_string()
+    );
+    #[cfg(feature = "std")]
+    assert_eq!(
+        Err(Error::Template("no value for \"name\"".to_string())),
+        template.render(&vec![("id", "1")].into_iter().collect())
+    );
+    assert!(matches!(
+        Template::parse("<a>{open</a>"),
+        Err(Error::Template(_))
+    ));
+}
@@ -0,0 +1,128 @@
+//! Helpers for property-testing code built on the crate: random elements
+//! that the parser reads back exactly, and a round-trip assertion. The
+//! generator takes a seed, so any property-testing library can drive it
+//! from the integers it already generates and shrinks, for example
+//! `any::<u64>().prop_map(arbitrary_element)` with proptest.
+
+use crate::prelude::*;
+use crate::{Element, Node, SerializeOptions};
+
+const NAMES: [&str; 6] = ["a", "item", "x:y", "data-set", "_n", "\u{e9}l\u{e9}ment"];
+const WORDS: [&str; 8] = [
+    "text",
+    "a&b",
+    "1 < 2",
+    "x > y",
+    "\"q\"",
+    "it's",
+    "\u{20ac}5",
+    "\u{1f600}",
+];
+
+/// A deterministic source of pseudo-random numbers (xorshift64).
+#[derive(Clone, Debug)]
+pub struct Gen {
+    state: u64,
+}
+
+impl Gen {
+    pub fn new(seed: u64) -> Gen {
+        // Xorshift never leaves zero.
+        Gen {
+            state: seed ^ 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number below `n`, which must not be 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn pick<'p>(&mut self, pool: &[&'p str]) -> &'p str {
+        pool[self.below(pool.len())]
+    }
+}
+
+/// The element generated from `seed`, at most four levels deep.
+pub fn arbitrary_element(seed: u64) -> Element {
+    arbitrary_element_with(&mut Gen::new(seed), 4)
+}
+
+/// A random element at most `depth` levels deep, with attributes, text,
+/// comments and processing instructions, that `Element::from_str` reads
+/// back equal to it: names and attributes are unique where they must be,
+/// text has no surrounding whitespace and is never next to other text.
+pub fn arbitrary_element_with(gen: &mut Gen, depth: usize) -> Element {
+    let mut el = Element::new(gen.pick(&NAMES));
+    let names: Vec<_> = NAMES.iter().filter(|_| gen.below(4) == 0).collect();
+    for name in names {
+        let value = match gen.below(3) {
+            0 => String::new(),
+            1 => gen.pick(&WORDS).to_string(),
+            _ => format!("{}\n\t{}", gen.pick(&WORDS), gen.pick(&WORDS)),
+        };
+        el.attributes.push((name.to_string(), value));
+    }
+    let count = match depth {
+        0 => 0,
+        _ => gen.below(5),
+    };
+    for _ in 0..count {
+        let text_before = matches!(el.children.last(), Some(Node::Text(_)));
+        let child = match gen.below(6) {
+            0 | 1 => Node::Element(arbitrary_element_with(gen, depth - 1)),
+            2 | 3 if !text_before => {
+                let words: Vec<_> = (0..=gen.below(3)).map(|_| gen.pick(&WORDS)).collect();
+                Node::Text(words.join(" "))
+            }
+            4 => Node::Comment(format!(" {} ", gen.pick(&WORDS))),
+            5 => Node::ProcessingInstruction {
+                target: "pi".to_string(),
+                data: gen.pick(&["", "a=1", "x y"]).to_string(),
+            },
+            _ => continue,
+        };
+        el.children.push(child);
+    }
+    el
+}
+
+/// Panics, showing the output, unless `el` reads back equal to itself when
+/// written compactly and when indented.
+pub fn assert_roundtrip(el: &Element) {
+    let indented = SerializeOptions::new().indent("  ");
+    let outputs = [
+        el.to_string(),
+        el.to_string_with(&indented).expect("no size limit"),
+    ];
+    for output in &outputs {
+        match output.parse::<Element>() {
+            Ok(copy) => assert!(
+                copy == *el,
+                "{} reads back differently: {:?}",
+                output,
+                el.diff(&copy)
+            ),
+            Err(error) => panic!("{} does not parse: {}", output, error),
+        }
+    }
+}
+
+#[test]
+fn arbitrary_elements_roundtrip() {
+    assert_eq!(arbitrary_element(7), arbitrary_element(7));
+    let mut deepest = 0;
+    for seed in 0..300 {
+        let el = arbitrary_element(seed);
+        deepest = deepest.max(el.iter_depth_first().map(|(d, _)| d).max().unwrap_or(0));
+        assert_roundtrip(&el);
+    }
+    assert_eq!(4, deepest);
+}
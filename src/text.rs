@@ -0,0 +1,70 @@
+// Marks that render as part of the preceding character: combining
+// diacritics, variation selectors and the zero-width joiner.
+fn is_extender(c: char) -> bool {
+    matches!(c,
+        '\u{300}'..='\u{36F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200D}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}')
+}
+
+// The start of the text is always a boundary, even before a stray mark.
+fn is_boundary(text: &str, index: usize) -> bool {
+    index == 0
+        || text.is_char_boundary(index)
+            && !text[index..].starts_with(is_extender)
+            && !text[..index].ends_with('\u{200D}')
+}
+
+/// Keeps at most `max_chars` characters of `text`, counting a character
+/// together with the combining marks that follow it as one.
+pub fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    let mut count = 0;
+    for (index, _) in text.char_indices() {
+        if is_boundary(text, index) {
+            if count == max_chars {
+                return &text[..index];
+            }
+            count += 1;
+        }
+    }
+    text
+}
+
+/// Splits `text` at byte `index`, moving the split back to the nearest
+/// position that doesn't break a UTF-8 sequence or detach combining marks.
+pub fn split_at_char_boundary(text: &str, index: usize) -> (&str, &str) {
+    let mut index = index.min(text.len());
+    while index > 0 && index < text.len() && !is_boundary(text, index) {
+        index -= 1;
+    }
+    text.split_at(index)
+}
+
+#[test]
+fn truncate_text() {
+    assert_eq!("na\u{EF}", truncate_chars("na\u{EF}ve", 3));
+    assert_eq!("cafe\u{301}", truncate_chars("cafe\u{301} au lait", 4));
+    assert_eq!("short", truncate_chars("short", 10));
+    assert_eq!("", truncate_chars("abc", 0));
+    assert_eq!("", truncate_chars("\u{301}abc", 0));
+    assert_eq!("\u{301}", truncate_chars("\u{301}abc", 1));
+}
+
+#[test]
+fn split_text() {
+    assert_eq!(("a", "\u{E9}b"), split_at_char_boundary("a\u{E9}b", 2));
+    assert_eq!(("", "e\u{301}"), split_at_char_boundary("e\u{301}", 1));
+    assert_eq!(
+        ("\u{1F469}\u{200D}\u{1F4BB}", "!"),
+        split_at_char_boundary("\u{1F469}\u{200D}\u{1F4BB}!", 11)
+    );
+    assert_eq!(
+        ("", "\u{1F469}\u{200D}\u{1F4BB}"),
+        split_at_char_boundary("\u{1F469}\u{200D}\u{1F4BB}", 5)
+    );
+    assert_eq!(("ab", ""), split_at_char_boundary("ab", 99));
+}
@@ -0,0 +1,301 @@
+//! A token layer for callers building their own trees.
+//!
+//! `Tokens` splits input into tags, names, values and text, each with the
+//! byte span it came from, but does not check how tags nest or decode
+//! references. `Token`, `Tokens` and `Span` are also exported from the
+//! crate root. The tree parsers are not built on it yet; only
+//! `is_well_formed` and `extract_attributes` read markup with it. The tokens are also looser than the parsers in two ways: attribute
+//! values may be single-quoted, and whitespace may surround the `=`.
+
+use crate::cst::doctype_end;
+use crate::parser::name_end;
+use crate::{SyntaxError, SyntaxErrorKind, XML_WHITESPACE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// `<`, opening a start tag.
+    LAngle,
+    /// `</`
+    EndTagOpen,
+    /// `<?`
+    PiOpen,
+    /// `?>`
+    PiClose,
+    /// The name of an element, or the target of a processing instruction.
+    TagName(&'a str),
+    /// Everything between a processing instruction's target and its `?>`,
+    /// past the whitespace after the target. There is no token when the
+    /// instruction has no data.
+    PiData(&'a str),
+    AttrName(&'a str),
+    /// `=`
+    Eq,
+    /// An attribute value without its double or single quotes, references
+    /// left undecoded.
+    AttrValue(&'a str),
+    /// `/>`
+    SelfClose,
+    /// `>`
+    RAngle,
+    /// Character data between tags, references left undecoded.
+    Text(&'a str),
+    /// The body of a `<!--...-->` comment.
+    Comment(&'a str),
+    /// The content of a `<![CDATA[...]]>` section.
+    CData(&'a str),
+    /// A `<!DOCTYPE ...>` or other `<!...>` declaration as written between
+    /// `<!` and `>`, including any internal subset.
+    Declaration(&'a str),
+}
+
+/// The byte range of the input a token was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn as_str(self, input: &str) -> &str {
+        &input[self.start..self.end]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Content,
+    // Just after `<` or `</`.
+    TagStart,
+    InTag,
+    // Just after `<?`.
+    PiTarget,
+    // Past a processing instruction's target.
+    PiData,
+}
+
+/// An iterator over the tokens of `input` and their spans. Whitespace inside
+/// tags is skipped; the first error ends the stream. Neither tokens nor
+/// errors allocate.
+#[derive(Clone, Debug)]
+pub struct Tokens<'a> {
+    input: &'a str,
+    rest: &'a str,
+    state: State,
+    failed: bool,
+}
+
+impl<'a> Tokens<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Tokens {
+            input,
+            rest: input,
+            state: State::Content,
+            failed: false,
+        }
+    }
+
+    /// The byte offset just past the last token.
+    pub fn offset(&self) -> usize {
+        self.input.len() - self.rest.len()
+    }
+
+    fn advance(
+        &mut self,
+        len: usize,
+        token: Token<'a>,
+        state: State,
+    ) -> Option<Result<(Token<'a>, Span), SyntaxError>> {
+        let start = self.offset();
+        self.rest = &self.rest[len..];
+        self.state = state;
+        Some(Ok((
+            token,
+            Span {
+                start,
+                end: start + len,
+            },
+        )))
+    }
+
+    fn fail(&mut self, kind: SyntaxErrorKind) -> Option<Result<(Token<'a>, Span), SyntaxError>> {
+        self.failed = true;
+        Some(Err(SyntaxError {
+            offset: self.offset(),
+            kind,
+        }))
+    }
+
+    fn tag_token(&mut self) -> Option<Result<(Token<'a>, Span), SyntaxError>> {
+        let tag_start = self.state == State::TagStart;
+        self.rest = self.rest.trim_start_matches(XML_WHITESPACE);
+        let rest = self.rest;
+        if rest.starts_with("/>") {
+            return self.advance(2, Token::SelfClose, State::Content);
+        }
+        match rest.chars().next() {
+            Some('>') => self.advance(1, Token::RAngle, State::Content),
+            Some('=') => self.advance(1, Token::Eq, State::InTag),
+            Some(quote) if quote == '"' || quote == '\'' => match rest[1..].find(quote) {
+                Some(end) => {
+                    self.advance(end + 2, Token::AttrValue(&rest[1..end + 1]), State::InTag)
+                }
+                None => self.fail(SyntaxErrorKind::UnterminatedValue),
+            },
+            Some(_) => match name_end(rest) {
+                Some(len) => {
+                    let token = match tag_start {
+                        true => Token::TagName(&rest[..len]),
+                        false => Token::AttrName(&rest[..len]),
+                    };
+                    self.advance(len, token, State::InTag)
+                }
+                None => self.fail(SyntaxErrorKind::ExpectedName),
+            },
+            None => self.fail(SyntaxErrorKind::UnexpectedEnd),
+        }
+    }
+
+    // The target, the data and the end of a processing instruction. The
+    // data is one raw token, since it need not look like attributes.
+    fn pi_token(&mut self) -> Option<Result<(Token<'a>, Span), SyntaxError>> {
+        if self.state == State::PiTarget {
+            return match name_end(self.rest) {
+                Some(len) => self.advance(len, Token::TagName(&self.rest[..len]), State::PiData),
+                None => self.fail(SyntaxErrorKind::ExpectedName),
+            };
+        }
+        self.rest = self.rest.trim_start_matches(XML_WHITESPACE);
+        let rest = self.rest;
+        match rest.find("?>") {
+            Some(0) => self.advance(2, Token::PiClose, State::Content),
+            Some(end) => self.advance(end, Token::PiData(&rest[..end]), State::PiData),
+            None => self.fail(SyntaxErrorKind::UnexpectedEnd),
+        }
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<(Token<'a>, Span), SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.state {
+            State::Content => {}
+            State::PiTarget | State::PiData => return self.pi_token(),
+            _ => return self.tag_token(),
+        }
+        let rest = self.rest;
+        if rest.is_empty() {
+            return None;
+        }
+        if let Some(body) = rest.strip_prefix("<!--") {
+            return match body.find("-->") {
+                Some(end) => self.advance(end + 7, Token::Comment(&body[..end]), State::Content),
+                None => self.fail(SyntaxErrorKind::UnexpectedEnd),
+            };
+        }
+        if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            return match body.find("]]>") {
+                Some(end) => self.advance(end + 12, Token::CData(&body[..end]), State::Content),
+                None => self.fail(SyntaxErrorKind::UnexpectedEnd),
+            };
+        }
+        if let Some(body) = rest.strip_prefix("<!") {
+            return match doctype_end(body) {
+                Some(end) => self.advance(
+                    end + 2,
+                    Token::Declaration(&body[..end - 1]),
+                    State::Content,
+                ),
+                None => self.fail(SyntaxErrorKind::UnexpectedEnd),
+            };
+        }
+        if let Some(kind) = rest.strip_prefix('<') {
+            return match kind.chars().next() {
+                Some('/') => self.advance(2, Token::EndTagOpen, State::TagStart),
+                Some('?') => self.advance(2, Token::PiOpen, State::PiTarget),
+                _ => self.advance(1, Token::LAngle, State::TagStart),
+            };
+        }
+        let end = rest.find('<').unwrap_or(rest.len());
+        self.advance(end, Token::Text(&rest[..end]), State::Content)
+    }
+}
+
+#[test]
+fn token_spans() {
+    use crate::prelude::*;
+    let input = "<a x = '1'>hi</a>";
+    let tokens: Result<Vec<_>, _> = Tokens::new(input).collect();
+    let tokens = tokens.unwrap();
+    assert_eq!(
+        vec![
+            Token::LAngle,
+            Token::TagName("a"),
+            Token::AttrName("x"),
+            Token::Eq,
+            Token::AttrValue("1"),
+            Token::RAngle,
+            Token::Text("hi"),
+            Token::EndTagOpen,
+            Token::TagName("a"),
+            Token::RAngle,
+        ],
+        tokens.iter().map(|(token, _)| *token).collect::<Vec<_>>()
+    );
+    let spans: Vec<_> = tokens.iter().map(|(_, span)| span.as_str(input)).collect();
+    assert_eq!(
+        vec!["<", "a", "x", "=", "'1'", ">", "hi", "</", "a", ">"],
+        spans
+    );
+}
+
+#[test]
+fn opaque_tokens() {
+    use crate::prelude::*;
+    let input =
+        "<!DOCTYPE a [<!ENTITY e \"<]>\">]><?php echo \"?\"; ?><a><![CDATA[<x>]]><?pi?></a>";
+    let tokens: Result<Vec<_>, _> = Tokens::new(input)
+        .map(|t| t.map(|(token, _)| token))
+        .collect();
+    assert_eq!(
+        Ok(vec![
+            Token::Declaration("DOCTYPE a [<!ENTITY e \"<]>\">]"),
+            Token::PiOpen,
+            Token::TagName("php"),
+            Token::PiData("echo \"?\"; "),
+            Token::PiClose,
+            Token::LAngle,
+            Token::TagName("a"),
+            Token::RAngle,
+            Token::CData("<x>"),
+            Token::PiOpen,
+            Token::TagName("pi"),
+            Token::PiClose,
+            Token::EndTagOpen,
+            Token::TagName("a"),
+            Token::RAngle,
+        ]),
+        tokens
+    );
+}
+
+#[test]
+fn token_error() {
+    use crate::SyntaxErrorKind;
+    let mut tokens = Tokens::new("<a x=\"1></a>").map(|t| t.map(|(token, _)| token));
+    assert_eq!(Some(Ok(Token::LAngle)), tokens.next());
+    assert_eq!(Some(Ok(Token::TagName("a"))), tokens.next());
+    assert_eq!(Some(Ok(Token::AttrName("x"))), tokens.next());
+    assert_eq!(Some(Ok(Token::Eq)), tokens.next());
+    assert_eq!(
+        Some(Err(SyntaxError {
+            offset: 5,
+            kind: SyntaxErrorKind::UnterminatedValue
+        })),
+        tokens.next()
+    );
+    assert_eq!(None, tokens.next());
+}
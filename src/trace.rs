@@ -0,0 +1,27 @@
+// Thin wrappers over `tracing` that compile to nothing without the feature.
+// Field values are still evaluated so that no binding goes unused.
+
+macro_rules! span {
+    ($name:expr $(, $field:ident = $value:expr)*) => {{
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!($name $(, $field = $value)*).entered();
+        #[cfg(not(feature = "tracing"))]
+        let span = {
+            let _ = ($($value,)*);
+            $crate::trace::NoSpan
+        };
+        span
+    }};
+}
+
+macro_rules! event {
+    ($message:expr $(, $field:ident = $value:expr)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($field = $value,)* $message);
+        #[cfg(not(feature = "tracing"))]
+        let _ = ($($value,)*);
+    }};
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
@@ -0,0 +1,148 @@
+use crate::prelude::*;
+use crate::{Element, Node};
+
+impl Element {
+    /// Deep-clones the element, passing every descendant node through
+    /// `transform` on the way down. Returning `None` drops the node and its
+    /// subtree; a returned element receives the clones of the original's
+    /// children after it, so `transform` only ever sees childless elements.
+    pub fn clone_with<F>(&self, mut transform: F) -> Element
+    where
+        F: FnMut(Node) -> Option<Node>,
+    {
+        self.clone_children_into(self.shallow_clone(), &mut transform)
+    }
+
+    fn shallow_clone(&self) -> Element {
+        Element {
+            name: self.name.clone(),
+            attributes: self.attributes.clone(),
+            children: vec![],
+        }
+    }
+
+    fn clone_children_into<F>(&self, mut copy: Element, transform: &mut F) -> Element
+    where
+        F: FnMut(Node) -> Option<Node>,
+    {
+        for child in &self.children {
+            let shallow = match child {
+                Node::Element(el) => Node::Element(el.shallow_clone()),
+                other => other.clone(),
+            };
+            match (child, transform(shallow)) {
+                (Node::Element(original), Some(Node::Element(el))) => copy
+                    .children
+                    .push(Node::Element(original.clone_children_into(el, transform))),
+                (_, Some(node)) => copy.children.push(node),
+                (_, None) => {}
+            }
+        }
+        copy
+    }
+
+    /// Renames this element and every element below it to what `rename`
+    /// returns for its current name.
+    pub fn map_names<F>(&mut self, mut rename: F) -> &mut Element
+    where
+        F: FnMut(&str) -> String,
+    {
+        self.map_names_with(&mut rename);
+        self
+    }
+
+    fn map_names_with<F: FnMut(&str) -> String>(&mut self, rename: &mut F) {
+        self.name = rename(&self.name);
+        for child in &mut self.children {
+            if let Node::Element(el) = child {
+                el.map_names_with(rename);
+            }
+        }
+    }
+
+    /// Drops every descendant element for which `keep` is false, with its
+    /// subtree. `keep` sees each element before its own children are
+    /// filtered; other nodes are kept.
+    pub fn retain_children<F>(&mut self, mut keep: F) -> &mut Element
+    where
+        F: FnMut(&Element) -> bool,
+    {
+        self.retain_children_with(&mut keep);
+        self
+    }
+
+    fn retain_children_with<F: FnMut(&Element) -> bool>(&mut self, keep: &mut F) {
+        self.children.retain_mut(|child| match child {
+            Node::Element(el) if keep(el) => {
+                el.retain_children_with(keep);
+                true
+            }
+            Node::Element(_) => false,
+            _ => true,
+        });
+    }
+
+    /// Replaces each attribute of this element and every element below it
+    /// with the name and value `rewrite` returns for it, or drops it on
+    /// `None`.
+    pub fn rewrite_attrs<F>(&mut self, mut rewrite: F) -> &mut Element
+    where
+        F: FnMut(&str, &str) -> Option<(String, String)>,
+    {
+        self.rewrite_attrs_with(&mut rewrite);
+        self
+    }
+
+    fn rewrite_attrs_with<F>(&mut self, rewrite: &mut F)
+    where
+        F: FnMut(&str, &str) -> Option<(String, String)>,
+    {
+        self.attributes = self
+            .attributes
+            .iter()
+            .filter_map(|(name, value)| rewrite(name, value))
+            .collect();
+        for child in &mut self.children {
+            if let Node::Element(el) = child {
+                el.rewrite_attrs_with(rewrite);
+            }
+        }
+    }
+}
+
+#[test]
+fn clone_with_transform() {
+    let el: Element = "<a><secret><b/></secret><b>text</b><c/></a>"
+        .parse()
+        .unwrap();
+    let copy = el.clone_with(|node| match node {
+        Node::Element(el) if el.name == "secret" => None,
+        Node::Element(mut el) if el.name == "b" => {
+            el.attributes.push(("seen".to_string(), "yes".to_string()));
+            Some(Node::Element(el))
+        }
+        Node::Text(text) => Some(Node::Text(text.to_uppercase())),
+        other => Some(other),
+    });
+    assert_eq!("<a><b seen=\"yes\">TEXT</b><c/></a>", copy.to_string());
+    assert_eq!(
+        "<a><secret><b/></secret><b>text</b><c/></a>",
+        el.to_string()
+    );
+}
+
+#[test]
+fn in_place_transforms() {
+    use crate::split_name;
+
+    let mut el: Element = "<svg:G><svg:Rect bgcolor=\"red\" svg:x=\"1\"/><Old/>t</svg:G>"
+        .parse()
+        .unwrap();
+    el.map_names(|name| split_name(name).1.to_lowercase())
+        .retain_children(|child| child.name != "old")
+        .rewrite_attrs(|name, value| match name {
+            "bgcolor" => None,
+            _ => Some((split_name(name).1.to_string(), value.to_string())),
+        });
+    assert_eq!("<g><rect x=\"1\"/>t</g>", el.to_string());
+}
@@ -0,0 +1,200 @@
+use crate::prelude::*;
+use crate::{Element, Node};
+
+/// Callbacks for `Element::walk`. `depth` is 0 for the element walked from.
+pub trait Visitor {
+    fn enter(&mut self, _element: &Element, _depth: usize) {}
+
+    fn exit(&mut self, _element: &Element, _depth: usize) {}
+
+    fn text(&mut self, _text: &str, _depth: usize) {}
+
+    fn comment(&mut self, _comment: &str, _depth: usize) {}
+
+    fn processing_instruction(&mut self, _target: &str, _data: &str, _depth: usize) {}
+}
+
+impl Element {
+    /// Visits the tree depth-first using an explicit stack, so arbitrarily
+    /// deep documents don't overflow the call stack.
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        let mut stack = vec![(self, 0)];
+        visitor.enter(self, 0);
+        while let Some(&(el, next)) = stack.last() {
+            let depth = stack.len() - 1;
+            match el.children.get(next) {
+                Some(child) => {
+                    stack[depth].1 += 1;
+                    match child {
+                        Node::Element(child) => {
+                            visitor.enter(child, depth + 1);
+                            stack.push((child, 0));
+                        }
+                        Node::Text(text) => visitor.text(text, depth + 1),
+                        Node::Comment(comment) => visitor.comment(comment, depth + 1),
+                        Node::ProcessingInstruction { target, data } => {
+                            visitor.processing_instruction(target, data, depth + 1)
+                        }
+                    }
+                }
+                None => {
+                    visitor.exit(el, depth);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    /// Iterates over this element and its descendant elements in document
+    /// order, with their depth below this element.
+    pub fn iter_depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst {
+            stack: vec![(0, self)],
+        }
+    }
+
+    /// Iterates over every node below this element, including text,
+    /// comments and processing instructions, in document order. Depth 1 is
+    /// a direct child.
+    pub fn iter_nodes(&self) -> Nodes<'_> {
+        Nodes {
+            stack: self.children.iter().rev().map(|child| (1, child)).collect(),
+        }
+    }
+
+    /// The text of every text node below this element, in document order,
+    /// like the DOM's `textContent`.
+    pub fn text_content(&self) -> String {
+        self.iter_nodes()
+            .filter_map(|(_, node)| match node {
+                Node::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The text of this element's direct text children only.
+    pub fn immediate_text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|child| match child {
+                Node::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The bodies of the comments anywhere below this element.
+    pub fn comments(&self) -> impl Iterator<Item = &str> {
+        self.iter_nodes().filter_map(|(_, node)| match node {
+            Node::Comment(comment) => Some(comment.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The data of the processing instructions called `target` anywhere
+    /// below this element.
+    pub fn processing_instructions<'a>(
+        &'a self,
+        target: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter_nodes().filter_map(move |(_, node)| match node {
+            Node::ProcessingInstruction { target: t, data } if t == target => Some(data.as_str()),
+            _ => None,
+        })
+    }
+}
+
+pub struct DepthFirst<'a> {
+    stack: Vec<(usize, &'a Element)>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = (usize, &'a Element);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, el) = self.stack.pop()?;
+        self.stack
+            .extend(el.children.iter().rev().filter_map(|child| match child {
+                Node::Element(child) => Some((depth + 1, child)),
+                _ => None,
+            }));
+        Some((depth, el))
+    }
+}
+
+pub struct Nodes<'a> {
+    stack: Vec<(usize, &'a Node)>,
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = (usize, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        if let Node::Element(el) = node {
+            self.stack
+                .extend(el.children.iter().rev().map(|child| (depth + 1, child)));
+        }
+        Some((depth, node))
+    }
+}
+
+#[test]
+fn walk_visitor() {
+    struct Trace(Vec<String>);
+    impl Visitor for Trace {
+        fn enter(&mut self, element: &Element, depth: usize) {
+            self.0.push(format!("{}<{}", depth, element.name));
+        }
+        fn exit(&mut self, element: &Element, depth: usize) {
+            self.0.push(format!("{}>{}", depth, element.name));
+        }
+        fn text(&mut self, text: &str, depth: usize) {
+            self.0.push(format!("{}:{}", depth, text));
+        }
+    }
+    let el: Element = "<a><b>hi</b><c/></a>".parse().unwrap();
+    let mut trace = Trace(vec![]);
+    el.walk(&mut trace);
+    assert_eq!(
+        vec!["0<a", "1<b", "2:hi", "1>b", "1<c", "1>c", "0>a"],
+        trace.0
+    );
+}
+
+#[test]
+fn depth_first_iterator() {
+    let el: Element = "<a><b><d/></b>text<c/></a>".parse().unwrap();
+    let names: Vec<_> = el
+        .iter_depth_first()
+        .map(|(depth, el)| (depth, el.name.as_str()))
+        .collect();
+    assert_eq!(vec![(0, "a"), (1, "b"), (2, "d"), (1, "c")], names);
+}
+
+#[test]
+fn node_queries() {
+    let el: Element = "<a><?pi one?><!-- TODO: b --><b><?pi two?><?other?><!--done--></b></a>"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        vec!["one", "two"],
+        el.processing_instructions("pi").collect::<Vec<_>>()
+    );
+    let todo: Vec<_> = el.comments().filter(|c| c.contains("TODO")).collect();
+    assert_eq!(vec![" TODO: b "], todo);
+    let depths: Vec<_> = el.iter_nodes().map(|(depth, _)| depth).collect();
+    assert_eq!(vec![1, 1, 1, 2, 2, 2], depths);
+}
+
+#[test]
+fn text_gathering() {
+    use crate::{element_with, ParseOptions, Parser, Whitespace};
+    let options = ParseOptions::new().whitespace(Whitespace::Preserve);
+    let (_, el) = element_with(options)
+        .parse("<p>Hello <b>big <i>wide</i></b> world<!-- no --></p>")
+        .unwrap();
+    assert_eq!("Hello big wide world", el.text_content());
+    assert_eq!("Hello  world", el.immediate_text());
+}
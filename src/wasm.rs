@@ -0,0 +1,148 @@
+//! JavaScript bindings, for running the parser in a browser.
+//!
+//! `parse` returns the tree as plain objects: an element is
+//! `{type: "element", name, attributes, children}`, with the attributes as
+//! an object in document order, and the other nodes are
+//! `{type: "text", text}`, `{type: "comment", text}` and
+//! `{type: "processingInstruction", target, data}`. `serialize` takes an
+//! element in the same shape and writes it out as XML. Errors are thrown as
+//! JavaScript `Error`s carrying the message of the Rust error.
+
+use js_sys::JSON;
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+
+use crate::prelude::*;
+use crate::{Document, Element, Error, Node};
+
+/// Parses a whole document and returns its root element.
+#[wasm_bindgen]
+pub fn parse(xml: &str) -> Result<JsValue, JsError> {
+    let document = Document::parse(xml).map_err(|error| JsError::new(&error.to_string()))?;
+    let tree = element_value(document.root()).to_string();
+    JSON::parse(&tree).map_err(|_| JsError::new("the tree could not be passed to JavaScript"))
+}
+
+/// Writes an element given in the shape `parse` returns as XML.
+#[wasm_bindgen]
+pub fn serialize(element: JsValue) -> Result<String, JsError> {
+    let json = JSON::stringify(&element)
+        .ok()
+        .and_then(|json| json.as_string())
+        .ok_or_else(|| JsError::new("the element could not be read from JavaScript"))?;
+    let value: Value =
+        serde_json::from_str(&json).map_err(|error| JsError::new(&error.to_string()))?;
+    let element = value_element(&value).map_err(|error| JsError::new(&error.to_string()))?;
+    Ok(element.to_string())
+}
+
+fn element_value(el: &Element) -> Value {
+    let attributes = el
+        .attributes
+        .iter()
+        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+        .collect();
+    let children = el.children.iter().map(node_value).collect();
+    object(&[
+        ("type", Value::from("element")),
+        ("name", Value::from(el.name.as_str())),
+        ("attributes", Value::Object(attributes)),
+        ("children", Value::Array(children)),
+    ])
+}
+
+fn node_value(node: &Node) -> Value {
+    match node {
+        Node::Element(el) => element_value(el),
+        Node::Text(text) => object(&[("type", "text".into()), ("text", text.as_str().into())]),
+        Node::Comment(text) => {
+            object(&[("type", "comment".into()), ("text", text.as_str().into())])
+        }
+        Node::ProcessingInstruction { target, data } => object(&[
+            ("type", "processingInstruction".into()),
+            ("target", target.as_str().into()),
+            ("data", data.as_str().into()),
+        ]),
+    }
+}
+
+fn object(entries: &[(&str, Value)]) -> Value {
+    let entries = entries
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()));
+    Value::Object(entries.collect::<Map<_, _>>())
+}
+
+fn value_element(value: &Value) -> Result<Element, Error> {
+    match value_node(value)? {
+        Node::Element(el) => Ok(el),
+        _ => Err(Error::Json("expected an element".to_string())),
+    }
+}
+
+// Missing attributes and children are taken to be empty.
+fn value_node(value: &Value) -> Result<Node, Error> {
+    let field = |name: &str| match value.get(name) {
+        Some(Value::String(text)) => Ok(text.clone()),
+        _ => Err(Error::Json(format!("{:?} must be a string", name))),
+    };
+    match field("type")?.as_str() {
+        "element" => {
+            let mut el = Element::new(field("name")?);
+            match value.get("attributes") {
+                Some(Value::Object(attributes)) => {
+                    for (name, value) in attributes {
+                        let value = value.as_str().ok_or_else(|| {
+                            Error::Json(format!("attribute {:?} must be a string", name))
+                        })?;
+                        el.attributes.push((name.clone(), value.to_string()));
+                    }
+                }
+                None | Some(Value::Null) => {}
+                Some(_) => return Err(Error::Json("\"attributes\" must be an object".to_string())),
+            }
+            match value.get("children") {
+                Some(Value::Array(children)) => {
+                    for child in children {
+                        el.children.push(value_node(child)?);
+                    }
+                }
+                None | Some(Value::Null) => {}
+                Some(_) => return Err(Error::Json("\"children\" must be an array".to_string())),
+            }
+            Ok(Node::Element(el))
+        }
+        "text" => Ok(Node::Text(field("text")?)),
+        "comment" => Ok(Node::Comment(field("text")?)),
+        "processingInstruction" => Ok(Node::ProcessingInstruction {
+            target: field("target")?,
+            data: field("data")?,
+        }),
+        other => Err(Error::Json(format!("unknown node type {:?}", other))),
+    }
+}
+
+#[test]
+fn wasm_tree() {
+    let el: Element = "<a z=\"1\" x=\"2\">t<!--c--><?p d?><b/></a>"
+        .parse()
+        .unwrap();
+    let value = element_value(&el);
+    assert_eq!(
+        concat!(
+            r#"{"type":"element","name":"a","attributes":{"z":"1","x":"2"},"children":["#,
+            r#"{"type":"text","text":"t"},{"type":"comment","text":"c"},"#,
+            r#"{"type":"processingInstruction","target":"p","data":"d"},"#,
+            r#"{"type":"element","name":"b","attributes":{},"children":[]}]}"#
+        ),
+        value.to_string()
+    );
+    assert_eq!(Ok(el), value_element(&value));
+
+    let bare = serde_json::json!({"type": "element", "name": "c"});
+    assert_eq!(Ok(Element::new("c")), value_element(&bare));
+    let text = serde_json::json!({"type": "text", "text": "t"});
+    assert!(value_element(&text).is_err());
+    let unknown = serde_json::json!({"type": "cdata", "text": "t"});
+    assert!(value_element(&unknown).is_err());
+}
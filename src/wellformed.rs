@@ -0,0 +1,199 @@
+use crate::dtd::{entity_reference, Expansion};
+use crate::prelude::*;
+use crate::tokenizer::{Span, Token, Tokens};
+use crate::{
+    comment, document_start_at, misc, processing_instruction, prolog_in, reference,
+    whitespace_wrap, zero_or_more, Context, ParseOptions, Parser, SyntaxError, SyntaxErrorKind,
+};
+
+/// Checks that `Document::parse` would accept `input`, without building the
+/// tree: tags are read with `Tokens` and matched against a stack of the
+/// names still open, borrowed from the input. The offset of an error is
+/// where the check stopped, which is not always where `Document::parse`
+/// would report it.
+pub fn is_well_formed(input: &str) -> Result<(), SyntaxError> {
+    let context = Context::new(ParseOptions::default());
+    let fail = |rest: &str, kind| SyntaxError {
+        offset: input.len() - rest.len(),
+        kind,
+    };
+    let start = document_start_at(&context, input)
+        .map_err(|rest| fail(rest, SyntaxErrorKind::Unexpected))?;
+    let (rest, _) = prolog_in(context.clone())
+        .parse(start)
+        .map_err(|rest| fail(rest, SyntaxErrorKind::Unexpected))?;
+    let rest = root(&context, rest.trim_start()).map_err(|(rest, kind)| fail(rest, kind))?;
+    let (rest, _) = zero_or_more(whitespace_wrap(misc()))
+        .parse(rest.trim_start())
+        .map_err(|rest| fail(rest, SyntaxErrorKind::Unexpected))?;
+    match rest.is_empty() {
+        true => Ok(()),
+        false => Err(fail(rest, SyntaxErrorKind::Unexpected)),
+    }
+}
+
+// Where a check failed, as the input left there.
+type Failure<'a> = (&'a str, SyntaxErrorKind);
+
+// The input past the root element, read the way `element_body` reads
+// children.
+fn root<'a>(context: &Context, input: &'a str) -> Result<&'a str, Failure<'a>> {
+    let mut open = Vec::new();
+    // Entities are expanded only to count them against the limits.
+    let mut scratch = String::new();
+    let mut rest = start_tag(context, input, &mut open, &mut scratch)?;
+    while let Some(name) = open.last() {
+        rest = if let Some(tag) = rest.strip_prefix("</") {
+            let after = tag.strip_prefix(name).and_then(|tag| tag.strip_prefix('>'));
+            open.pop();
+            after.ok_or((rest, SyntaxErrorKind::MismatchedTag))?
+        } else if rest.starts_with("<!--") {
+            let (after, _) = comment()
+                .parse(rest)
+                .map_err(|_| (rest, SyntaxErrorKind::Unexpected))?;
+            after
+        } else if rest.starts_with("<?") {
+            let (after, _) = processing_instruction()
+                .parse(rest)
+                .map_err(|_| (rest, SyntaxErrorKind::Unexpected))?;
+            after
+        } else if rest.starts_with('<') {
+            start_tag(context, rest, &mut open, &mut scratch)?
+        } else if rest.is_empty() {
+            return Err((rest, SyntaxErrorKind::UnexpectedEnd));
+        } else {
+            text(context, rest, &mut scratch)?
+        };
+    }
+    Ok(rest)
+}
+
+// Reads the start tag at `input`, pushing its name unless it closes itself.
+// The tokenizer skips whitespace inside tags that the tree parser does not
+// allow, so tokens other than attribute names must follow the last one
+// directly.
+fn start_tag<'a>(
+    context: &Context,
+    input: &'a str,
+    open: &mut Vec<&'a str>,
+    scratch: &mut String,
+) -> Result<&'a str, Failure<'a>> {
+    let mut tokens = Tokens::new(input);
+    let mut next = |after: usize| match tokens.next() {
+        Some(Ok(token)) => Ok(token),
+        Some(Err(error)) => Err((&input[error.offset..], error.kind)),
+        None => Err((&input[after..], SyntaxErrorKind::UnexpectedEnd)),
+    };
+    let adjacent = |(token, span): (Token<'a>, Span), end: usize| match span.start == end {
+        true => Ok((token, span)),
+        false => Err((&input[end..], SyntaxErrorKind::Unexpected)),
+    };
+    let (name, mut end) = match adjacent(next(0)?, 0)? {
+        (Token::LAngle, _) => match adjacent(next(1)?, 1)? {
+            (Token::TagName(name), span) => (name, span.end),
+            _ => return Err((&input[1..], SyntaxErrorKind::ExpectedName)),
+        },
+        _ => return Err((input, SyntaxErrorKind::Unexpected)),
+    };
+    loop {
+        let (token, span) = next(end)?;
+        match token {
+            Token::RAngle | Token::SelfClose if span.start == end => {
+                if token == Token::RAngle {
+                    open.push(name);
+                }
+                return Ok(&input[span.end..]);
+            }
+            Token::AttrName(_) if span.start > end => {
+                let (eq, eq_span) = adjacent(next(span.end)?, span.end)?;
+                let (value, value_span) = adjacent(next(eq_span.end)?, eq_span.end)?;
+                let quoted = input[value_span.start..].starts_with('"');
+                match (eq, value) {
+                    (Token::Eq, Token::AttrValue(value)) if quoted => {
+                        // The value ends before the closing quote, not the
+                        // input, so the failure is moved back onto `input`.
+                        attribute_value(context, value, scratch).map_err(|(rest, kind)| {
+                            (&input[value_span.end - 1 - rest.len()..], kind)
+                        })?;
+                        end = value_span.end;
+                    }
+                    _ => return Err((&input[eq_span.start..], SyntaxErrorKind::Unexpected)),
+                }
+            }
+            _ => return Err((&input[end..], SyntaxErrorKind::Unexpected)),
+        }
+    }
+}
+
+// `<` is allowed in attribute values, so references are checked run by run.
+fn attribute_value<'a>(
+    context: &Context,
+    value: &'a str,
+    scratch: &mut String,
+) -> Result<(), Failure<'a>> {
+    let mut rest = text(context, value, scratch)?;
+    while let Some(after) = rest.strip_prefix('<') {
+        rest = text(context, after, scratch)?;
+    }
+    Ok(())
+}
+
+// The input past character data, with references checked as `text_at`
+// decodes them.
+fn text<'a>(
+    context: &Context,
+    input: &'a str,
+    scratch: &mut String,
+) -> Result<&'a str, Failure<'a>> {
+    let mut rest = input;
+    loop {
+        rest = &rest[rest.find(['<', '&']).unwrap_or(rest.len())..];
+        if !rest.starts_with('&') {
+            return Ok(rest);
+        }
+        if let Some((next, name)) = entity_reference(rest).filter(|_| reference(rest).is_err()) {
+            scratch.clear();
+            match context.expand_entity(name, scratch) {
+                Ok(()) => {
+                    rest = next;
+                    continue;
+                }
+                Err(Expansion::OverLimit) => return Err((rest, SyntaxErrorKind::InvalidReference)),
+                Err(Expansion::Undefined) => {}
+            }
+        }
+        match reference(rest) {
+            Ok((next, Some(_))) => rest = next,
+            _ => return Err((rest, SyntaxErrorKind::InvalidReference)),
+        }
+    }
+}
+
+#[test]
+fn well_formedness() {
+    use crate::Document;
+
+    let good = [
+        "<?xml version=\"1.0\"?>\n<!-- c --><a x=\"1\" y=\"&lt;\">t&amp;<b/><?pi d?></a>\n",
+        "<!DOCTYPE a [<!ENTITY e \"v\">]><a>&e;</a>",
+        " <a/> <!-- after --> ",
+        "\u{FEFF}<?xml version=\"1.0\"?><a/>",
+    ];
+    for input in good {
+        assert_eq!(Ok(()), is_well_formed(input), "{}", input);
+    }
+    let bad = [
+        ("<a><b></a>", 6, SyntaxErrorKind::MismatchedTag),
+        ("<a >x</a>", 2, SyntaxErrorKind::Unexpected),
+        ("<a x='1'/>", 4, SyntaxErrorKind::Unexpected),
+        ("<a>&nope;</a>", 3, SyntaxErrorKind::InvalidReference),
+        ("<a x=\"<&nope;\"/>", 7, SyntaxErrorKind::InvalidReference),
+        ("<a/><b/>", 4, SyntaxErrorKind::Unexpected),
+        ("<a><b>", 6, SyntaxErrorKind::UnexpectedEnd),
+        ("\u{FEFF}x<a/>", 3, SyntaxErrorKind::Unexpected),
+    ];
+    for (input, offset, kind) in bad {
+        assert_eq!(Err(SyntaxError { offset, kind }), is_well_formed(input));
+        assert!(Document::parse(input).is_err());
+    }
+}
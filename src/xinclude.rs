@@ -0,0 +1,210 @@
+use crate::ids::id_index;
+use crate::prelude::*;
+use crate::{split_name, Document, Element, Error, Node};
+
+pub const XINCLUDE_NAMESPACE: &str = "http://www.w3.org/2001/XInclude";
+
+impl Element {
+    /// Replaces every `xi:include` in the tree (any prefix bound to
+    /// `XINCLUDE_NAMESPACE`) with the content `loader` returns for its
+    /// `href`: the root of the parsed document, or with `parse="text"` the
+    /// text itself. Included documents are resolved in turn, with `href`s
+    /// passed to `loader` as written. A failed load, or a loaded document
+    /// that does not parse, is replaced by the children of the include's
+    /// `xi:fallback` if it has one; an include that leads back to itself
+    /// fails with `Error::Include`. On an error the include that failed is
+    /// left in the tree as it was.
+    pub fn resolve_includes<F>(&mut self, mut loader: F) -> Result<(), Error>
+    where
+        F: FnMut(&str) -> Result<String, Error>,
+    {
+        let mut resolver = Resolver {
+            loader: &mut loader,
+            bindings: Vec::new(),
+            including: Vec::new(),
+        };
+        resolver.element(self)
+    }
+}
+
+impl Document {
+    /// `Element::resolve_includes` on the root, keeping `get_element_by_id`
+    /// fast for the elements brought in.
+    pub fn resolve_includes<F>(&mut self, loader: F) -> Result<(), Error>
+    where
+        F: FnMut(&str) -> Result<String, Error>,
+    {
+        self.root.resolve_includes(loader)?;
+        self.ids = id_index(&self.root);
+        Ok(())
+    }
+}
+
+struct Resolver<'l> {
+    loader: &'l mut dyn FnMut(&str) -> Result<String, Error>,
+    // The namespace declarations of the elements being walked, innermost last.
+    bindings: Vec<(String, String)>,
+    // The `href`s of the includes being resolved, to find cycles.
+    including: Vec<String>,
+}
+
+impl<'l> Resolver<'l> {
+    fn element(&mut self, el: &mut Element) -> Result<(), Error> {
+        let depth = self.bindings.len();
+        self.declare(el);
+        let mut i = 0;
+        while i < el.children.len() {
+            let spliced = match &mut el.children[i] {
+                Node::Element(child) if self.is_xinclude(child, "include") => {
+                    self.include(child)?
+                }
+                Node::Element(child) => {
+                    self.element(child)?;
+                    i += 1;
+                    continue;
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let len = spliced.len();
+            el.children.splice(i..i + 1, spliced);
+            i += len;
+        }
+        self.bindings.truncate(depth);
+        Ok(())
+    }
+
+    fn include(&mut self, include: &Element) -> Result<Vec<Node>, Error> {
+        let depth = self.bindings.len();
+        self.declare(include);
+        let result = self.load(include);
+        self.bindings.truncate(depth);
+        result
+    }
+
+    fn load(&mut self, include: &Element) -> Result<Vec<Node>, Error> {
+        let href = include
+            .attribute("href")
+            .ok_or_else(|| Error::Include("xi:include without href".to_string()))?
+            .to_string();
+        if include.attribute("xpointer").is_some() {
+            return Err(Error::Include("xpointer is not supported".to_string()));
+        }
+        let text = match include.attribute("parse").unwrap_or("xml") {
+            "text" => true,
+            "xml" => false,
+            other => return Err(Error::Include(format!("unknown parse value {:?}", other))),
+        };
+        if self.including.contains(&href) {
+            return Err(Error::Include(format!("{:?} includes itself", href)));
+        }
+        let content = match (self.loader)(&href) {
+            Ok(content) => content,
+            Err(error) => return self.fallback(include).unwrap_or(Err(error)),
+        };
+        if text {
+            return Ok(vec![Node::Text(content)]);
+        }
+        let mut root = match Document::parse(&content) {
+            Ok(document) => document.root,
+            Err(error) => return self.fallback(include).unwrap_or(Err(error)),
+        };
+        self.including.push(href);
+        // Bindings in scope at the include do not reach into the included
+        // document.
+        let outer = core::mem::take(&mut self.bindings);
+        let result = self.element(&mut root);
+        self.bindings = outer;
+        self.including.pop();
+        result.map(|_| vec![Node::Element(root)])
+    }
+
+    // The resolved children of the include's `xi:fallback`, if it has one.
+    fn fallback(&mut self, include: &Element) -> Option<Result<Vec<Node>, Error>> {
+        let mut fallback = include
+            .children
+            .iter()
+            .find_map(|child| match child {
+                Node::Element(el) if self.is_xinclude(el, "fallback") => Some(el),
+                _ => None,
+            })?
+            .clone();
+        Some(self.element(&mut fallback).map(|_| fallback.children))
+    }
+
+    fn declare(&mut self, el: &Element) {
+        self.bindings.extend(
+            el.namespace_declarations()
+                .into_iter()
+                .map(|(prefix, uri)| (prefix.to_string(), uri.to_string())),
+        );
+    }
+
+    fn is_xinclude(&self, el: &Element, local: &str) -> bool {
+        let (prefix, name) = split_name(&el.name);
+        let prefix = prefix.unwrap_or("");
+        let own = el.namespace_declarations();
+        let uri = own
+            .iter()
+            .rev()
+            .find(|(bound, _)| *bound == prefix)
+            .map(|(_, uri)| *uri)
+            .or_else(|| {
+                self.bindings
+                    .iter()
+                    .rev()
+                    .find(|(bound, _)| bound == prefix)
+                    .map(|(_, uri)| uri.as_str())
+            });
+        name == local && uri == Some(XINCLUDE_NAMESPACE)
+    }
+}
+
+#[test]
+fn xinclude_resolution() {
+    const XI: &str = "xmlns:xi=\"http://www.w3.org/2001/XInclude\"";
+    let loader = |href: &str| match href {
+        "chapter.xml" => Ok(format!(
+            "<chapter><xi:include {} href=\"note.txt\" parse=\"text\"/></chapter>",
+            XI
+        )),
+        "note.txt" => Ok("a < b".to_string()),
+        "loop.xml" => Ok(format!("<x {}><xi:include href=\"loop.xml\"/></x>", XI)),
+        "broken.xml" => Ok("<unclosed>".to_string()),
+        _ => Err(Error::Include(format!("no such file {:?}", href))),
+    };
+    let mut doc = Document::parse(&format!(
+        "<book {}><xi:include href=\"chapter.xml\"/><xi:include href=\"missing.xml\">\
+         <xi:fallback><p>gone</p></xi:fallback></xi:include><xi:include href=\"broken.xml\">\
+         <xi:fallback><p>bad</p></xi:fallback></xi:include></book>",
+        XI
+    ))
+    .unwrap();
+    doc.resolve_includes(loader).unwrap();
+    assert_eq!(
+        format!(
+            "<book {}><chapter>a &lt; b</chapter><p>gone</p><p>bad</p></book>",
+            XI
+        ),
+        doc.root().to_string()
+    );
+
+    let mut looped: Element = format!("<r {}><xi:include href=\"loop.xml\"/></r>", XI)
+        .parse()
+        .unwrap();
+    let original = looped.clone();
+    assert!(matches!(
+        looped.resolve_includes(loader),
+        Err(Error::Include(_))
+    ));
+    assert_eq!(original, looped);
+    let mut broken: Element = format!("<r {}><xi:include href=\"broken.xml\"/></r>", XI)
+        .parse()
+        .unwrap();
+    assert!(matches!(
+        broken.resolve_includes(loader),
+        Err(Error::Syntax { .. })
+    ));
+}
@@ -0,0 +1,913 @@
+use alloc::collections::BTreeMap;
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use crate::diff::child_paths;
+use crate::prelude::*;
+use crate::{split_name, Document, Element, Error, Node, ValidationError, ValidationErrorKind};
+
+pub const XSD_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// A schema loaded from a subset of XML Schema 1.0: global and local
+/// `element` declarations (with `ref`, `type`, `minOccurs` and
+/// `maxOccurs`), named and anonymous `complexType`s built from `sequence`,
+/// `choice`, `attribute`, `anyAttribute` and `simpleContent` extensions, and
+/// `simpleType` restrictions of the common built-in types with enumeration,
+/// length and range facets. Anything else is rejected when the schema is
+/// loaded rather than silently ignored. Elements are matched by their local
+/// name, without regard to their namespace or the `targetNamespace`.
+#[derive(Clone, Debug)]
+pub struct XsdSchema {
+    elements: BTreeMap<String, ElementDecl>,
+    complex_types: BTreeMap<String, ComplexType>,
+    simple_types: BTreeMap<String, SimpleType>,
+}
+
+#[derive(Clone, Debug)]
+struct ElementDecl {
+    name: String,
+    ty: TypeRef,
+}
+
+#[derive(Clone, Debug)]
+enum TypeRef {
+    Any,
+    Named(String),
+    Complex(Box<ComplexType>),
+    Simple(SimpleType),
+}
+
+enum Resolved<'a> {
+    Any,
+    Complex(&'a ComplexType),
+    Simple(&'a SimpleType),
+}
+
+#[derive(Clone, Debug, Default)]
+struct ComplexType {
+    attributes: Vec<AttributeDecl>,
+    any_attributes: bool,
+    content: Option<Particle>,
+    mixed: bool,
+    // Set for `simpleContent`, whose text must be of this type.
+    text: Option<SimpleType>,
+}
+
+#[derive(Clone, Debug)]
+struct AttributeDecl {
+    name: String,
+    ty: SimpleType,
+    required: bool,
+}
+
+#[derive(Clone, Debug)]
+struct Particle {
+    term: Term,
+    min: usize,
+    max: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+enum Term {
+    Element(ElementDecl),
+    Ref(String),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+}
+
+#[derive(Clone, Debug)]
+enum SimpleType {
+    Builtin(Builtin),
+    Named(String),
+    Restriction {
+        base: Box<SimpleType>,
+        facets: Vec<Facet>,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Builtin {
+    String,
+    Boolean,
+    Integer,
+    NonNegativeInteger,
+    PositiveInteger,
+    Decimal,
+    Double,
+    Date,
+}
+
+#[derive(Clone, Debug)]
+enum Facet {
+    Enumeration(Vec<String>),
+    Length(usize),
+    MinLength(usize),
+    MaxLength(usize),
+    MinInclusive(f64),
+    MaxInclusive(f64),
+    MinExclusive(f64),
+    MaxExclusive(f64),
+}
+
+impl XsdSchema {
+    pub fn parse(input: &str) -> Result<XsdSchema, Error> {
+        XsdSchema::from_document(&Document::parse(input)?)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<XsdSchema, Error> {
+        XsdSchema::from_document(&Document::from_file(path)?)
+    }
+
+    pub fn from_document(document: &Document) -> Result<XsdSchema, Error> {
+        let root = document.root();
+        let xs = root
+            .namespace_declarations()
+            .into_iter()
+            .find(|(_, uri)| *uri == XSD_NAMESPACE)
+            .map(|(prefix, _)| prefix.to_string())
+            .ok_or_else(|| schema_error("the root does not bind the XML Schema namespace"))?;
+        let loader = Loader { xs };
+        if local(root) != "schema" {
+            return Err(schema_error("the root is not `schema`"));
+        }
+        let mut schema = XsdSchema {
+            elements: BTreeMap::new(),
+            complex_types: BTreeMap::new(),
+            simple_types: BTreeMap::new(),
+        };
+        for el in children(root) {
+            match local(el) {
+                "element" => {
+                    let decl = loader.element(el)?;
+                    schema.elements.insert(decl.name.clone(), decl);
+                }
+                "complexType" => {
+                    let name = required(el, "name")?.to_string();
+                    schema.complex_types.insert(name, loader.complex_type(el)?);
+                }
+                "simpleType" => {
+                    let name = required(el, "name")?.to_string();
+                    schema.simple_types.insert(name, loader.simple_type(el)?);
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        schema.check()?;
+        Ok(schema)
+    }
+
+    pub fn validate(&self, document: &Document) -> Vec<ValidationError> {
+        self.validate_element(document.root())
+    }
+
+    /// Checks `root` against the global element declaration of its name,
+    /// reporting every broken rule in document order.
+    pub fn validate_element(&self, root: &Element) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let path = format!("/{}", root.name);
+        let decl = match self.elements.get(split_name(&root.name).1) {
+            Some(decl) => decl,
+            None => {
+                errors.push(ValidationError {
+                    path,
+                    kind: ValidationErrorKind::UnexpectedRoot(root.name.clone()),
+                });
+                return errors;
+            }
+        };
+        let mut stack = vec![(root, &decl.ty, path)];
+        while let Some((el, ty, path)) = stack.pop() {
+            let mut error = |kind| {
+                errors.push(ValidationError {
+                    path: path.clone(),
+                    kind,
+                })
+            };
+            let ty = match self.resolve(ty) {
+                Resolved::Any => continue,
+                Resolved::Complex(ty) => ty,
+                Resolved::Simple(ty) => {
+                    self.simple_element(el, ty, &mut error);
+                    continue;
+                }
+            };
+            self.attributes(el, ty, &mut error);
+            let decls = match (&ty.text, &ty.content) {
+                (Some(text), _) => {
+                    self.simple_element(el, text, &mut error);
+                    continue;
+                }
+                (None, Some(content)) => self.content(el, content, &mut error),
+                (None, None) => {
+                    unexpected_children(el, &[], &mut error);
+                    Vec::new()
+                }
+            };
+            if !ty.mixed && !own_text(el).trim().is_empty() {
+                error(ValidationErrorKind::UnexpectedText);
+            }
+            let paths = child_paths(&path, &el.children);
+            let nested = el.children.iter().zip(paths).rev();
+            stack.extend(nested.filter_map(|(child, path)| {
+                match child {
+                    Node::Element(child) => decls
+                        .iter()
+                        .find(|decl| decl.name == local(child))
+                        .map(|decl| (child, &decl.ty, path)),
+                    _ => None,
+                }
+            }));
+        }
+        errors
+    }
+
+    fn resolve<'a>(&'a self, ty: &'a TypeRef) -> Resolved<'a> {
+        match ty {
+            TypeRef::Any => Resolved::Any,
+            TypeRef::Named(name) => match self.complex_types.get(name) {
+                Some(ty) => Resolved::Complex(ty),
+                None => self
+                    .simple_types
+                    .get(name)
+                    .map_or(Resolved::Any, Resolved::Simple),
+            },
+            TypeRef::Complex(ty) => Resolved::Complex(ty),
+            TypeRef::Simple(ty) => Resolved::Simple(ty),
+        }
+    }
+
+    fn simple_element(
+        &self,
+        el: &Element,
+        ty: &SimpleType,
+        error: &mut impl FnMut(ValidationErrorKind),
+    ) {
+        unexpected_children(el, &[], error);
+        let text = own_text(el);
+        if !self.accepts(ty, &text) {
+            error(ValidationErrorKind::InvalidText(text));
+        }
+    }
+
+    fn attributes(
+        &self,
+        el: &Element,
+        ty: &ComplexType,
+        error: &mut impl FnMut(ValidationErrorKind),
+    ) {
+        for decl in &ty.attributes {
+            match el.attribute(&decl.name) {
+                Some(value) if !self.accepts(&decl.ty, value) => {
+                    error(ValidationErrorKind::InvalidAttributeValue {
+                        name: decl.name.clone(),
+                        value: value.to_string(),
+                    })
+                }
+                None if decl.required => {
+                    error(ValidationErrorKind::MissingAttribute(decl.name.clone()))
+                }
+                _ => {}
+            }
+        }
+        if ty.any_attributes {
+            return;
+        }
+        for (name, _) in &el.attributes {
+            let declared = ty.attributes.iter().any(|decl| decl.name == *name);
+            if !declared && name != "xmlns" && split_name(name).0 != Some("xmlns") {
+                error(ValidationErrorKind::UnexpectedAttribute(name.clone()));
+            }
+        }
+    }
+
+    // Checks the child elements against the content model, returning the
+    // declarations they are validated against.
+    fn content<'a>(
+        &'a self,
+        el: &Element,
+        content: &'a Particle,
+        error: &mut impl FnMut(ValidationErrorKind),
+    ) -> Vec<&'a ElementDecl> {
+        let mut decls = Vec::new();
+        self.declarations(content, &mut decls);
+        let known: Vec<&str> = decls.iter().map(|decl| decl.name.as_str()).collect();
+        if unexpected_children(el, &known, error) {
+            return decls;
+        }
+        let names: Vec<&str> = el
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Node::Element(child) => Some(local(child)),
+                _ => None,
+            })
+            .collect();
+        if !self
+            .particle(content, &names, vec![0])
+            .contains(&names.len())
+        {
+            error(ValidationErrorKind::InvalidContent);
+        }
+        decls
+    }
+
+    fn declarations<'a>(&'a self, particle: &'a Particle, decls: &mut Vec<&'a ElementDecl>) {
+        match &particle.term {
+            Term::Element(decl) => decls.push(decl),
+            Term::Ref(name) => decls.extend(self.elements.get(name)),
+            Term::Sequence(particles) | Term::Choice(particles) => {
+                for particle in particles {
+                    self.declarations(particle, decls);
+                }
+            }
+        }
+    }
+
+    // The positions in `names` that matching `particle` from any of `start`
+    // can end at.
+    fn particle(&self, particle: &Particle, names: &[&str], start: Vec<usize>) -> Vec<usize> {
+        let mut reached = Vec::new();
+        let mut current = start;
+        let mut count = 0;
+        loop {
+            if count >= particle.min {
+                merge(&mut reached, &current);
+            }
+            if current.is_empty() || particle.max.is_some_and(|max| count >= max) {
+                return reached;
+            }
+            let mut next = Vec::new();
+            for &position in &current {
+                merge(&mut next, &self.term(&particle.term, names, position));
+            }
+            count += 1;
+            // Going on from a position already reached past `min` can't
+            // reach anything new, which also stops empty matches looping.
+            if count > particle.min {
+                next.retain(|position| !reached.contains(position));
+            }
+            current = next;
+        }
+    }
+
+    fn term(&self, term: &Term, names: &[&str], start: usize) -> Vec<usize> {
+        match term {
+            Term::Element(ElementDecl { name, .. }) | Term::Ref(name) => match names.get(start) {
+                Some(found) if found == name => vec![start + 1],
+                _ => Vec::new(),
+            },
+            Term::Sequence(particles) => particles.iter().fold(vec![start], |positions, p| {
+                self.particle(p, names, positions)
+            }),
+            Term::Choice(particles) => {
+                let mut reached = Vec::new();
+                for particle in particles {
+                    merge(&mut reached, &self.particle(particle, names, vec![start]));
+                }
+                reached
+            }
+        }
+    }
+
+    fn accepts(&self, ty: &SimpleType, value: &str) -> bool {
+        match ty {
+            SimpleType::Builtin(builtin) => builtin.accepts(value),
+            SimpleType::Named(name) => self
+                .simple_types
+                .get(name)
+                .is_some_and(|ty| self.accepts(ty, value)),
+            SimpleType::Restriction { base, facets } => {
+                self.accepts(base, value) && facets.iter().all(|facet| facet.accepts(value))
+            }
+        }
+    }
+
+    // Every name used must be declared, so validation never has to report
+    // a broken schema.
+    fn check(&self) -> Result<(), Error> {
+        for decl in self.elements.values() {
+            self.check_type(&decl.ty)?;
+        }
+        for ty in self.complex_types.values() {
+            self.check_complex(ty)?;
+        }
+        for ty in self.simple_types.values() {
+            self.check_simple(ty)?;
+        }
+        for name in self.simple_types.keys() {
+            self.check_restriction_cycle(name)?;
+        }
+        Ok(())
+    }
+
+    // Follows the bases of a named simple type, which must end at a
+    // built-in type rather than come back round to one already seen.
+    fn check_restriction_cycle(&self, name: &str) -> Result<(), Error> {
+        let mut seen = vec![name];
+        let mut ty = &self.simple_types[name];
+        loop {
+            ty = match ty {
+                SimpleType::Builtin(_) => return Ok(()),
+                SimpleType::Restriction { base, .. } => base,
+                SimpleType::Named(next) if seen.contains(&next.as_str()) => {
+                    return Err(schema_error(&format!(
+                        "simple type `{}` restricts itself",
+                        next
+                    )))
+                }
+                SimpleType::Named(next) => {
+                    seen.push(next);
+                    &self.simple_types[next]
+                }
+            };
+        }
+    }
+
+    fn check_type(&self, ty: &TypeRef) -> Result<(), Error> {
+        match ty {
+            TypeRef::Any => Ok(()),
+            TypeRef::Named(name)
+                if self.complex_types.contains_key(name)
+                    || self.simple_types.contains_key(name) =>
+            {
+                Ok(())
+            }
+            TypeRef::Named(name) => Err(undefined("type", name)),
+            TypeRef::Complex(ty) => self.check_complex(ty),
+            TypeRef::Simple(ty) => self.check_simple(ty),
+        }
+    }
+
+    fn check_complex(&self, ty: &ComplexType) -> Result<(), Error> {
+        for decl in &ty.attributes {
+            self.check_simple(&decl.ty)?;
+        }
+        if let Some(text) = &ty.text {
+            self.check_simple(text)?;
+        }
+        match &ty.content {
+            Some(content) => self.check_particle(content),
+            None => Ok(()),
+        }
+    }
+
+    fn check_particle(&self, particle: &Particle) -> Result<(), Error> {
+        match &particle.term {
+            Term::Element(decl) => self.check_type(&decl.ty),
+            Term::Ref(name) if self.elements.contains_key(name) => Ok(()),
+            Term::Ref(name) => Err(undefined("element", name)),
+            Term::Sequence(particles) | Term::Choice(particles) => particles
+                .iter()
+                .try_for_each(|particle| self.check_particle(particle)),
+        }
+    }
+
+    fn check_simple(&self, ty: &SimpleType) -> Result<(), Error> {
+        match ty {
+            SimpleType::Builtin(_) => Ok(()),
+            SimpleType::Named(name) if self.simple_types.contains_key(name) => Ok(()),
+            SimpleType::Named(name) => Err(undefined("simple type", name)),
+            SimpleType::Restriction { base, .. } => self.check_simple(base),
+        }
+    }
+}
+
+impl FromStr for XsdSchema {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<XsdSchema, Error> {
+        XsdSchema::parse(input)
+    }
+}
+
+// Reads schema components, knowing which prefix the schema namespace is
+// bound to so built-in type names can be told apart from the schema's own.
+struct Loader {
+    xs: String,
+}
+
+impl Loader {
+    fn element(&self, el: &Element) -> Result<ElementDecl, Error> {
+        let name = required(el, "name")?.to_string();
+        let ty = match (el.attribute("type"), children(el).next()) {
+            (Some(ty), _) => self.type_ref(ty)?,
+            (None, Some(child)) if local(child) == "complexType" => {
+                TypeRef::Complex(Box::new(self.complex_type(child)?))
+            }
+            (None, Some(child)) if local(child) == "simpleType" => {
+                TypeRef::Simple(self.simple_type(child)?)
+            }
+            (None, Some(child)) => return Err(unsupported(local(child))),
+            (None, None) => TypeRef::Any,
+        };
+        Ok(ElementDecl { name, ty })
+    }
+
+    fn complex_type(&self, el: &Element) -> Result<ComplexType, Error> {
+        let mut ty = ComplexType {
+            mixed: el.attribute("mixed") == Some("true"),
+            ..ComplexType::default()
+        };
+        for child in children(el) {
+            match local(child) {
+                "sequence" | "choice" if ty.content.is_none() && ty.text.is_none() => {
+                    ty.content = Some(self.particle(child)?);
+                }
+                "attribute" => ty.attributes.push(self.attribute(child)?),
+                "anyAttribute" => ty.any_attributes = true,
+                "simpleContent" if ty.content.is_none() => {
+                    let extension = children(child)
+                        .next()
+                        .filter(|ext| local(ext) == "extension")
+                        .ok_or_else(|| unsupported("simpleContent"))?;
+                    ty.text = Some(self.simple_ref(required(extension, "base")?)?);
+                    for attribute in children(extension) {
+                        match local(attribute) {
+                            "attribute" => ty.attributes.push(self.attribute(attribute)?),
+                            "anyAttribute" => ty.any_attributes = true,
+                            other => return Err(unsupported(other)),
+                        }
+                    }
+                }
+                other => return Err(unsupported(other)),
+            }
+        }
+        Ok(ty)
+    }
+
+    fn particle(&self, el: &Element) -> Result<Particle, Error> {
+        let term = match local(el) {
+            "element" => match el.attribute("ref") {
+                Some(name) => Term::Ref(split_name(name).1.to_string()),
+                None => Term::Element(self.element(el)?),
+            },
+            "sequence" => Term::Sequence(self.particles(el)?),
+            "choice" => Term::Choice(self.particles(el)?),
+            other => return Err(unsupported(other)),
+        };
+        let min = match el.attribute("minOccurs") {
+            Some(min) => number(min)?,
+            None => 1,
+        };
+        let max = match el.attribute("maxOccurs") {
+            Some("unbounded") => None,
+            Some(max) => Some(number(max)?),
+            None => Some(1),
+        };
+        Ok(Particle { term, min, max })
+    }
+
+    fn particles(&self, el: &Element) -> Result<Vec<Particle>, Error> {
+        children(el).map(|child| self.particle(child)).collect()
+    }
+
+    fn attribute(&self, el: &Element) -> Result<AttributeDecl, Error> {
+        let name = required(el, "name")?.to_string();
+        let ty = match (el.attribute("type"), children(el).next()) {
+            (Some(ty), _) => self.simple_ref(ty)?,
+            (None, Some(child)) if local(child) == "simpleType" => self.simple_type(child)?,
+            (None, Some(child)) => return Err(unsupported(local(child))),
+            (None, None) => SimpleType::Builtin(Builtin::String),
+        };
+        let required = match el.attribute("use") {
+            None | Some("optional") => false,
+            Some("required") => true,
+            Some(other) => return Err(unsupported(other)),
+        };
+        Ok(AttributeDecl { name, ty, required })
+    }
+
+    fn simple_type(&self, el: &Element) -> Result<SimpleType, Error> {
+        let restriction = children(el)
+            .next()
+            .ok_or_else(|| schema_error("empty simpleType"))?;
+        if local(restriction) != "restriction" {
+            return Err(unsupported(local(restriction)));
+        }
+        let base = self.simple_ref(required(restriction, "base")?)?;
+        let mut facets = Vec::new();
+        let mut enumeration = Vec::new();
+        for facet in children(restriction) {
+            let value = required(facet, "value")?;
+            facets.push(match local(facet) {
+                "enumeration" => {
+                    enumeration.push(value.to_string());
+                    continue;
+                }
+                "length" => Facet::Length(number(value)?),
+                "minLength" => Facet::MinLength(number(value)?),
+                "maxLength" => Facet::MaxLength(number(value)?),
+                "minInclusive" => Facet::MinInclusive(bound(value)?),
+                "maxInclusive" => Facet::MaxInclusive(bound(value)?),
+                "minExclusive" => Facet::MinExclusive(bound(value)?),
+                "maxExclusive" => Facet::MaxExclusive(bound(value)?),
+                other => return Err(unsupported(other)),
+            });
+        }
+        if !enumeration.is_empty() {
+            facets.push(Facet::Enumeration(enumeration));
+        }
+        Ok(SimpleType::Restriction {
+            base: Box::new(base),
+            facets,
+        })
+    }
+
+    fn type_ref(&self, name: &str) -> Result<TypeRef, Error> {
+        let (prefix, local) = split_name(name);
+        if prefix.unwrap_or("") != self.xs {
+            return Ok(TypeRef::Named(local.to_string()));
+        }
+        match local {
+            "anyType" => Ok(TypeRef::Any),
+            _ => self.simple_ref(name).map(TypeRef::Simple),
+        }
+    }
+
+    fn simple_ref(&self, name: &str) -> Result<SimpleType, Error> {
+        let (prefix, local) = split_name(name);
+        if prefix.unwrap_or("") != self.xs {
+            return Ok(SimpleType::Named(local.to_string()));
+        }
+        let builtin = match local {
+            "string" | "normalizedString" | "token" | "anySimpleType" | "anyURI" | "language"
+            | "Name" | "NCName" | "NMTOKEN" | "ID" | "IDREF" => Builtin::String,
+            "boolean" => Builtin::Boolean,
+            "integer" | "long" | "int" | "short" | "byte" => Builtin::Integer,
+            "nonNegativeInteger" | "unsignedLong" | "unsignedInt" | "unsignedShort"
+            | "unsignedByte" => Builtin::NonNegativeInteger,
+            "positiveInteger" => Builtin::PositiveInteger,
+            "decimal" => Builtin::Decimal,
+            "double" | "float" => Builtin::Double,
+            "date" => Builtin::Date,
+            _ => return Err(unsupported(name)),
+        };
+        Ok(SimpleType::Builtin(builtin))
+    }
+}
+
+impl Builtin {
+    // Everything but strings is compared with surrounding whitespace removed.
+    fn accepts(self, value: &str) -> bool {
+        let value = value.trim();
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let unsigned = value.strip_prefix('+').unwrap_or(value);
+        let signed = value.strip_prefix(['+', '-']).unwrap_or(value);
+        match self {
+            Builtin::String => true,
+            Builtin::Boolean => matches!(value, "true" | "false" | "1" | "0"),
+            Builtin::Integer => digits(signed),
+            Builtin::NonNegativeInteger => digits(unsigned),
+            Builtin::PositiveInteger => digits(unsigned) && unsigned.bytes().any(|b| b != b'0'),
+            Builtin::Decimal => match signed.split_once('.') {
+                Some((whole, fraction)) => {
+                    (whole.is_empty() || digits(whole))
+                        && (fraction.is_empty() || digits(fraction))
+                        && !(whole.is_empty() && fraction.is_empty())
+                }
+                None => digits(signed),
+            },
+            Builtin::Double => {
+                matches!(value, "INF" | "-INF" | "NaN") || value.parse::<f64>().is_ok()
+            }
+            Builtin::Date => {
+                let parts: Vec<&str> = value.split('-').collect();
+                match parts.as_slice() {
+                    [year, month, day] => {
+                        year.len() == 4
+                            && digits(year)
+                            && month.len() == 2
+                            && matches!(month.parse::<u8>(), Ok(1..=12))
+                            && day.len() == 2
+                            && matches!(day.parse::<u8>(), Ok(1..=31))
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+impl Facet {
+    fn accepts(&self, value: &str) -> bool {
+        let number = || value.trim().parse::<f64>().ok();
+        let length = value.chars().count();
+        match self {
+            Facet::Enumeration(values) => values.iter().any(|v| v == value.trim()),
+            Facet::Length(n) => length == *n,
+            Facet::MinLength(n) => length >= *n,
+            Facet::MaxLength(n) => length <= *n,
+            Facet::MinInclusive(bound) => number().is_some_and(|n| n >= *bound),
+            Facet::MaxInclusive(bound) => number().is_some_and(|n| n <= *bound),
+            Facet::MinExclusive(bound) => number().is_some_and(|n| n > *bound),
+            Facet::MaxExclusive(bound) => number().is_some_and(|n| n < *bound),
+        }
+    }
+}
+
+fn local(el: &Element) -> &str {
+    split_name(&el.name).1
+}
+
+// The child elements of a schema component, without its annotations.
+fn children(el: &Element) -> impl Iterator<Item = &Element> {
+    el.children.iter().filter_map(|child| match child {
+        Node::Element(child) if local(child) != "annotation" => Some(child),
+        _ => None,
+    })
+}
+
+fn own_text(el: &Element) -> String {
+    el.children
+        .iter()
+        .filter_map(|child| match child {
+            Node::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Reports the child elements not named in `known`, returning whether any were.
+fn unexpected_children(
+    el: &Element,
+    known: &[&str],
+    error: &mut impl FnMut(ValidationErrorKind),
+) -> bool {
+    let mut found = false;
+    for child in &el.children {
+        match child {
+            Node::Element(child) if !known.contains(&local(child)) => {
+                error(ValidationErrorKind::UnexpectedChild(child.name.clone()));
+                found = true;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+fn merge(positions: &mut Vec<usize>, more: &[usize]) {
+    for position in more {
+        if !positions.contains(position) {
+            positions.push(*position);
+        }
+    }
+}
+
+fn required<'a>(el: &'a Element, name: &str) -> Result<&'a str, Error> {
+    el.attribute(name)
+        .ok_or_else(|| schema_error(&format!("`{}` is missing `{}`", local(el), name)))
+}
+
+fn number(value: &str) -> Result<usize, Error> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| schema_error(&format!("`{}` is not a count", value)))
+}
+
+fn bound(value: &str) -> Result<f64, Error> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| schema_error(&format!("`{}` is not a number", value)))
+}
+
+fn schema_error(message: &str) -> Error {
+    Error::Schema(message.to_string())
+}
+
+fn unsupported(construct: &str) -> Error {
+    schema_error(&format!("`{}` is not supported", construct))
+}
+
+fn undefined(what: &str, name: &str) -> Error {
+    schema_error(&format!("{} `{}` is not defined", what, name))
+}
+
+#[test]
+fn xsd_validation() {
+    let schema: XsdSchema = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+        <xs:element name="order">
+          <xs:complexType>
+            <xs:sequence>
+              <xs:element name="item" type="Item" maxOccurs="unbounded"/>
+              <xs:choice minOccurs="0">
+                <xs:element name="pickup"/>
+                <xs:element name="address" type="xs:string"/>
+              </xs:choice>
+            </xs:sequence>
+            <xs:attribute name="id" type="xs:positiveInteger" use="required"/>
+          </xs:complexType>
+        </xs:element>
+        <xs:complexType name="Item">
+          <xs:simpleContent>
+            <xs:extension base="xs:string">
+              <xs:attribute name="qty" type="Quantity"/>
+            </xs:extension>
+          </xs:simpleContent>
+        </xs:complexType>
+        <xs:simpleType name="Quantity">
+          <xs:restriction base="xs:integer">
+            <xs:minInclusive value="1"/>
+            <xs:maxInclusive value="99"/>
+          </xs:restriction>
+        </xs:simpleType>
+      </xs:schema>"#
+        .parse()
+        .unwrap();
+
+    let ok = Document::parse(
+        "<order id=\"7\"><item qty=\"2\">pen</item><item>ink</item><address>x</address></order>",
+    )
+    .unwrap();
+    assert!(schema.validate(&ok).is_empty());
+
+    let bad = Document::parse(
+        "<order id=\"0\"><pickup/><item qty=\"100\"><b/></item><address/><pickup/></order>",
+    )
+    .unwrap();
+    let errors: Vec<_> = schema
+        .validate(&bad)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(
+        vec![
+            "/order: invalid value `0` for attribute `id`",
+            "/order: child elements do not match the content model",
+            "/order/item[1]: invalid value `100` for attribute `qty`",
+            "/order/item[1]: unexpected child element `b`",
+        ],
+        errors
+    );
+
+    assert_eq!(
+        Err(Error::Schema("type `Missing` is not defined".to_string())),
+        XsdSchema::parse(
+            "<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\
+             <xs:element name=\"a\" type=\"Missing\"/></xs:schema>"
+        )
+        .map(|_| ())
+    );
+    assert_eq!(
+        Err(Error::Schema("`all` is not supported".to_string())),
+        XsdSchema::parse(
+            "<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\
+             <xs:element name=\"a\"><xs:complexType><xs:all/></xs:complexType></xs:element>\
+             </xs:schema>"
+        )
+        .map(|_| ())
+    );
+}
+
+#[test]
+fn xsd_restriction_cycle() {
+    let cycle = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+        <xs:element name="a" type="A"/>
+        <xs:simpleType name="A"><xs:restriction base="B"/></xs:simpleType>
+        <xs:simpleType name="B">
+          <xs:restriction base="A"><xs:maxLength value="3"/></xs:restriction>
+        </xs:simpleType>
+      </xs:schema>"#;
+    assert_eq!(
+        Err(Error::Schema(
+            "simple type `A` restricts itself".to_string()
+        )),
+        XsdSchema::parse(cycle).map(|_| ())
+    );
+    let own = "<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\
+               <xs:simpleType name=\"C\"><xs:restriction base=\"C\"/></xs:simpleType>\
+               </xs:schema>";
+    assert_eq!(
+        Err(Error::Schema(
+            "simple type `C` restricts itself".to_string()
+        )),
+        XsdSchema::parse(own).map(|_| ())
+    );
+}
+
+#[test]
+fn xsd_prefixed_instance() {
+    let schema: XsdSchema = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+          targetNamespace="urn:o">
+        <xs:element name="order">
+          <xs:complexType>
+            <xs:sequence><xs:element name="item" maxOccurs="unbounded"/></xs:sequence>
+          </xs:complexType>
+        </xs:element>
+      </xs:schema>"#
+        .parse()
+        .unwrap();
+    let ok = Document::parse("<o:order xmlns:o=\"urn:o\"><o:item/><o:item/></o:order>").unwrap();
+    assert!(schema.validate(&ok).is_empty());
+    let bad = Document::parse("<o:order xmlns:o=\"urn:o\"><o:other/></o:order>").unwrap();
+    let errors: Vec<_> = schema
+        .validate(&bad)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    assert_eq!(vec!["/o:order: unexpected child element `o:other`"], errors);
+}